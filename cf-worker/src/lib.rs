@@ -13,26 +13,155 @@ cfg_if! {
     }
 }
 
+/// Renders the screenplay as HTML. Every function takes an optional profile, "spec" or
+/// "lenient", for the rules to parse the screenplay by (see `fountain::options::ParseOptions`).
 #[wasm_bindgen]
-pub fn parse(screenplay: &str) -> String {
+pub fn parse(screenplay: &str, profile: Option<String>) -> String {
+    utils::set_panic_hook();
     println!("Parsing the following Fountain doc:\n{}", screenplay);
 
     // Write to String buffer.
-    match fountain::parse_document::<(&str, _)>(screenplay) {
+    let options = parse_options(profile);
+    match fountain::parse_document_with_options::<(&str, _)>(screenplay, &options) {
         Err(e) => format!(
             "\
 <h1 class='fountain-parse-error-title'>Error</h1>
 <p class='fountain-parse-error-body'>{:?}</p>",
             e
         ),
-        Ok(("", parsed)) => parsed.as_html(),
-        Ok((unparsed, parsed)) => format!(
-            "\
+        Ok((unparsed, parsed)) => render(&parsed, unparsed),
+    }
+}
+
+/// The options for the profile, or the default ones if there's no such profile.
+fn parse_options(profile: Option<String>) -> fountain::options::ParseOptions {
+    profile
+        .as_deref()
+        .and_then(fountain::options::ParseOptions::for_profile)
+        .unwrap_or_default()
+}
+
+fn render(parsed: &fountain::data::Document, unparsed: &str) -> String {
+    if unparsed.is_empty() {
+        return parsed.as_html();
+    }
+    format!(
+        "\
 <h1 class='fountain-unparsed-title'>Unparsed</h1>
 <p class='fountain-unparsed-body'>'{}'</p>
 {}",
-            unparsed,
-            parsed.as_html()
-        ),
+        unparsed,
+        parsed.as_html()
+    )
+}
+
+/// A screenplay being edited in the browser. Each edit only reparses the part of the script it
+/// touched, so it keeps up with typing in feature-length scripts, unlike calling `parse` on every
+/// keystroke.
+#[wasm_bindgen]
+pub struct Screenplay {
+    parsed: fountain::incremental::Parsed,
+}
+
+#[wasm_bindgen]
+impl Screenplay {
+    #[wasm_bindgen(constructor)]
+    pub fn new(screenplay: &str, profile: Option<String>) -> Screenplay {
+        utils::set_panic_hook();
+        Screenplay {
+            parsed: fountain::incremental::Parsed::with_options(screenplay, parse_options(profile)),
+        }
+    }
+
+    /// Replaces the text from `start` to `end` with `text`. Offsets count UTF-16 code units, like
+    /// JavaScript string indices. Returns which lines changed as JSON, e.g.
+    /// `{"start": 12, "removed": 1, "inserted": 2, "titlepage": false}`.
+    pub fn edit(&mut self, start: usize, end: usize, text: &str) -> String {
+        let current = self.parsed.text();
+        let edit = match (byte_offset(current, start), byte_offset(current, end)) {
+            (Some(start), Some(end)) => fountain::incremental::TextEdit::new(start, end, text),
+            _ => return serde_json::json!({ "error": "The edit is outside the text" }).to_string(),
+        };
+        match self.parsed.edit(&edit) {
+            Ok(change) => serde_json::json!({
+                "start": change.start,
+                "removed": change.removed,
+                "inserted": change.inserted,
+                "titlepage": change.titlepage,
+            })
+            .to_string(),
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        }
     }
+
+    /// The screenplay as HTML, like `parse` returns.
+    pub fn html(&self) -> String {
+        render(self.parsed.document(), self.parsed.unparsed())
+    }
+}
+
+/// Converts an offset in UTF-16 code units into a byte offset, if it's within the text.
+fn byte_offset(text: &str, utf16: usize) -> Option<usize> {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units == utf16 {
+            return Some(i);
+        }
+        units += c.len_utf16();
+    }
+    (units == utf16).then_some(text.len())
+}
+
+/// Estimates the screenplay's page count and runtime, returned as JSON.
+/// See `fountain::runtime::Estimate` for the fields.
+#[wasm_bindgen]
+pub fn stats(screenplay: &str, profile: Option<String>) -> String {
+    utils::set_panic_hook();
+    let options = parse_options(profile);
+    match fountain::parse_document_with_options::<(&str, _)>(screenplay, &options) {
+        Err(e) => serde_json::json!({ "error": format!("{:?}", e) }).to_string(),
+        Ok((_, parsed)) => serde_json::to_string(&parsed.estimate())
+            .unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }).to_string()),
+    }
+}
+
+/// Parses the screenplay into the versioned JSON format described in `fountain::schema`.
+#[wasm_bindgen]
+pub fn to_json(screenplay: &str, profile: Option<String>) -> String {
+    utils::set_panic_hook();
+    let options = parse_options(profile);
+    match fountain::parse_document_with_options::<(&str, _)>(screenplay, &options) {
+        Err(e) => serde_json::json!({ "error": format!("{:?}", e) }).to_string(),
+        Ok((_, parsed)) => serde_json::to_string(&fountain::schema::Versioned::from(parsed))
+            .unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }).to_string()),
+    }
+}
+
+/// Splits the screenplay into tokens for syntax highlighting, returned as a JSON array of
+/// `{"kind": "scene-heading", "start": 0, "end": 9}` objects. See `fountain::tokens::TokenKind`
+/// for the kinds. Unlike the library's byte offsets, `start` and `end` count UTF-16 code units, so
+/// they can index JavaScript (and Elm) strings directly.
+#[wasm_bindgen]
+pub fn tokens(screenplay: &str, profile: Option<String>) -> String {
+    utils::set_panic_hook();
+    // Tokens are in order, so offsets can be converted in one pass over the text.
+    let mut utf16 = 0;
+    let mut byte = 0;
+    let mut to_utf16 = |offset: usize| {
+        utf16 += screenplay[byte..offset].encode_utf16().count();
+        byte = offset;
+        utf16
+    };
+    let options = parse_options(profile);
+    let tokens: Vec<_> = fountain::tokens::tokenize_with_options(screenplay, &options)
+        .into_iter()
+        .map(|token| {
+            serde_json::json!({
+                "kind": token.kind,
+                "start": to_utf16(token.span.start),
+                "end": to_utf16(token.span.end),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(tokens).to_string()
 }
@@ -16,6 +16,8 @@ async function handleRequest(request) {
   }
   let j = await request.json();
   const screenplay = j.screenplay
+  // An optional 'profile' field, "spec" or "lenient", sets the rules to parse it by.
+  const profile = j.profile
   if (screenplay === null || screenplay === undefined || screenplay == "") {
     return new Response(
       `Body must contain a 'screenplay' field and it cannot be ${screenplay}`,
@@ -24,9 +26,21 @@ async function handleRequest(request) {
   }
 
   // Respond
-  const { parse } = wasm_bindgen;
+  const { parse, stats, to_json, tokens } = wasm_bindgen;
   await wasm_bindgen(wasm)
-  const output = parse(screenplay)
+  // POSTs to /stats get a JSON runtime estimate, POSTs to /json get the parsed document as
+  // versioned JSON, POSTs to /tokens get tokens for syntax highlighting, and everything else gets
+  // HTML.
+  const pathname = new URL(request.url).pathname
+  const jsonRoutes = { '/stats': stats, '/json': to_json, '/tokens': tokens }
+  const route = Object.keys(jsonRoutes).find(path => pathname.endsWith(path))
+  if (route !== undefined) {
+    const output = jsonRoutes[route](screenplay, profile)
+    let res = new Response(output, { status: 200 })
+    res.headers.set("Content-type", "application/json")
+    return res
+  }
+  const output = parse(screenplay, profile)
   let res = new Response(output, { status: 200 })
   res.headers.set("Content-type", "text/html")
   return res
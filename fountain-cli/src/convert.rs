@@ -0,0 +1,73 @@
+use crate::error::FountainError;
+use crate::output::Output;
+use fountain::data::Document;
+use fountain::schema::{Header, Versioned};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// What to convert from
+    #[arg(long, value_enum, default_value_t = InputFormat::Fountain)]
+    from: InputFormat,
+    /// What to convert to
+    #[arg(short, long, visible_alias = "to", value_enum)]
+    format: ConvertFormat,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum InputFormat {
+    /// A Fountain file or project manifest
+    Fountain,
+    /// JSON written by `fountain convert --to json`
+    Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ConvertFormat {
+    /// A web page, like `fountain render`
+    Html,
+    /// A single Fountain file, with any includes resolved
+    Fountain,
+    /// The parsed document, in the versioned format described by `fountain schema`
+    Json,
+}
+
+/// Runs `fountain convert`.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let extension = match args.format {
+        ConvertFormat::Html => "html",
+        ConvertFormat::Fountain => "fountain",
+        ConvertFormat::Json => "json",
+    };
+    args.output.write_each(&args.files, extension, |path| {
+        let doc = match args.from {
+            InputFormat::Fountain => crate::parse_file(path)?,
+            InputFormat::Json => read_json(path)?,
+        };
+        Ok(match args.format {
+            ConvertFormat::Html => crate::in_html(&doc.as_html()),
+            ConvertFormat::Fountain => doc.as_fountain(),
+            ConvertFormat::Json => serde_json::to_string_pretty(&Versioned::from(doc))?,
+        })
+    })
+}
+
+/// Runs `fountain schema`, which prints the JSON Schema for `fountain convert --to json`.
+pub fn schema(output: Output) -> Result<(), FountainError> {
+    output.write(&serde_json::to_string_pretty(
+        &fountain::schema::json_schema(),
+    )?)
+}
+
+// Read a document written by `fountain convert --to json`, checking its version is one we know
+// before reading the rest.
+fn read_json(path: &str) -> Result<Document, FountainError> {
+    let json: serde_json::Value = serde_json::from_str(&crate::read(path)?)?;
+    let header: Header = serde_json::from_value(json.clone())?;
+    header.check()?;
+    let versioned: Versioned = serde_json::from_value(json)?;
+    Ok(versioned.into_document()?)
+}
@@ -0,0 +1,181 @@
+use crate::error::FountainError;
+use crate::format::escape;
+use crate::output::Output;
+use fountain::data::Document;
+use fountain::diff::{diff, DocumentDiff, LineEdit, SceneDiff, SceneStatus};
+use std::collections::BTreeMap;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(short, long, value_enum, default_value_t)]
+    format: DiffFormat,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "OLDPATH")]
+    old: String,
+    #[arg(value_name = "NEWPATH")]
+    new: String,
+}
+
+/// How a diff should be printed.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum DiffFormat {
+    #[default]
+    Text,
+    /// A side-by-side view
+    Html,
+    Json,
+}
+
+/// Runs `fountain diff`.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let old = crate::parse_file(&args.old)?;
+    let new = crate::parse_file(&args.new)?;
+    let diff = diff(&old, &new);
+    let output = match args.format {
+        DiffFormat::Text => text(&old, &new, &diff),
+        DiffFormat::Html => html(&old, &new, &diff),
+        DiffFormat::Json => serde_json::to_string_pretty(&diff)?,
+    };
+    args.output.write(&output)
+}
+
+fn symbol(status: SceneStatus) -> &'static str {
+    match status {
+        SceneStatus::Unchanged => " ",
+        SceneStatus::Changed => "~",
+        SceneStatus::Moved => ">",
+        SceneStatus::Added => "+",
+        SceneStatus::Removed => "-",
+    }
+}
+
+/// e.g. "Scene 3 (was 2): INT. MESS - NIGHT"
+fn describe(scene: &SceneDiff) -> String {
+    let heading = &scene.heading;
+    match (scene.old, scene.new) {
+        (Some(0), _) | (_, Some(0)) => "(before the first scene)".to_owned(),
+        (Some(old), Some(new)) if old != new => format!("Scene {} (was {}): {}", new, old, heading),
+        (_, Some(n)) | (Some(n), None) => format!("Scene {}: {}", n, heading),
+        (None, None) => heading.to_owned(),
+    }
+}
+
+/// A summary of every changed scene, with the lines which changed, followed by the dialogue
+/// changes for each speaker.
+fn text(old: &Document, new: &Document, diff: &DocumentDiff) -> String {
+    if diff.is_unchanged() {
+        return "No changes.".to_owned();
+    }
+    let mut out = Vec::new();
+    for scene in diff
+        .scenes
+        .iter()
+        .filter(|s| s.status != SceneStatus::Unchanged)
+    {
+        out.push(format!("{} {}", symbol(scene.status), describe(scene)));
+        if matches!(scene.status, SceneStatus::Changed | SceneStatus::Moved) {
+            for edit in &scene.edits {
+                match *edit {
+                    LineEdit::Delete { old: i } => {
+                        out.push(format!("    - {}", old.lines[i].text()))
+                    }
+                    LineEdit::Insert { new: i } => {
+                        out.push(format!("    + {}", new.lines[i].text()))
+                    }
+                    LineEdit::Equal { .. } => {}
+                }
+            }
+        }
+    }
+
+    // (added, removed, reworded) speeches per speaker.
+    let mut speakers: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for change in diff.scenes.iter().flat_map(|s| &s.dialogue) {
+        let counts = speakers.entry(&change.speaker).or_default();
+        match (&change.old, &change.new) {
+            (None, _) => counts.0 += 1,
+            (_, None) => counts.1 += 1,
+            _ => counts.2 += 1,
+        }
+    }
+    if !speakers.is_empty() {
+        out.push(String::new());
+        out.push("Dialogue changes:".to_owned());
+        for (speaker, (added, removed, reworded)) in speakers {
+            out.push(format!(
+                "  {}: {} added, {} removed, {} reworded",
+                speaker, added, removed, reworded
+            ));
+        }
+    }
+    out.join("\n")
+}
+
+/// An HTML page showing the old and new drafts side by side, scene by scene.
+fn html(old: &Document, new: &Document, diff: &DocumentDiff) -> String {
+    let mut rows = Vec::new();
+    for scene in &diff.scenes {
+        let status = format!("{:?}", scene.status).to_lowercase();
+        rows.push(format!(
+            "<tr class='scene {}'><th colspan='2'>{} {}</th></tr>",
+            status,
+            symbol(scene.status),
+            escape(&describe(scene))
+        ));
+        let cell = |doc: &Document, i: usize, class: &str| {
+            format!("<td class='{}'>{}</td>", class, escape(doc.lines[i].text()))
+        };
+        // Deleted and inserted lines between two unchanged lines are shown next to each other.
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        let flush =
+            |rows: &mut Vec<String>, deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+                for row in 0..deleted.len().max(inserted.len()) {
+                    let left = deleted
+                        .get(row)
+                        .map_or_else(|| "<td></td>".to_owned(), |&i| cell(old, i, "removed"));
+                    let right = inserted
+                        .get(row)
+                        .map_or_else(|| "<td></td>".to_owned(), |&i| cell(new, i, "added"));
+                    rows.push(format!("<tr>{}{}</tr>", left, right));
+                }
+                deleted.clear();
+                inserted.clear();
+            };
+        for edit in &scene.edits {
+            match *edit {
+                LineEdit::Delete { old: i } => deleted.push(i),
+                LineEdit::Insert { new: i } => inserted.push(i),
+                LineEdit::Equal { old: o, new: n } => {
+                    flush(&mut rows, &mut deleted, &mut inserted);
+                    rows.push(format!(
+                        "<tr>{}{}</tr>",
+                        cell(old, o, "equal"),
+                        cell(new, n, "equal")
+                    ));
+                }
+            }
+        }
+        flush(&mut rows, &mut deleted, &mut inserted);
+    }
+    format!(
+        "
+<html>
+    <head>
+        <style>
+{}
+        </style>
+    </head>
+    <body>
+<table class='diff'>
+<tr><th>Old</th><th>New</th></tr>
+{}
+</table>
+    </body>
+</html>
+",
+        include_str!("diff.css"),
+        rows.join("\n"),
+    )
+}
@@ -1,13 +1,72 @@
+use std::fmt;
 use std::io;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum FountainError {
-    ParseError(String),
     IOError(io::Error),
+    JSONError(serde_json::Error),
+    UsageError(String),
+    ConfigError(String),
+    LintError(usize),
+    ProjectError(fountain::project::ProjectError),
+    SchemaError(fountain::schema::SchemaError),
+    /// How many files `fountain fmt --check` found which weren't formatted.
+    FormatError(usize),
+    /// A document couldn't be exported.
+    ExportError(String),
 }
 
+impl fmt::Display for FountainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FountainError::IOError(e) => write!(f, "I/O error: {}", e),
+            FountainError::JSONError(e) => write!(f, "JSON error: {}", e),
+            FountainError::UsageError(usage) => write!(f, "{}", usage),
+            FountainError::ConfigError(e) => write!(f, "Invalid config file {}", e),
+            FountainError::LintError(n) => write!(f, "Found {} problem(s)", n),
+            FountainError::ProjectError(e) => write!(f, "{}", e),
+            FountainError::SchemaError(e) => write!(f, "{}", e),
+            FountainError::FormatError(n) => write!(f, "{} file(s) need formatting", n),
+            FountainError::ExportError(e) => write!(f, "Couldn't export: {}", e),
+        }
+    }
+}
+
+impl FountainError {
+    /// The process exit code for this error: 2 for usage errors, 3 if the input has problems
+    /// (see `EXIT_WARNINGS`), and 1 for everything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FountainError::UsageError(_) => 2,
+            FountainError::LintError(_) | FountainError::FormatError(_) => crate::EXIT_WARNINGS,
+            _ => 1,
+        }
+    }
+}
+
+impl std::error::Error for FountainError {}
+
 impl From<io::Error> for FountainError {
     fn from(err: io::Error) -> FountainError {
         FountainError::IOError(err)
     }
 }
+
+impl From<serde_json::Error> for FountainError {
+    fn from(err: serde_json::Error) -> FountainError {
+        FountainError::JSONError(err)
+    }
+}
+
+impl From<fountain::project::ProjectError> for FountainError {
+    fn from(err: fountain::project::ProjectError) -> FountainError {
+        FountainError::ProjectError(err)
+    }
+}
+
+impl From<fountain::schema::SchemaError> for FountainError {
+    fn from(err: fountain::schema::SchemaError) -> FountainError {
+        FountainError::SchemaError(err)
+    }
+}
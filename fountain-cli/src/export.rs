@@ -0,0 +1,257 @@
+use crate::error::FountainError;
+use crate::format::{self, escape};
+use crate::output::Output;
+use fountain::breakdown::SceneBreakdown;
+use fountain::data::Document;
+use std::collections::HashMap;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    export: Export,
+}
+
+#[derive(clap::Subcommand)]
+enum Export {
+    /// A stripboard CSV, with one strip per scene, for scheduling software
+    Stripboard(Files),
+    /// HTML breakdown sheets, one per scene
+    Breakdown(Files),
+}
+
+#[derive(clap::Args)]
+struct Files {
+    /// Use the scene and page numbers of a locked draft, or a lock file
+    #[arg(long, value_name = "BASELINEPATH")]
+    locked: Option<String>,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+type Exporter = fn(&Document, &[SceneBreakdown]) -> Result<String, FountainError>;
+
+/// Runs `fountain export`.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let (export, files, extension): (Exporter, _, _) = match &args.export {
+        Export::Stripboard(files) => (stripboard, files, "csv"),
+        Export::Breakdown(files) => (breakdown_sheets, files, "html"),
+    };
+    let lock = crate::lock::load_baseline(files.locked.as_deref())?;
+    let words = &crate::parse_options().vocabulary;
+    files.output.write_each(&files.files, extension, |path| {
+        let doc = crate::parse_file(path)?;
+        let breakdown = match &lock {
+            Some(lock) => doc.locked_scene_breakdown_with(lock, words),
+            None => doc.scene_breakdown_with(words),
+        };
+        export(&doc, &breakdown)
+    })
+}
+
+/// Numbers the cast the way a production does: the character with the most speeches is 1, the
+/// next is 2, and so on.
+fn cast_ids(doc: &Document) -> HashMap<String, usize> {
+    doc.characters()
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| (c.name, i + 1))
+        .collect()
+}
+
+fn cast_id(ids: &HashMap<String, usize>, name: &str) -> Result<usize, FountainError> {
+    ids.get(name).copied().ok_or_else(|| {
+        FountainError::ExportError(format!("{} speaks in a scene but isn't in the cast", name))
+    })
+}
+
+fn location(scene: &SceneBreakdown) -> String {
+    match &scene.heading.sub_location {
+        Some(sub) => format!("{} - {}", scene.heading.location, sub),
+        None => scene.heading.location.clone(),
+    }
+}
+
+/// A stripboard CSV, with one strip (row) per scene, for importing into scheduling software.
+fn stripboard(doc: &Document, breakdown: &[SceneBreakdown]) -> Result<String, FountainError> {
+    let ids = cast_ids(doc);
+    let header = [
+        "scene",
+        "int_ext",
+        "day_night",
+        "location",
+        "page",
+        "pages",
+        "cast_ids",
+        "cast",
+    ];
+    let rows = breakdown
+        .iter()
+        .map(|scene| {
+            let cast_ids = scene
+                .characters
+                .iter()
+                .map(|name| cast_id(&ids, name).map(|id| id.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(vec![
+                scene.number.clone(),
+                scene
+                    .heading
+                    .setting
+                    .map_or_else(String::new, |s| s.to_string()),
+                scene.heading.time.clone().unwrap_or_default(),
+                location(scene),
+                scene.page.to_string(),
+                scene.length.to_string(),
+                cast_ids.join(" "),
+                scene.characters.join(", "),
+            ])
+        })
+        .collect::<Result<Vec<_>, FountainError>>()?;
+    Ok(format::csv(&header, &rows))
+}
+
+/// An HTML document with one breakdown sheet per scene, each printed on its own page.
+fn breakdown_sheets(doc: &Document, breakdown: &[SceneBreakdown]) -> Result<String, FountainError> {
+    let ids = cast_ids(doc);
+    let title = escape(doc.titlepage.title.as_deref().unwrap_or("Untitled"));
+    let sheets = breakdown
+        .iter()
+        .map(|scene| {
+            let cast = scene
+                .characters
+                .iter()
+                .map(|name| {
+                    Ok(format!(
+                        "<li>{}. {}</li>",
+                        cast_id(&ids, name)?,
+                        escape(name)
+                    ))
+                })
+                .collect::<Result<Vec<_>, FountainError>>()?;
+            Ok(format!(
+                "\
+<div class='breakdown-sheet'>
+<h1>{title}</h1>
+<h2>Scene {number}</h2>
+<table>
+<tr><th>Int/Ext</th><td>{setting}</td><th>Day/Night</th><td>{time}</td></tr>
+<tr><th>Location</th><td colspan='3'>{location}</td></tr>
+<tr><th>Page</th><td>{page}</td><th>Pages</th><td>{length}</td></tr>
+</table>
+<h3>Cast Members</h3>
+<ul class='cast'>
+{cast}
+</ul>
+</div>",
+                title = title,
+                number = escape(&scene.number),
+                setting = scene
+                    .heading
+                    .setting
+                    .map_or_else(String::new, |s| s.to_string()),
+                time = escape(scene.heading.time.as_deref().unwrap_or("")),
+                location = escape(&location(scene)),
+                page = scene.page,
+                length = scene.length,
+                cast = cast.join("\n"),
+            ))
+        })
+        .collect::<Result<Vec<_>, FountainError>>()?;
+    Ok(format!(
+        "
+<html>
+    <head>
+        <style>
+{}
+        </style>
+    </head>
+    <body>
+{}
+    </body>
+</html>
+",
+        include_str!("breakdown.css"),
+        sheets.join("\n"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(text: &str) -> Document {
+        fountain::parse_document::<(&str, _)>(text).unwrap().1
+    }
+
+    #[test]
+    fn test_cast_ids() {
+        let doc = doc("\
+INT. KITCHEN - DAY
+
+BRICK
+Hi.
+
+STEEL
+Hey.
+
+BRICK
+Bye.
+");
+        let ids = cast_ids(&doc);
+        assert_eq!(ids.get("BRICK"), Some(&1));
+        assert_eq!(ids.get("STEEL"), Some(&2));
+        assert!(matches!(
+            cast_id(&ids, "NOBODY"),
+            Err(FountainError::ExportError(_))
+        ));
+    }
+
+    #[test]
+    fn test_stripboard_quotes_cells() {
+        let doc = doc("\
+EXT. \"THE PIT\", OUTSIDE TOWN - NIGHT
+
+BRICK
+Hi.
+
+STEEL
+Hey.
+");
+        let csv = stripboard(&doc, &doc.scene_breakdown()).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "scene,int_ext,day_night,location,page,pages,cast_ids,cast"
+        );
+        assert!(
+            lines[1].contains(",\"\"\"THE PIT\"\", OUTSIDE TOWN\","),
+            "{}",
+            lines[1]
+        );
+        assert!(lines[1].ends_with(",1 2,\"BRICK, STEEL\""), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_breakdown_sheets_number_cast() {
+        let doc = doc("\
+INT. KITCHEN - DAY
+
+STEEL
+Hey.
+
+BRICK
+Hi.
+
+BRICK
+Bye.
+");
+        let html = breakdown_sheets(&doc, &doc.scene_breakdown()).unwrap();
+        assert!(
+            html.contains("<li>2. STEEL</li>\n<li>1. BRICK</li>"),
+            "{}",
+            html
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error::FountainError;
+use std::fs;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Don't change any files, just list the ones which aren't formatted
+    #[arg(long)]
+    check: bool,
+    /// Files to rewrite in place. Use - to format stdin to stdout
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+/// Runs `fountain fmt`. Rewrites each file the way `Document::as_fountain` writes it. Included
+/// files aren't inlined, since each file is formatted on its own.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let mut unformatted = 0;
+    for path in &args.files {
+        let text = crate::read(path)?;
+        let doc =
+            match fountain::parse_document_with_options::<(&str, _)>(&text, crate::parse_options())
+            {
+                Ok(("", doc)) => doc,
+                // Formatting would lose whatever the parser couldn't understand.
+                Ok((unparsed, _)) => {
+                    let position = fountain::source::position(&text, text.len() - unparsed.len());
+                    crate::warn(&format!(
+                        "Not formatting {}: parsing stopped at line {}",
+                        path, position.line
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    crate::warn(&format!("Not formatting {}: {:?}", path, e));
+                    continue;
+                }
+            };
+        let formatted = doc.as_fountain();
+        // Don't write anything which wouldn't be read back as the same document.
+        match fountain::parse_document_with_options::<(&str, _)>(&formatted, crate::parse_options())
+        {
+            Ok(("", reparsed)) if reparsed == doc => {}
+            _ => {
+                crate::warn(&format!(
+                    "Not formatting {}: the formatted text would parse differently",
+                    path
+                ));
+                continue;
+            }
+        }
+        if args.check {
+            if formatted != text {
+                println!("{}", path);
+                unformatted += 1;
+            }
+        } else if path == "-" {
+            print!("{}", formatted);
+        } else if formatted != text {
+            fs::write(path, formatted)?;
+            eprintln!("Formatted {}", path);
+        }
+    }
+    if unformatted > 0 {
+        return Err(FountainError::FormatError(unformatted));
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use crate::error::FountainError;
+use crate::format::{self, Format};
+use crate::output::Output;
+use fountain::lint::{lint, Diagnostic, LintConfig, Rule};
+use std::path::{Path, PathBuf};
+
+/// Config file which is used if no --config option is given, and it exists.
+const DEFAULT_CONFIG: &str = ".fountainlint.toml";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Read rules from this TOML file instead of .fountainlint.toml
+    #[arg(long, value_name = "CONFIGPATH")]
+    config: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+/// Runs `fountain lint`. Checks every file, including any files they include, and fails if any of
+/// them have problems.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let config = match &args.config {
+        Some(path) => read_config(path)?,
+        None => default_config()?,
+    };
+    let mut diagnostics: Vec<(String, Diagnostic)> = Vec::new();
+    for path in &args.files {
+        let project = crate::project::load(path)?;
+        for file in &project.files {
+            let path = file.path.display().to_string();
+            diagnostics.extend(
+                lint(&file.text, &config)
+                    .into_iter()
+                    .map(|d| (path.clone(), d)),
+            );
+        }
+    }
+    let output = match args.format {
+        Format::Json => {
+            let diagnostics: Vec<_> = diagnostics
+                .iter()
+                .map(|(path, d)| {
+                    let mut json = serde_json::to_value(d)?;
+                    json["file"] = path.as_str().into();
+                    Ok(json)
+                })
+                .collect::<Result<_, serde_json::Error>>()?;
+            serde_json::to_string_pretty(&diagnostics)?
+        }
+        Format::Table => diagnostics
+            .iter()
+            .map(|(path, d)| describe(path, d))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Csv => {
+            let header = ["file", "line", "column", "severity", "rule", "message"];
+            let rows: Vec<_> = diagnostics
+                .iter()
+                .map(|(path, d)| {
+                    vec![
+                        path.clone(),
+                        d.position.line.to_string(),
+                        d.position.column.to_string(),
+                        d.severity.to_string(),
+                        d.rule.to_string(),
+                        d.message.clone(),
+                    ]
+                })
+                .collect();
+            format::csv(&header, &rows)
+        }
+    };
+    args.output.write(&output)?;
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(FountainError::LintError(diagnostics.len()))
+    }
+}
+
+/// The config in `.fountainlint.toml`, if that file exists, otherwise the default config.
+pub fn default_config() -> Result<LintConfig, FountainError> {
+    if Path::new(DEFAULT_CONFIG).exists() {
+        read_config(Path::new(DEFAULT_CONFIG))
+    } else {
+        Ok(defaults())
+    }
+}
+
+/// The default config, parsing with the global options.
+pub fn defaults() -> LintConfig {
+    let mut config = LintConfig::default();
+    config.parse_options = crate::parse_options().clone();
+    config
+}
+
+/// e.g. "alien.fountain:12:1: warning[missing-time-of-day]: Scene Heading has no time of day"
+pub fn describe(path: &str, d: &Diagnostic) -> String {
+    format!(
+        "{}:{}:{}: {}[{}]: {}",
+        path, d.position.line, d.position.column, d.severity, d.rule, d.message
+    )
+}
+
+/// Reads a TOML lint config file, e.g.
+/// ```toml
+/// max-parenthetical-length = 30
+///
+/// [rules]
+/// missing-time-of-day = false
+/// ```
+fn read_config(path: &Path) -> Result<LintConfig, FountainError> {
+    let err = |msg: String| FountainError::ConfigError(format!("{}: {}", path.display(), msg));
+    let text = std::fs::read_to_string(path)?;
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| err(e.to_string()))?;
+    let mut config = defaults();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("max-parenthetical-length", toml::Value::Integer(n)) if n >= 0 => {
+                config.max_parenthetical_length = n as usize;
+            }
+            ("rules", toml::Value::Table(rules)) => {
+                for (rule, enabled) in rules {
+                    let rule: Rule = rule.parse().map_err(err)?;
+                    match enabled {
+                        toml::Value::Boolean(enabled) => config.set_enabled(rule, enabled),
+                        _ => return Err(err(format!("rules.{} must be true or false", rule))),
+                    }
+                }
+            }
+            (key, _) => return Err(err(format!("unexpected setting '{}'", key))),
+        }
+    }
+    Ok(config)
+}
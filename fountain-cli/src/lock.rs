@@ -0,0 +1,38 @@
+use crate::error::FountainError;
+use crate::output::Output;
+use fountain::lock::Lock;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Keep the numbers of a previous lock, giving new scenes and pages A-numbers
+    #[arg(long, value_name = "BASELINEPATH")]
+    locked: Option<String>,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH")]
+    file: String,
+}
+
+/// Runs `fountain lock`. Prints the document's locked scene and page numbers as JSON, which can be
+/// passed to `--locked` in later revisions.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let baseline = load_baseline(args.locked.as_deref())?;
+    let doc = crate::parse_file(&args.file)?;
+    let lock = match baseline {
+        Some(baseline) => doc.relock(&baseline),
+        None => doc.lock(),
+    };
+    args.output.write(&serde_json::to_string_pretty(&lock)?)
+}
+
+/// Reads the baseline given by a `--locked BASELINEPATH` option, if there was one. The baseline is
+/// either a lock file written by `fountain lock`, or the locked draft itself.
+pub fn load_baseline(path: Option<&str>) -> Result<Option<Lock>, FountainError> {
+    match path {
+        None => Ok(None),
+        Some(path) if path.ends_with(".json") => {
+            Ok(Some(serde_json::from_str(&crate::read(path)?)?))
+        }
+        Some(path) => Ok(Some(crate::parse_file(path)?.lock())),
+    }
+}
@@ -1,38 +1,223 @@
+mod convert;
+mod diff;
 mod error;
+mod export;
+mod fmt;
+mod format;
+mod lint;
+mod lock;
+mod output;
+mod project;
+mod query;
+mod render;
+mod serve;
+mod sides;
+mod stats;
+mod watch;
 
+use clap::{CommandFactory, Parser, Subcommand};
 use error::FountainError;
+use fountain::options::ParseOptions;
+use fountain::scenes::Vocabulary;
 use std::env;
+use std::ffi::OsString;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
 
 const ERR_UNPARSED: &str = "Parsing stopped before the document ended. Check the formatting of the following section. Unparsed text";
 
-fn main() -> Result<(), FountainError> {
-    let args: Vec<String> = env::args().collect();
-    if let Some(path) = args.get(1) {
-        println!("{}", in_html(&fountain_to_html(path)?))
-    } else {
-        eprintln!("Missing FILEPATH arg");
-        eprintln!("usage: $ fountain FILEPATH");
+/// Exit code when a command finished, but warned about its input, e.g. text the parser couldn't
+/// understand, or lint problems.
+const EXIT_WARNINGS: i32 = 3;
+
+/// How many warnings have been printed, so the exit code can reflect them.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// How every command parses its input, set from the global options.
+static PARSE_OPTIONS: OnceLock<ParseOptions> = OnceLock::new();
+
+/// Parse Fountain screenplays, and render, check or report on them.
+///
+/// Every FILEPATH can be a .fountain file, a TOML project manifest, or - to read from stdin.
+///
+/// Exit codes: 0 on success, 1 on errors, 2 on usage errors, and 3 if the command finished but
+/// found problems in its input (unparsed text, lint problems or unformatted files).
+#[derive(Parser)]
+#[command(name = "fountain", version, arg_required_else_help = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// The language Scene Headings are written in, e.g. INNEN. KÜCHE - NACHT is German
+    #[arg(long, global = true, value_enum, default_value_t)]
+    language: Language,
+    /// Which rules to parse by: those of the Fountain spec, or looser ones for scripts which bend
+    /// them. Without it, Scene Headings must be uppercase and Transitions needn't be set apart
+    #[arg(long, global = true, value_enum)]
+    profile: Option<Profile>,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    fn vocabulary(self) -> Vocabulary {
+        match self {
+            Language::English => Vocabulary::english(),
+            Language::German => Vocabulary::german(),
+            Language::French => Vocabulary::french(),
+            Language::Spanish => Vocabulary::spanish(),
+        }
     }
-    Ok(())
 }
 
-// Parse the .fountain file at the given filepath
-fn fountain_to_html(filepath: &str) -> Result<fountain::data::Document, FountainError> {
-    let text = read(filepath)?;
-    match fountain::parse_document::<(&str, _)>(&text) {
-        Err(e) => Err(FountainError::ParseError(format!("{:?}", e))),
-        Ok(("", parsed)) => Ok(parsed),
-        Ok((unparsed, parsed)) => {
-            eprintln!("{}: {}", ERR_UNPARSED, unparsed);
-            Ok(parsed)
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Profile {
+    Spec,
+    Lenient,
+}
+
+impl Profile {
+    fn options(self) -> ParseOptions {
+        match self {
+            Profile::Spec => ParseOptions::spec(),
+            Profile::Lenient => ParseOptions::lenient(),
         }
     }
 }
 
-fn in_html(parsed: &fountain::data::Document) -> String {
+#[derive(Subcommand)]
+enum Command {
+    /// Render screenplays as HTML. This is the default, so `fountain FILEPATH` works too
+    Render(render::Args),
+    /// Convert screenplays to HTML, Fountain (with includes resolved) or JSON
+    Convert(convert::Args),
+    /// Print the JSON Schema for `fountain convert --to json`
+    Schema(output::Output),
+    /// Rewrite Fountain files in a consistent style
+    Fmt(fmt::Args),
+    /// Print runtime, cast or scene reports
+    Stats(stats::Args),
+    /// Check screenplays for common mistakes
+    Lint(lint::Args),
+    /// Compare two drafts scene by scene
+    Diff(diff::Args),
+    /// Export a stripboard or breakdown sheets for scheduling
+    Export(export::Args),
+    /// Lock scene and page numbers for production
+    Lock(lock::Args),
+    /// Print the lines matching some filters
+    Query(query::Args),
+    /// Print sides for one character
+    Sides(sides::Args),
+    /// Render a screenplay to a file whenever it changes
+    Watch(watch::Args),
+    /// Serve a live preview of a screenplay on localhost
+    Serve(serve::Args),
+}
+
+fn main() {
+    let cli = Cli::parse_from(with_default_command(env::args_os().collect()));
+    let options = cli
+        .profile
+        .map_or_else(ParseOptions::default, Profile::options);
+    let options = options.with_vocabulary(&cli.language.vocabulary());
+    PARSE_OPTIONS.get_or_init(|| options);
+    let code = match run(cli.command) {
+        Ok(()) if WARNINGS.load(Ordering::Relaxed) > 0 => EXIT_WARNINGS,
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            e.exit_code()
+        }
+    };
+    process::exit(code);
+}
+
+fn run(command: Command) -> Result<(), FountainError> {
+    match command {
+        Command::Render(args) => render::run(args),
+        Command::Convert(args) => convert::run(args),
+        Command::Schema(output) => convert::schema(output),
+        Command::Fmt(args) => fmt::run(args),
+        Command::Stats(args) => stats::run(args),
+        Command::Lint(args) => lint::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Export(args) => export::run(args),
+        Command::Lock(args) => lock::run(args),
+        Command::Query(args) => query::run(args),
+        Command::Sides(args) => sides::run(args),
+        Command::Watch(args) => watch::run(args),
+        Command::Serve(args) => serve::run(args),
+    }
+}
+
+// Render is the default command, so `fountain [OPTIONS] FILEPATH` means `fountain render ...`.
+// Global options like --language may come before the command.
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    const GLOBALS: [&str; 2] = ["--language", "--profile"];
+    let mut i = 1;
+    while let Some(arg) = args.get(i).and_then(|arg| arg.to_str()) {
+        let name = arg.split('=').next().unwrap_or(arg);
+        match arg {
+            _ if GLOBALS.contains(&arg) => i += 2,
+            _ if GLOBALS.contains(&name) => i += 1,
+            _ => break,
+        }
+    }
+    let first = match args.get(i).and_then(|arg| arg.to_str()) {
+        Some(first) => first.to_owned(),
+        None => return args,
+    };
+    let command = Cli::command();
+    let is_command = first == "help" || command.find_subcommand(&first).is_some();
+    let is_global = ["-h", "--help", "-V", "--version"].contains(&first.as_str());
+    if !is_command && !is_global {
+        args.insert(1, "render".into());
+    }
+    args
+}
+
+// Print a warning about the input, which makes the command exit with EXIT_WARNINGS
+fn warn(message: &str) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    eprintln!("{}", message);
+}
+
+// How to parse the input, including the words its Scene Headings are written with
+fn parse_options() -> &'static ParseOptions {
+    PARSE_OPTIONS.get_or_init(ParseOptions::default)
+}
+
+// Parse the .fountain file (or project manifest) at the given filepath, and any files it includes
+fn parse_file(filepath: &str) -> Result<fountain::data::Document, FountainError> {
+    let project = project::load(filepath)?;
+    for &unparsed in &project.unparsed {
+        let (path, position) = project.position_of(unparsed);
+        let text = &project.files[unparsed.file].text[unparsed.span.start..unparsed.span.end];
+        warn(&format!(
+            "{} ({}:{}:{}): {}",
+            ERR_UNPARSED,
+            path.display(),
+            position.line,
+            position.column,
+            text
+        ));
+    }
+    Ok(project.document)
+}
+
+// Wrap rendered HTML in a page, with the screenplay stylesheet
+fn in_html(body: &str) -> String {
     format!(
         "
 <html>
@@ -47,14 +232,81 @@ fn in_html(parsed: &fountain::data::Document) -> String {
 </html>
 ",
         include_str!("style.css"),
-        parsed.as_html(),
+        body,
     )
 }
 
-// Read a file's contents into a string
+// Read a file's contents into a string, or stdin if the filepath is -
 fn read(filepath: &str) -> Result<String, io::Error> {
-    let mut f = File::open(filepath)?;
     let mut contents = String::new();
-    f.read_to_string(&mut contents)?;
+    if filepath == "-" {
+        io::stdin().read_to_string(&mut contents)?;
+    } else {
+        File::open(filepath)?.read_to_string(&mut contents)?;
+    }
     Ok(contents)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        let args = args.iter().map(OsString::from).collect();
+        with_default_command(args)
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_with_default_command() {
+        assert_eq!(
+            args(&["fountain", "alien.fountain"]),
+            vec!["fountain", "render", "alien.fountain"]
+        );
+        assert_eq!(
+            args(&["fountain", "--locked", "draft1.fountain", "-"]),
+            vec!["fountain", "render", "--locked", "draft1.fountain", "-"]
+        );
+        assert_eq!(
+            args(&["fountain", "lint", "alien.fountain"]),
+            vec!["fountain", "lint", "alien.fountain"]
+        );
+        assert_eq!(
+            args(&["fountain", "--language", "german", "lint", "-"]),
+            vec!["fountain", "--language", "german", "lint", "-"]
+        );
+        assert_eq!(
+            args(&["fountain", "--language=german", "-"]),
+            vec!["fountain", "render", "--language=german", "-"]
+        );
+        assert_eq!(
+            args(&["fountain", "--profile", "spec", "--language=german", "-"]),
+            vec![
+                "fountain",
+                "render",
+                "--profile",
+                "spec",
+                "--language=german",
+                "-"
+            ]
+        );
+        assert_eq!(
+            args(&["fountain", "--profile=lenient", "lint", "-"]),
+            vec!["fountain", "--profile=lenient", "lint", "-"]
+        );
+        assert_eq!(args(&["fountain", "--help"]), vec!["fountain", "--help"]);
+        assert_eq!(args(&["fountain", "help"]), vec!["fountain", "help"]);
+        assert_eq!(args(&["fountain"]), vec!["fountain"]);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(FountainError::UsageError(String::new()).exit_code(), 2);
+        assert_eq!(FountainError::LintError(1).exit_code(), EXIT_WARNINGS);
+        assert_eq!(FountainError::FormatError(1).exit_code(), EXIT_WARNINGS);
+        let io = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert_eq!(FountainError::IOError(io).exit_code(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::error::FountainError;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The `-o` option shared by every command which prints a result.
+#[derive(clap::Args)]
+pub struct Output {
+    /// Write to this file instead of stdout. When there are several input files, this is a
+    /// directory, and each output is named after its input
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+impl Output {
+    /// Writes a command's output to the output file, or stdout.
+    pub fn write(&self, text: &str) -> Result<(), FountainError> {
+        match &self.output {
+            Some(path) => fs::write(path, with_newline(text))?,
+            None => print!("{}", with_newline(text)),
+        }
+        Ok(())
+    }
+
+    /// Writes the output for one of several input files, e.g. "act1.fountain" becomes
+    /// "act1.html" in the output directory, or next to the input if there's no `-o`. Output for
+    /// stdin goes to stdout.
+    pub fn write_for(&self, input: &str, extension: &str, text: &str) -> Result<(), FountainError> {
+        if input == "-" {
+            print!("{}", with_newline(text));
+            return Ok(());
+        }
+        if let Some(dir) = &self.output {
+            fs::create_dir_all(dir)?;
+        }
+        let path = self.path_for(input, extension);
+        if path == Path::new(input) {
+            return Err(FountainError::UsageError(format!(
+                "Writing {} would overwrite the input; pass an output directory with -o",
+                path.display()
+            )));
+        }
+        fs::write(&path, with_newline(text))?;
+        eprintln!("Wrote {}", path.display());
+        Ok(())
+    }
+
+    /// Where `write_for` writes the output for an input file.
+    fn path_for(&self, input: &str, extension: &str) -> PathBuf {
+        let input = Path::new(input);
+        let dir = match &self.output {
+            Some(dir) => dir.as_path(),
+            None => input.parent().unwrap_or(Path::new("")),
+        };
+        dir.join(
+            input
+                .with_extension(extension)
+                .file_name()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Writes the output for each input: to `write` if there's only one input, otherwise to
+    /// `write_for`. Nothing is written if two inputs' outputs would have the same path.
+    pub fn write_each<F>(
+        &self,
+        inputs: &[String],
+        extension: &str,
+        f: F,
+    ) -> Result<(), FountainError>
+    where
+        F: Fn(&str) -> Result<String, FountainError>,
+    {
+        match inputs {
+            [input] => self.write(&f(input)?),
+            _ => {
+                // Inputs with the same name in different directories would overwrite each other.
+                let mut paths = HashSet::new();
+                for input in inputs.iter().filter(|&input| input != "-") {
+                    let path = self.path_for(input, extension);
+                    if !paths.insert(path.clone()) {
+                        return Err(FountainError::UsageError(format!(
+                            "More than one input would be written to {}",
+                            path.display()
+                        )));
+                    }
+                }
+                for input in inputs {
+                    self.write_for(input, extension, &f(input)?)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn with_newline(text: &str) -> String {
+    if text.ends_with('\n') || text.is_empty() {
+        text.to_owned()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_output_paths() {
+        let output = Output {
+            output: Some(PathBuf::from("out")),
+        };
+        assert_eq!(
+            output.path_for("drafts/act1.fountain", "html"),
+            Path::new("out/act1.html")
+        );
+        let inputs = vec![
+            "draft1/act1.fountain".to_owned(),
+            "draft2/act1.fountain".to_owned(),
+        ];
+        let result = output.write_each(&inputs, "html", |_| panic!("nothing should be rendered"));
+        assert!(matches!(result, Err(FountainError::UsageError(_))));
+    }
+}
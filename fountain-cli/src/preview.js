@@ -0,0 +1,25 @@
+// Polls the server for a newer version of the screenplay, and swaps it in without reloading the
+// page, scrolling to the scene which changed.
+const screenplay = document.getElementById('screenplay');
+let version = screenplay.dataset.version;
+
+async function poll() {
+    try {
+        const response = await fetch('/preview?version=' + version);
+        if (response.status === 200) {
+            const preview = await response.json();
+            version = preview.version;
+            document.getElementById('diagnostics').innerHTML = preview.diagnostics;
+            screenplay.innerHTML = preview.body;
+            const scene = preview.scene && screenplay.querySelectorAll('.scene')[preview.scene - 1];
+            if (scene) {
+                scene.scrollIntoView({ block: 'start', behavior: 'smooth' });
+            }
+        }
+    } catch (e) {
+        // The server has probably stopped; keep trying in case it comes back.
+    }
+    setTimeout(poll, 300);
+}
+
+poll();
@@ -0,0 +1,50 @@
+use crate::error::FountainError;
+use fountain::project::Project;
+use std::path::Path;
+
+/// Loads a .fountain file and any files it includes, or every file listed in a TOML manifest, e.g.
+/// ```toml
+/// files = ["act1.fountain", "act2.fountain", "act3.fountain"]
+/// ```
+/// Files in a manifest are relative to the manifest's directory. A path of - reads stdin, and any
+/// files it includes are relative to the current directory.
+pub fn load(path: &str) -> Result<Project, FountainError> {
+    if path == "-" {
+        let options = crate::parse_options();
+        return Ok(Project::load_with_options(
+            &[path],
+            options,
+            |file| match file.to_str() {
+                Some("-") => crate::read("-"),
+                _ => std::fs::read_to_string(file),
+            },
+        )?);
+    }
+    if !path.ends_with(".toml") {
+        return Ok(Project::open_with_options(&[path], crate::parse_options())?);
+    }
+    let path = Path::new(path);
+    let err = |msg: String| FountainError::ConfigError(format!("{}: {}", path.display(), msg));
+    let table: toml::Table = crate::read(&path.to_string_lossy())?
+        .parse()
+        .map_err(|e: toml::de::Error| err(e.to_string()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("files", toml::Value::Array(paths)) => {
+                for file in paths {
+                    match file {
+                        toml::Value::String(file) => files.push(dir.join(file)),
+                        _ => return Err(err("files must be a list of paths".to_owned())),
+                    }
+                }
+            }
+            (key, _) => return Err(err(format!("unexpected setting '{}'", key))),
+        }
+    }
+    if files.is_empty() {
+        return Err(err("the manifest doesn't list any files".to_owned()));
+    }
+    Ok(Project::open_with_options(&files, crate::parse_options())?)
+}
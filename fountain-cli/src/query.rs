@@ -0,0 +1,106 @@
+use crate::error::FountainError;
+use crate::output::Output;
+use fountain::project::Project;
+use fountain::query::{Element, Kind};
+use serde_json::json;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Only lines of this kind
+    #[arg(long, value_parser = parse_kind)]
+    kind: Option<Kind>,
+    /// Only the lines of this character's speeches
+    #[arg(long, value_name = "NAME")]
+    speaker: Option<String>,
+    /// Only lines in scenes set at this location
+    #[arg(long)]
+    location: Option<String>,
+    /// Only lines in scenes set at this time of day
+    #[arg(long)]
+    time: Option<String>,
+    /// Only lines within the section with this title
+    #[arg(long, value_name = "TITLE")]
+    section: Option<String>,
+    /// Only lines in this scene, counting from 1
+    #[arg(long, value_name = "N")]
+    scene: Option<usize>,
+    /// Only lines containing this text
+    #[arg(long)]
+    text: Option<String>,
+    #[arg(short, long, value_enum, default_value_t)]
+    format: QueryFormat,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH")]
+    file: String,
+}
+
+/// How matches should be printed.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum QueryFormat {
+    #[default]
+    Fountain,
+    /// With each line's file and position
+    Json,
+}
+
+fn parse_kind(s: &str) -> Result<Kind, String> {
+    s.parse()
+}
+
+/// Runs `fountain query`. Prints the lines matching every filter given, either as Fountain or as
+/// JSON with their positions in the file.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let project = crate::project::load(&args.file)?;
+    let mut query = project
+        .document
+        .query_with(&crate::parse_options().vocabulary);
+    if let Some(kind) = args.kind {
+        query = query.kind(kind);
+    }
+    if let Some(speaker) = &args.speaker {
+        query = query.speaker(speaker);
+    }
+    if let Some(location) = &args.location {
+        query = query.location(location);
+    }
+    if let Some(time) = &args.time {
+        query = query.time_of_day(time);
+    }
+    if let Some(section) = &args.section {
+        query = query.section(section);
+    }
+    if let Some(n) = args.scene {
+        query = query.scene(n);
+    }
+    if let Some(text) = &args.text {
+        query = query.containing(text);
+    }
+
+    let output = match args.format {
+        QueryFormat::Fountain => query.to_document().as_fountain(),
+        QueryFormat::Json => {
+            let matches: Vec<_> = query
+                .iter()
+                .map(|element| to_json(&element, &project))
+                .collect();
+            serde_json::to_string_pretty(&matches)?
+        }
+    };
+    args.output.write(&output)
+}
+
+fn to_json(element: &Element, project: &Project) -> serde_json::Value {
+    let (path, position) = project.locate(element.index);
+    json!({
+        "file": path,
+        "line": position.line,
+        "column": position.column,
+        "kind": Kind::of(element.line),
+        "text": element.line.text(),
+        "scene": element.scene,
+        "heading": element.heading,
+        "speaker": element.speaker,
+        "sections": element.sections,
+    })
+}
@@ -0,0 +1,68 @@
+use crate::error::FountainError;
+use crate::output::Output;
+use fountain::revisions::Revision;
+
+const ERR_NO_REVISION: &str =
+    "No revision name: use --revision, or add a Revision key to the title page";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Number scenes and pages using a locked draft, or a lock file written by `fountain lock`
+    #[arg(long, value_name = "BASELINEPATH")]
+    locked: Option<String>,
+    /// Mark the lines which changed since this previous draft
+    #[arg(long, value_name = "OLDPATH")]
+    revised_from: Option<String>,
+    /// The revision's name, e.g. "Blue Revision". Defaults to the title page's Revision key
+    #[arg(long, value_name = "NAME", requires = "revised_from")]
+    revision: Option<String>,
+    /// The revision's date. Defaults to the title page's Revision Date key
+    #[arg(long, value_name = "DATE", requires = "revised_from")]
+    revision_date: Option<String>,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+/// Renders documents as HTML. With `--revised-from`, lines which changed since the previous draft
+/// get revision marks, and changed pages are stamped with the revision. The revision defaults to
+/// the title page's `Revision` and `Revision Date`. With `--locked`, pages and scenes are numbered
+/// using a locked baseline.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let lock = crate::lock::load_baseline(args.locked.as_deref())?;
+    let previous = match &args.revised_from {
+        Some(path) => Some(crate::parse_file(path)?),
+        None => None,
+    };
+    args.output.write_each(&args.files, "html", |path| {
+        let doc = crate::parse_file(path)?;
+        let revised_from = match &previous {
+            None => None,
+            Some(previous) => {
+                let from_titlepage = Revision::from_titlepage(&doc.titlepage);
+                let revision = match (args.revision.clone(), from_titlepage) {
+                    (Some(name), _) => Revision { name, date: None },
+                    (None, Some(revision)) => revision,
+                    (None, None) => {
+                        return Err(FountainError::UsageError(ERR_NO_REVISION.to_owned()))
+                    }
+                };
+                let revision = Revision {
+                    date: args.revision_date.clone().or(revision.date),
+                    ..revision
+                };
+                Some((previous, revision))
+            }
+        };
+        let revised_from = revised_from
+            .as_ref()
+            .map(|(doc, revision)| (*doc, revision));
+        let body = match (&lock, revised_from) {
+            (Some(lock), revised_from) => doc.as_locked_html(lock, revised_from),
+            (None, Some((previous, revision))) => doc.as_revised_html(previous, revision),
+            (None, None) => doc.as_html(),
+        };
+        Ok(crate::in_html(&body))
+    })
+}
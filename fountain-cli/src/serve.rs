@@ -0,0 +1,128 @@
+use crate::error::FountainError;
+use crate::format::escape;
+use crate::watch::{Preview, Watcher};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(short, long, default_value_t = 8000)]
+    port: u16,
+    #[arg(value_name = "FILEPATH")]
+    file: String,
+}
+
+/// The latest preview, and how many times it's been rebuilt.
+struct State {
+    version: u64,
+    preview: Arc<Preview>,
+}
+
+/// Runs `fountain serve`. Serves a live preview of the
+/// document on localhost, which reloads whenever the document (or any file it includes) changes,
+/// and scrolls to the scene which changed.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let (path, port) = (args.file, args.port);
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    // Files are watched from before they're read, so changes made while rendering aren't missed.
+    let mut watcher = Watcher::new(&[PathBuf::from(&path)]);
+    let mut preview = Arc::new(Preview::build(&path, None));
+    let state = Arc::new(Mutex::new(State {
+        version: 1,
+        preview: Arc::clone(&preview),
+    }));
+    eprintln!("Previewing {} at http://localhost:{}", path, port);
+
+    let watched = Arc::clone(&state);
+    thread::spawn(move || loop {
+        watcher.watch(&preview.files);
+        watcher.wait();
+        watcher = Watcher::new(&preview.files);
+        // Requests are still answered with the old preview while the new one is built.
+        preview = Arc::new(Preview::build(&path, Some(&preview)));
+        let mut state = watched.lock().unwrap();
+        state.preview = Arc::clone(&preview);
+        state.version += 1;
+    });
+
+    for stream in listener.incoming() {
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(e) = stream.and_then(|stream| respond(stream, &state)) {
+                eprintln!("Couldn't respond to a request: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Answers one HTTP request. The page itself is served from "/", and then polls "/preview" with
+/// the version it has, which answers 204 No Content until there's a newer version.
+fn respond(mut stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    let mut request = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader.read_line(&mut request)?;
+    // Skip the headers; nothing here needs them.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let state = state.lock().unwrap();
+    let (status, content_type, body) = match path {
+        "/" => ("200 OK", "text/html", page(&state)),
+        "/preview" => {
+            let seen = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("version="))
+                .and_then(|v| v.parse::<u64>().ok());
+            if seen == Some(state.version) {
+                ("204 No Content", "text/plain", String::new())
+            } else {
+                let update = json!({
+                    "version": state.version,
+                    "body": state.preview.body,
+                    "diagnostics": diagnostics(&state.preview),
+                    "scene": state.preview.changed_scene,
+                });
+                ("200 OK", "application/json", update.to_string())
+            }
+        }
+        _ => ("404 Not Found", "text/plain", "Not found".to_owned()),
+    };
+    drop(state);
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The whole preview page, with the script which keeps it up to date.
+fn page(state: &State) -> String {
+    crate::in_html(&format!(
+        "<style>\n{}</style>\n<div id='diagnostics'>{}</div>\n<div id='screenplay' data-version='{}'>\n{}\n</div>\n<script>\n{}</script>",
+        include_str!("preview.css"),
+        diagnostics(&state.preview),
+        state.version,
+        state.preview.body,
+        include_str!("preview.js"),
+    ))
+}
+
+fn diagnostics(preview: &Preview) -> String {
+    preview
+        .diagnostics
+        .iter()
+        .map(|d| format!("<p class='diagnostic'>{}</p>", escape(d)))
+        .collect()
+}
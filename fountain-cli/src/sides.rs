@@ -0,0 +1,48 @@
+use crate::error::FountainError;
+use crate::output::Output;
+use fountain::sides::Unfeatured;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The character the sides are for
+    #[arg(short, long, value_name = "NAME")]
+    character: String,
+    /// Keep the scenes the character isn't in, struck through
+    #[arg(long)]
+    cross_out: bool,
+    /// Use the scene and page numbers of a locked draft, or a lock file
+    #[arg(long, value_name = "BASELINEPATH")]
+    locked: Option<String>,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH")]
+    file: String,
+}
+
+/// Runs `fountain sides`. Prints HTML sides with only the scenes featuring the character, or with
+/// the other scenes struck through if `--cross-out` is given.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let lock = crate::lock::load_baseline(args.locked.as_deref())?;
+    let unfeatured = if args.cross_out {
+        Unfeatured::CrossOut
+    } else {
+        Unfeatured::Omit
+    };
+    let character = &args.character;
+    let doc = crate::parse_file(&args.file)?;
+    let sides = match &lock {
+        Some(lock) => doc.locked_sides(character, lock),
+        None => doc.sides(character),
+    };
+    if !sides.iter().any(|side| side.featured) {
+        return Err(FountainError::UsageError(format!(
+            "{} doesn't appear in any scene of {}",
+            character, args.file
+        )));
+    }
+    args.output.write(&crate::in_html(&doc.as_sides_html(
+        &character.to_uppercase(),
+        &sides,
+        unfeatured,
+    )))
+}
@@ -0,0 +1,205 @@
+use crate::error::FountainError;
+use crate::format::{self, Format};
+use crate::output::Output;
+use fountain::data::Document;
+use fountain::runtime::Length;
+
+#[derive(clap::Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    report: Option<Report>,
+    #[command(flatten)]
+    files: Files,
+}
+
+/// The reports other than the default runtime report.
+#[derive(clap::Subcommand)]
+enum Report {
+    /// One row per character: how often they speak, and in which scenes
+    Characters(Files),
+    /// One row per scene: where and when it's set, its length and its characters
+    Scenes(Files),
+}
+
+#[derive(clap::Args)]
+struct Files {
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+    #[command(flatten)]
+    output: Output,
+    #[arg(value_name = "FILEPATH", required = true)]
+    files: Vec<String>,
+}
+
+/// Runs `fountain stats`. Without a report name, prints how long the document is.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    let (report, files): (fn(&Document, Format) -> _, _) = match &args.report {
+        None => (runtime, &args.files),
+        Some(Report::Characters(files)) => (characters, files),
+        Some(Report::Scenes(files)) => (scenes, files),
+    };
+    let extension = match files.format {
+        Format::Table => "txt",
+        Format::Csv => "csv",
+        Format::Json => "json",
+    };
+    files.output.write_each(&files.files, extension, |path| {
+        report(&crate::parse_file(path)?, files.format)
+    })
+}
+
+/// The runtime report: how long the whole document, each act and each scene is.
+fn runtime(doc: &Document, format: Format) -> Result<String, FountainError> {
+    let estimate = doc.estimate();
+    let rows = |kind: &str, lengths: &[Length]| -> Vec<Vec<String>> {
+        lengths
+            .iter()
+            .map(|l| {
+                vec![
+                    kind.to_owned(),
+                    l.name.clone(),
+                    l.length.to_string(),
+                    duration(l.seconds),
+                ]
+            })
+            .collect()
+    };
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(&estimate)?,
+        Format::Table => {
+            let header = ["", "NAME", "PAGES", "RUNTIME"];
+            let mut table = rows("act", &estimate.acts);
+            table.extend(rows("scene", &estimate.scenes));
+            format!(
+                "Pages:    {}\nLength:   {}\nRuntime:  {}\nDialogue: {:.0}% of dialogue and action\n\n{}",
+                estimate.pages,
+                estimate.length,
+                duration(estimate.seconds),
+                estimate.dialogue_ratio() * 100.0,
+                format::table(&header, &table)
+            )
+        }
+        Format::Csv => {
+            let header = ["kind", "name", "pages", "runtime"];
+            let mut table = vec![vec![
+                "document".to_owned(),
+                doc.titlepage.title.clone().unwrap_or_default(),
+                estimate.length.to_string(),
+                duration(estimate.seconds),
+            ]];
+            table.extend(rows("act", &estimate.acts));
+            table.extend(rows("scene", &estimate.scenes));
+            format::csv(&header, &table)
+        }
+    })
+}
+
+/// Formats a number of seconds like 1:52:30 or 4:05.
+fn duration(seconds: u64) -> String {
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// The cast report: one row per character.
+fn characters(doc: &Document, format: Format) -> Result<String, FountainError> {
+    let characters = doc.characters();
+    let first_scene = |scenes: &[usize]| match scenes.first() {
+        Some(n) => n.to_string(),
+        None => "-".to_owned(),
+    };
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(&characters)?,
+        Format::Table => {
+            let header = ["NAME", "SPEECHES", "WORDS", "SCENES", "FIRST SCENE", "DUAL"];
+            let rows: Vec<_> = characters
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.name.clone(),
+                        c.speeches.to_string(),
+                        c.words.to_string(),
+                        c.scenes.len().to_string(),
+                        first_scene(&c.scenes),
+                        c.dual_dialogues.to_string(),
+                    ]
+                })
+                .collect();
+            format::table(&header, &rows)
+        }
+        Format::Csv => {
+            let header = [
+                "name",
+                "aliases",
+                "speeches",
+                "words",
+                "scenes",
+                "first_scene",
+                "dual_dialogues",
+            ];
+            let rows: Vec<_> = characters
+                .iter()
+                .map(|c| {
+                    let scenes: Vec<_> = c.scenes.iter().map(|n| n.to_string()).collect();
+                    vec![
+                        c.name.clone(),
+                        c.aliases.join("; "),
+                        c.speeches.to_string(),
+                        c.words.to_string(),
+                        scenes.join(" "),
+                        first_scene(&c.scenes),
+                        c.dual_dialogues.to_string(),
+                    ]
+                })
+                .collect();
+            format::csv(&header, &rows)
+        }
+    })
+}
+
+/// The scene breakdown report: one row per scene.
+fn scenes(doc: &Document, format: Format) -> Result<String, FountainError> {
+    let breakdown = doc.scene_breakdown_with(&crate::parse_options().vocabulary);
+    let header = [
+        "SCENE",
+        "INT/EXT",
+        "LOCATION",
+        "TIME",
+        "PAGE",
+        "LENGTH",
+        "CHARACTERS",
+    ];
+    let rows: Vec<_> = breakdown
+        .iter()
+        .map(|s| {
+            let location = match &s.heading.sub_location {
+                Some(sub) => format!("{} - {}", s.heading.location, sub),
+                None => s.heading.location.clone(),
+            };
+            vec![
+                s.number.clone(),
+                s.heading
+                    .setting
+                    .map_or_else(String::new, |setting| setting.to_string()),
+                location,
+                s.heading.time.clone().unwrap_or_default(),
+                s.page.to_string(),
+                s.length.to_string(),
+                s.characters.join(", "),
+            ]
+        })
+        .collect();
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(&breakdown)?,
+        Format::Table => format::table(&header, &rows),
+        Format::Csv => {
+            let header: Vec<_> = header.iter().map(|h| h.to_lowercase()).collect();
+            let header: Vec<_> = header.iter().map(String::as_str).collect();
+            format::csv(&header, &rows)
+        }
+    })
+}
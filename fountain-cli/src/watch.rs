@@ -0,0 +1,150 @@
+use crate::error::FountainError;
+use fountain::data::Document;
+use fountain::lint::lint;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often to check whether the watched files have changed.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The HTML file to write
+    #[arg(short, long, value_name = "PATH")]
+    output: PathBuf,
+    #[arg(value_name = "FILEPATH")]
+    file: String,
+}
+
+/// Runs `fountain watch`. Renders the document to the output file, then renders it again whenever
+/// it (or any file it includes) changes. Never returns unless the output can't be written.
+pub fn run(args: Args) -> Result<(), FountainError> {
+    // Files are watched from before they're read, so changes made while rendering aren't missed.
+    let mut watcher = Watcher::new(&[PathBuf::from(&args.file)]);
+    let mut preview = Preview::build(&args.file, None);
+    loop {
+        watcher.watch(&preview.files);
+        for diagnostic in &preview.diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        fs::write(&args.output, crate::in_html(&preview.body))?;
+        eprintln!("Wrote {}", args.output.display());
+        watcher.wait();
+        watcher = Watcher::new(&preview.files);
+        preview = Preview::build(&args.file, Some(&preview));
+    }
+}
+
+/// The rendered document, and any problems found while rendering it.
+pub struct Preview {
+    /// The document rendered as HTML, without the surrounding page.
+    pub body: String,
+    /// Lint diagnostics and errors, one per line.
+    pub diagnostics: Vec<String>,
+    /// Every file the document was loaded from, so they can be watched for changes.
+    pub files: Vec<PathBuf>,
+    /// The scene containing the first change since the previous preview, if any.
+    pub changed_scene: Option<usize>,
+    doc: Option<Document>,
+}
+
+impl Preview {
+    /// Loads and renders the document at the given path. If it can't be loaded, the previous
+    /// preview's HTML is kept, with the error as a diagnostic.
+    pub fn build(path: &str, previous: Option<&Preview>) -> Preview {
+        let project = match crate::project::load(path) {
+            Ok(project) => project,
+            Err(e) => {
+                let mut files = vec![PathBuf::from(path)];
+                let (body, doc) = match previous {
+                    Some(previous) => {
+                        files.extend(previous.files.iter().cloned());
+                        (previous.body.clone(), previous.doc.clone())
+                    }
+                    None => (String::new(), None),
+                };
+                return Preview {
+                    body,
+                    diagnostics: vec![e.to_string()],
+                    files,
+                    changed_scene: None,
+                    doc,
+                };
+            }
+        };
+        let (config, mut diagnostics) = match crate::lint::default_config() {
+            Ok(config) => (config, Vec::new()),
+            Err(e) => (crate::lint::defaults(), vec![e.to_string()]),
+        };
+        diagnostics.extend(project.files.iter().flat_map(|file| {
+            let path = file.path.display().to_string();
+            lint(&file.text, &config)
+                .into_iter()
+                .map(move |d| crate::lint::describe(&path, &d))
+        }));
+        let doc = project.document;
+        let changed_scene = previous
+            .and_then(|p| p.doc.as_ref())
+            .and_then(|old| doc.revised_lines(old).into_iter().next())
+            .and_then(|i| doc.scene_number_at(i));
+        // A manifest isn't one of the project's files, but changes to it matter too.
+        let mut files: Vec<PathBuf> = project.files.into_iter().map(|f| f.path).collect();
+        if !files.iter().any(|f| f.as_path() == Path::new(path)) {
+            files.push(PathBuf::from(path));
+        }
+        Preview {
+            body: doc.as_html(),
+            diagnostics,
+            files,
+            changed_scene,
+            doc: Some(doc),
+        }
+    }
+}
+
+/// Notices when any of a set of files is modified, created or deleted, by polling their
+/// modification times.
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Watcher {
+    pub fn new(paths: &[PathBuf]) -> Watcher {
+        Watcher {
+            files: paths
+                .iter()
+                .map(|path| (path.clone(), modified(path)))
+                .collect(),
+        }
+    }
+
+    /// Also watches these paths, if they aren't watched already. They weren't looked at when the
+    /// watcher was created, so they count as changed unless they don't exist.
+    pub fn watch(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            if !self.files.iter().any(|(watched, _)| watched == path) {
+                self.files.push((path.clone(), None));
+            }
+        }
+    }
+
+    /// True if any file has changed since the watcher was created.
+    pub fn changed(&self) -> bool {
+        self.files
+            .iter()
+            .any(|(path, time)| modified(path) != *time)
+    }
+
+    /// Blocks until a file changes.
+    pub fn wait(&self) {
+        while !self.changed() {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
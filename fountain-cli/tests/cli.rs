@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the `fountain` binary with the given arguments and stdin.
+fn fountain(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fountain"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+const SCRIPT: &str = "INT. MESS - NIGHT\n\nThe crew eats.\n";
+
+#[test]
+fn test_render_is_default_command() {
+    let output = fountain(&["-"], SCRIPT);
+    assert_eq!(output.status.code(), Some(0));
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.contains("<p class='action'>The crew eats.</p>"));
+}
+
+#[test]
+fn test_stdin() {
+    let output = fountain(&["fmt", "-"], "INT. MESS - NIGHT\n\n\nThe crew eats.");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "INT. MESS - NIGHT\n\n\nThe crew eats.\n"
+    );
+}
+
+#[test]
+fn test_language() {
+    let script = "INNEN. KÜCHE - NACHT\n\nAnna kocht.\n";
+    let output = fountain(&["stats", "scenes", "--language", "german", "-"], script);
+    assert_eq!(output.status.code(), Some(0));
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("KÜCHE"), "{}", report);
+    let script = "INT. KÜCHE - NACHT\n\nAnna kocht.\n";
+    let output = fountain(&["lint", "-"], script);
+    assert_eq!(output.status.code(), Some(3));
+    let output = fountain(&["--language", "german", "lint", "-"], script);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_profile() {
+    let script = "int. mess - night\n\nThe crew eats.\n";
+    let render = |args: &[&str]| String::from_utf8(fountain(args, script).stdout).unwrap();
+    assert!(!render(&["-"]).contains("<p class='scene'>"));
+    assert!(render(&["--profile", "spec", "-"]).contains("<p class='scene'>int. mess - night</p>"));
+    let script = "ext./int. mess - night\n\nThe crew eats.\n";
+    let output = fountain(&["stats", "scenes", "-"], script);
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("mess"));
+    let output = fountain(&["--profile=lenient", "stats", "scenes", "-"], script);
+    assert_eq!(output.status.code(), Some(0));
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("mess"), "{}", report);
+}
+
+#[test]
+fn test_exit_codes() {
+    // Errors
+    let output = fountain(&["render", "no-such-file.fountain"], "");
+    assert_eq!(output.status.code(), Some(1));
+    // Usage errors
+    let output = fountain(&["stats", "-", "--no-such-flag"], SCRIPT);
+    assert_eq!(output.status.code(), Some(2));
+    // Problems in the input
+    let output = fountain(&["lint", "-"], "INT. MESS\n\nThe crew eats.\n");
+    assert_eq!(output.status.code(), Some(3));
+    let output = fountain(
+        &["fmt", "--check", "-"],
+        "INT. MESS - NIGHT\n\nThe crew eats.",
+    );
+    assert_eq!(output.status.code(), Some(3));
+}
@@ -0,0 +1,516 @@
+//! Everything the server knows about one open document, worked out from its text.
+use crate::position::LineIndex;
+use fountain::characters::normalize_name;
+use fountain::data::{Document, Line};
+use fountain::edit::{self, Mentions};
+use fountain::lint::{self, LintConfig, Severity};
+use fountain::options::ParseOptions;
+use fountain::scenes::SceneHeading;
+use fountain::source::Span;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic, DiagnosticSeverity,
+    DocumentSymbol, FoldingRange, FoldingRangeKind, Location, NumberOrString, Position, Range,
+    SymbolInformation, SymbolKind, TextEdit, Url,
+};
+use std::collections::BTreeSet;
+
+pub struct Analysis {
+    text: String,
+    index: LineIndex,
+    doc: Document,
+    /// The nth span is where `doc.lines[n]` came from.
+    spans: Vec<Span>,
+    diagnostics: Vec<lint::Diagnostic>,
+    options: ParseOptions,
+}
+
+/// A section or scene in the outline, covering lines `start..end` of the document.
+struct OutlineItem {
+    start: usize,
+    end: usize,
+    symbol: DocumentSymbol,
+}
+
+impl Analysis {
+    #[cfg(test)]
+    pub fn new(text: String) -> Self {
+        Analysis::with_options(text, ParseOptions::default())
+    }
+
+    pub fn with_options(text: String, options: ParseOptions) -> Self {
+        let parsed = fountain::parse_document_with_spans_and_options::<(&str, _)>(&text, &options);
+        let (doc, spans) = match parsed {
+            Ok((_, parsed)) => parsed,
+            Err(_) => Default::default(),
+        };
+        // The linter reports anything the parser couldn't understand, too.
+        let mut config = LintConfig::default();
+        config.parse_options = options.clone();
+        let diagnostics = lint::lint(&text, &config);
+        Analysis {
+            index: LineIndex::new(&text),
+            text,
+            doc,
+            spans,
+            diagnostics,
+            options,
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .iter()
+            .map(|d| Diagnostic {
+                range: self.range(d.span),
+                severity: Some(match d.severity {
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                }),
+                code: Some(NumberOrString::String(d.rule.name().to_owned())),
+                source: Some("fountain".to_owned()),
+                message: d.message.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// The document's outline: sections, with the sections and scenes inside them as children.
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        let mut items = self.outline().into_iter().peekable();
+        nest(&mut items, self.doc.lines.len())
+    }
+
+    /// Scenes whose number is the query, or whose heading contains it, for jumping to a scene.
+    pub fn find_scenes(&self, uri: &Url, query: &str) -> Vec<SymbolInformation> {
+        let query = query.trim().to_uppercase();
+        self.doc
+            .scenes()
+            .into_iter()
+            .filter(|scene| {
+                scene.number.to_string() == query || scene.heading.to_uppercase().contains(&query)
+            })
+            .map(|scene| {
+                #[allow(deprecated)]
+                SymbolInformation {
+                    name: scene.heading.to_owned(),
+                    kind: SymbolKind::CLASS,
+                    tags: None,
+                    deprecated: None,
+                    location: Location::new(uri.clone(), self.range(self.spans[scene.start])),
+                    container_name: Some(format!("Scene {}", scene.number)),
+                }
+            })
+            .collect()
+    }
+
+    /// Every section and scene can be folded away.
+    pub fn folding_ranges(&self) -> Vec<FoldingRange> {
+        self.outline()
+            .into_iter()
+            .filter(|item| item.symbol.range.end.line > item.symbol.range.start.line)
+            .map(|item| FoldingRange {
+                start_line: item.symbol.range.start.line,
+                end_line: item.symbol.range.end.line,
+                kind: Some(FoldingRangeKind::Region),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Locations (and times of day) while typing a Scene Heading, or character names while typing
+    /// a Speaker.
+    pub fn completions(&self, position: Position) -> Vec<CompletionItem> {
+        let offset = self.index.offset(&self.text, position);
+        let (line_start, line) = self.index.line_at(&self.text, offset);
+        let typed = &line[..offset - line_start];
+        let scene_prefix = self
+            .options
+            .scene_prefix_len(typed)
+            .or_else(|| (typed.starts_with('.') && !typed.starts_with("..")).then_some(1));
+        if let Some(prefix) = scene_prefix {
+            if let Some(dash) = typed.rfind(" - ").filter(|&dash| dash >= prefix) {
+                let replace = Span {
+                    start: line_start + dash + 3,
+                    end: offset,
+                };
+                let times = self.options.vocabulary.times_of_day.clone();
+                return self.completion_items(times, replace, CompletionItemKind::CONSTANT);
+            }
+            let replace = Span {
+                start: line_start + prefix,
+                end: offset,
+            };
+            return self.completion_items(
+                self.locations(offset),
+                replace,
+                CompletionItemKind::MODULE,
+            );
+        }
+        // Speakers always follow a blank line, and are written in capitals.
+        let after_blank = line_start == 0 || self.text[..line_start - 1].ends_with('\n');
+        if !after_blank || (typed.chars().any(char::is_lowercase) && !typed.starts_with('@')) {
+            return Vec::new();
+        }
+        let replace = Span {
+            start: line_start + typed.starts_with('@') as usize,
+            end: offset,
+        };
+        let names = self.doc.characters().into_iter().map(|c| c.name).collect();
+        self.completion_items(names, replace, CompletionItemKind::VARIABLE)
+    }
+
+    /// The character whose name is at the position, on a Speaker line or mentioned in action, and
+    /// where their name is.
+    pub fn character_at(&self, position: Position) -> Option<(Range, String)> {
+        let offset = self.index.offset(&self.text, position);
+        let i = self.spans.partition_point(|span| span.end < offset);
+        let span = *self.spans.get(i).filter(|span| span.start <= offset)?;
+        let source = &self.text[span.start..span.end];
+        match &self.doc.lines[i] {
+            Line::Speaker { name, .. } => {
+                let name = normalize_name(name);
+                let start = span.start + source.find(&name)?;
+                let end = start + name.len();
+                (start..=end)
+                    .contains(&offset)
+                    .then(|| (self.range(Span { start, end }), name))
+            }
+            Line::Action(_) => self.doc.characters().into_iter().find_map(|character| {
+                edit::mentions(source, &character.name)
+                    .into_iter()
+                    .map(|m| Span {
+                        start: span.start + m.start,
+                        end: span.start + m.end,
+                    })
+                    .find(|m| (m.start..=m.end).contains(&offset))
+                    .map(|m| (self.range(m), character.name))
+            }),
+            _ => None,
+        }
+    }
+
+    /// Renames the character at the position everywhere they speak or are mentioned in action.
+    pub fn rename(&self, position: Position, to: &str) -> Result<Vec<TextEdit>, String> {
+        let (_, from) = self
+            .character_at(position)
+            .ok_or_else(|| "There's no character here to rename".to_owned())?;
+        let mut renamed = self.doc.clone();
+        renamed
+            .rename_character(&from, to, Mentions::Rename)
+            .map_err(|e| e.to_string())?;
+        Ok(self
+            .doc
+            .lines
+            .iter()
+            .zip(&renamed.lines)
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(i, (old, new))| self.line_edit(self.spans[i], old, new))
+            .collect())
+    }
+
+    /// An edit which changes a line's text from `old` to `new`, touching as little of the source
+    /// as possible so that its formatting is kept.
+    fn line_edit(&self, span: Span, old: &Line, new: &Line) -> TextEdit {
+        let source = &self.text[span.start..span.end];
+        let (old, new_text) = (old.text(), new.text());
+        let at = match source.find(old) {
+            Some(at) => span.start + at,
+            None => return TextEdit::new(self.range(span), new.as_fountain()),
+        };
+        let shortest = old.len().min(new_text.len());
+        let mut prefix = old
+            .bytes()
+            .zip(new_text.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(new_text.bytes().rev())
+            .take(shortest - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) {
+            suffix -= 1;
+        }
+        let changed = Span {
+            start: at + prefix,
+            end: at + old.len() - suffix,
+        };
+        TextEdit::new(
+            self.range(changed),
+            new_text[prefix..new_text.len() - suffix].to_owned(),
+        )
+    }
+
+    /// Every distinct scene location, except one being typed at the offset.
+    fn locations(&self, offset: usize) -> Vec<String> {
+        let locations: BTreeSet<String> = self
+            .doc
+            .scenes()
+            .into_iter()
+            .filter(|scene| {
+                let span = self.spans[scene.start];
+                !(span.start..=span.end).contains(&offset)
+            })
+            .map(|scene| SceneHeading::parse_with(scene.heading, &self.options.vocabulary).location)
+            .filter(|location| !location.is_empty())
+            .collect();
+        locations.into_iter().collect()
+    }
+
+    fn completion_items(
+        &self,
+        labels: Vec<String>,
+        replace: Span,
+        kind: CompletionItemKind,
+    ) -> Vec<CompletionItem> {
+        let range = self.range(replace);
+        labels
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| CompletionItem {
+                kind: Some(kind),
+                // Keep our order (e.g. most frequent speaker first) rather than sorting by label.
+                sort_text: Some(format!("{:05}", i)),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                    range,
+                    label.clone(),
+                ))),
+                label,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Sections and scenes in document order. A section lasts until the next section at the same
+    /// or a higher level, and a scene lasts until the next scene or section.
+    fn outline(&self) -> Vec<OutlineItem> {
+        let lines = &self.doc.lines;
+        let ends = |start: usize, ends_at: &dyn Fn(&Line) -> bool| {
+            lines[start + 1..]
+                .iter()
+                .position(ends_at)
+                .map_or(lines.len(), |n| start + 1 + n)
+        };
+        let mut scene = 0;
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(start, line)| {
+                let (name, detail, kind, end) = match line {
+                    Line::Section { title, depth } => {
+                        let end = ends(
+                            start,
+                            &|l| matches!(l, Line::Section { depth: d, .. } if d <= depth),
+                        );
+                        (title.clone(), None, SymbolKind::NAMESPACE, end)
+                    }
+                    Line::Scene(heading) => {
+                        scene += 1;
+                        let end = ends(start, &|l| l.is_scene() || l.is_section());
+                        let detail = Some(format!("Scene {}", scene));
+                        (heading.clone(), detail, SymbolKind::CLASS, end)
+                    }
+                    _ => return None,
+                };
+                let range = self.range(Span {
+                    start: self.spans[start].start,
+                    end: self.spans[end - 1].end,
+                });
+                #[allow(deprecated)]
+                let symbol = DocumentSymbol {
+                    name,
+                    detail,
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range,
+                    selection_range: self.range(self.spans[start]),
+                    children: None,
+                };
+                Some(OutlineItem { start, end, symbol })
+            })
+            .collect()
+    }
+
+    fn range(&self, span: Span) -> Range {
+        self.index.range(&self.text, span)
+    }
+}
+
+/// Nests the outline items which start before `end`, and the items inside them.
+fn nest(
+    items: &mut std::iter::Peekable<impl Iterator<Item = OutlineItem>>,
+    end: usize,
+) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    while let Some(item) = items.next_if(|item| item.start < end) {
+        let children = nest(items, item.end);
+        let mut symbol = item.symbol;
+        symbol.children = Some(children).filter(|c| !c.is_empty());
+        symbols.push(symbol);
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fountain::scenes::Vocabulary;
+
+    const TEXT: &str = "\
+# ACT ONE
+
+INT. MESS - NIGHT
+
+Kane eats.
+
+KANE (CONT'D)
+Food!
+
+EXT. NOSTROMO - DAY
+
+DALLAS
+Hello.
+
+# ACT TWO
+
+INT. MESS - DAY
+
+Ripley looks at Kane.
+";
+
+    #[test]
+    fn test_symbols() {
+        let symbols = Analysis::new(TEXT.to_owned()).symbols();
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["ACT ONE", "ACT TWO"]);
+        let act_one = symbols[0].children.as_ref().unwrap();
+        assert_eq!(act_one.len(), 2);
+        assert_eq!(act_one[1].name, "EXT. NOSTROMO - DAY");
+        assert_eq!(act_one[1].detail.as_deref(), Some("Scene 2"));
+        assert_eq!(act_one[1].range.start, Position::new(9, 0));
+        assert_eq!(act_one[1].range.end, Position::new(12, 6));
+        assert_eq!(symbols[1].range.end, Position::new(18, 21));
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let ranges = Analysis::new(TEXT.to_owned()).folding_ranges();
+        let lines: Vec<_> = ranges.iter().map(|r| (r.start_line, r.end_line)).collect();
+        assert_eq!(lines, vec![(0, 12), (2, 7), (9, 12), (14, 18), (16, 18)]);
+    }
+
+    #[test]
+    fn test_find_scenes() {
+        let analysis = Analysis::new(TEXT.to_owned());
+        let uri = Url::parse("file:///alien.fountain").unwrap();
+        let found = analysis.find_scenes(&uri, "mess");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].location.range.start, Position::new(16, 0));
+        assert_eq!(
+            analysis.find_scenes(&uri, "2")[0].name,
+            "EXT. NOSTROMO - DAY"
+        );
+    }
+
+    #[test]
+    fn test_complete_locations() {
+        let text = format!("{}\nINT. NO", TEXT);
+        let analysis = Analysis::new(text);
+        let items = analysis.completions(Position::new(20, 7));
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["MESS", "NOSTROMO"]);
+        match &items[1].text_edit {
+            Some(CompletionTextEdit::Edit(edit)) => {
+                assert_eq!(
+                    edit.range,
+                    Range::new(Position::new(20, 5), Position::new(20, 7))
+                )
+            }
+            _ => panic!("expected a text edit"),
+        }
+        let text = format!("{}\nINT. MESS - N", TEXT);
+        let items = Analysis::new(text).completions(Position::new(20, 13));
+        assert!(items.iter().any(|i| i.label == "NIGHT"));
+    }
+
+    #[test]
+    fn test_language() {
+        let german = Vocabulary::german();
+        let options = ParseOptions::default().with_vocabulary(&german);
+        let text = "INNEN. KÜCHE - NACHT\n\nAnna kocht.\n".to_owned();
+        let analysis = Analysis::with_options(text.clone(), options.clone());
+        assert_eq!(analysis.diagnostics(), vec![]);
+        let analysis = Analysis::with_options(format!("{}\nINNEN. KÜCHE - N", text), options);
+        let items = analysis.completions(Position::new(4, 16));
+        assert!(items.iter().any(|i| i.label == "NACHT"));
+        let items = analysis.completions(Position::new(4, 9));
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["KÜCHE"]);
+    }
+
+    #[test]
+    fn test_complete_speakers() {
+        let text = format!("{}\nDA", TEXT);
+        let analysis = Analysis::new(text);
+        let labels: Vec<_> = analysis
+            .completions(Position::new(20, 2))
+            .into_iter()
+            .map(|i| i.label)
+            .collect();
+        assert_eq!(labels, vec!["KANE", "DALLAS"]);
+        // Not in the middle of a paragraph
+        assert!(analysis.completions(Position::new(7, 2)).is_empty());
+    }
+
+    #[test]
+    fn test_rename() {
+        let analysis = Analysis::new(TEXT.to_owned());
+        let (range, name) = analysis.character_at(Position::new(6, 2)).unwrap();
+        assert_eq!(name, "KANE");
+        assert_eq!(range, Range::new(Position::new(6, 0), Position::new(6, 4)));
+        // Mentions in action count too
+        let (_, name) = analysis.character_at(Position::new(18, 18)).unwrap();
+        assert_eq!(name, "KANE");
+        assert_eq!(analysis.character_at(Position::new(18, 2)), None);
+
+        let edits = analysis.rename(Position::new(6, 2), "Parker").unwrap();
+        let changed: Vec<_> = edits
+            .iter()
+            .map(|e| {
+                (
+                    e.range.start.line,
+                    e.range.start.character,
+                    e.new_text.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![(4, 0, "Parker"), (6, 0, "PARKER"), (18, 16, "Parker")]
+        );
+        assert!(analysis.rename(Position::new(6, 2), "").is_err());
+
+        let analysis = Analysis::new("JOSÉ\nHola.\n\nJosé se sienta.\n".to_owned());
+        let (range, name) = analysis.character_at(Position::new(3, 1)).unwrap();
+        assert_eq!(name, "JOSÉ");
+        assert_eq!(range, Range::new(Position::new(3, 0), Position::new(3, 4)));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostics = Analysis::new("INT. MESS\n\nThe crew eats.\n".to_owned()).diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("missing-time-of-day".to_owned()))
+        );
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 0));
+    }
+}
@@ -0,0 +1,208 @@
+//! A [Language Server Protocol](https://microsoft.github.io/language-server-protocol/) server for
+//! Fountain, which talks to the editor over stdin and stdout. It provides diagnostics from the
+//! linter, an outline of sections and scenes, jumping to a scene by number or heading, completion
+//! of character names and locations, folding, and renaming characters.
+mod analysis;
+mod position;
+
+use analysis::Analysis;
+use fountain::options::ParseOptions;
+use fountain::scenes::Vocabulary;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, FoldingRangeRequest, PrepareRenameRequest, Rename,
+    Request as RequestTrait, WorkspaceSymbolRequest,
+};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, FoldingRangeProviderCapability,
+    OneOf, PrepareRenameResponse, PublishDiagnosticsParams, RenameOptions, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url, WorkspaceEdit, WorkspaceSymbolResponse,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: Default::default(),
+        })),
+        ..Default::default()
+    };
+    let params = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let mut server = Server::default();
+    // e.g. {"language": "german"}, for scripts whose Scene Headings are written in German, and
+    // {"profile": "spec"} or {"profile": "lenient"} for the rules to parse them by.
+    let profile = params["initializationOptions"]["profile"].as_str();
+    server.options = profile
+        .and_then(ParseOptions::for_profile)
+        .unwrap_or_default();
+    let language = params["initializationOptions"]["language"].as_str();
+    if let Some(words) = language.and_then(Vocabulary::for_language) {
+        server.options = server.options.with_vocabulary(&words);
+    }
+    server.run(&connection)?;
+    // The writer thread stops once the connection's sender is dropped.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+/// The open documents, by URI. Each one is reanalyzed whenever it changes.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Analysis>,
+    /// How every document is parsed.
+    options: ParseOptions,
+}
+
+impl Server {
+    fn run(&mut self, connection: &Connection) -> Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(reply) = self.handle_notification(notification) {
+                        connection.sender.send(Message::Notification(reply))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => respond::<DocumentSymbolRequest>(request, |params| {
+                let analysis = self.document(&params.text_document.uri)?;
+                Ok(Some(DocumentSymbolResponse::Nested(analysis.symbols())))
+            }),
+            WorkspaceSymbolRequest::METHOD => {
+                respond::<WorkspaceSymbolRequest>(request, |params| {
+                    let symbols = self
+                        .documents
+                        .iter()
+                        .flat_map(|(uri, analysis)| analysis.find_scenes(uri, &params.query))
+                        .collect();
+                    Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+                })
+            }
+            FoldingRangeRequest::METHOD => respond::<FoldingRangeRequest>(request, |params| {
+                let analysis = self.document(&params.text_document.uri)?;
+                Ok(Some(analysis.folding_ranges()))
+            }),
+            Completion::METHOD => respond::<Completion>(request, |params| {
+                let position = params.text_document_position;
+                let analysis = self.document(&position.text_document.uri)?;
+                let items = analysis.completions(position.position);
+                Ok(Some(CompletionResponse::Array(items)))
+            }),
+            PrepareRenameRequest::METHOD => respond::<PrepareRenameRequest>(request, |params| {
+                let analysis = self.document(&params.text_document.uri)?;
+                let character = analysis.character_at(params.position);
+                Ok(character.map(|(range, placeholder)| {
+                    PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }
+                }))
+            }),
+            Rename::METHOD => respond::<Rename>(request, |params| {
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let edits = self
+                    .document(&uri)?
+                    .rename(position.position, &params.new_name)?;
+                let changes = HashMap::from([(uri, edits)]);
+                Ok(Some(WorkspaceEdit::new(changes)))
+            }),
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", request.method),
+            ),
+        }
+    }
+
+    /// Updates the open documents, and returns the diagnostics to publish for the one that changed.
+    fn handle_notification(&mut self, notification: Notification) -> Option<Notification> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let document = params::<DidOpenTextDocument>(notification)?.text_document;
+                let analysis = Analysis::with_options(document.text, self.options.clone());
+                self.documents.insert(document.uri.clone(), analysis);
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = params::<DidChangeTextDocument>(notification)?;
+                // With full sync, every change holds the whole text.
+                let text = params.content_changes.into_iter().last()?.text;
+                let uri = params.text_document.uri;
+                let analysis = Analysis::with_options(text, self.options.clone());
+                self.documents.insert(uri.clone(), analysis);
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let uri = params::<DidCloseTextDocument>(notification)?
+                    .text_document
+                    .uri;
+                self.documents.remove(&uri);
+                uri
+            }
+            _ => return None,
+        };
+        // A closed document's diagnostics are cleared.
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(Analysis::diagnostics)
+            .unwrap_or_default();
+        Some(Notification::new(
+            PublishDiagnostics::METHOD.to_owned(),
+            PublishDiagnosticsParams::new(uri, diagnostics, None),
+        ))
+    }
+
+    fn document(&self, uri: &Url) -> std::result::Result<&Analysis, String> {
+        self.documents
+            .get(uri)
+            .ok_or_else(|| format!("{} isn't open", uri))
+    }
+}
+
+/// Deserializes a request's parameters, and responds with the handler's result or error.
+fn respond<R: RequestTrait>(
+    request: Request,
+    handler: impl FnOnce(R::Params) -> std::result::Result<R::Result, String>,
+) -> Response {
+    let params = match serde_json::from_value(request.params) {
+        Ok(params) => params,
+        Err(e) => {
+            return Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string())
+        }
+    };
+    match handler(params) {
+        Ok(result) => Response::new_ok(request.id, result),
+        Err(message) => Response::new_err(request.id, ErrorCode::RequestFailed as i32, message),
+    }
+}
+
+fn params<N: NotificationTrait>(notification: Notification) -> Option<N::Params> {
+    serde_json::from_value(notification.params).ok()
+}
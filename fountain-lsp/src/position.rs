@@ -0,0 +1,90 @@
+use fountain::source::Span;
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets into a document's text and LSP positions, whose lines count from
+/// 0 and whose columns count UTF-16 code units.
+pub struct LineIndex {
+    /// Byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { starts }
+    }
+
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        let character = text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, text: &str, span: Span) -> Range {
+        Range::new(
+            self.position(text, span.start),
+            self.position(text, span.end),
+        )
+    }
+
+    /// The byte offset of a position, clamped to the end of its line (or of the text).
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let line = position.line as usize;
+        let start = match self.starts.get(line) {
+            Some(&start) => start,
+            None => return text.len(),
+        };
+        let end = self.line_end(text, line);
+        let mut units = 0;
+        for (i, c) in text[start..end].char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+
+    /// The text of the line containing the offset, without its line ending, and where it starts.
+    pub fn line_at<'a>(&self, text: &'a str, offset: usize) -> (usize, &'a str) {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        (start, &text[start..self.line_end(text, line)])
+    }
+
+    fn line_end(&self, text: &str, line: usize) -> usize {
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(text.len(), |&next| next - 1);
+        if text[..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "INT. CAFÉ\r\n\n😀 KANE\n";
+        let index = LineIndex::new(text);
+        assert_eq!(index.position(text, 0), Position::new(0, 0));
+        assert_eq!(index.position(text, 10), Position::new(0, 9));
+        assert_eq!(index.offset(text, Position::new(0, 9)), 10);
+        // Past the end of the line
+        assert_eq!(index.offset(text, Position::new(0, 40)), 10);
+        // The emoji is two UTF-16 code units
+        assert_eq!(index.position(text, 18), Position::new(2, 3));
+        assert_eq!(index.offset(text, Position::new(2, 3)), 18);
+        assert_eq!(index.line_at(text, 19), (13, "😀 KANE"));
+        assert_eq!(index.offset(text, Position::new(9, 0)), text.len());
+    }
+}
@@ -0,0 +1,87 @@
+//! Benchmarks for parsing and rendering a feature-length screenplay. Run them with `cargo bench`.
+//! Each benchmark reports its throughput in bytes of Fountain per second; the targets it should
+//! stay above are listed in the README.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fountain::events::Events;
+use nom::error::ErrorKind;
+
+/// Builds a screenplay of about 120 pages, with the mix of elements a real one has: mostly action
+/// and dialogue, some parentheticals, dual dialogue, transitions, lyrics and sections.
+fn feature_length() -> String {
+    let characters = [
+        "RIPLEY", "DALLAS", "KANE", "LAMBERT", "PARKER", "BRETT", "ASH",
+    ];
+    let locations = [
+        "MESS",
+        "BRIDGE",
+        "INFIRMARY",
+        "CARGO HOLD",
+        "AIRLOCK",
+        "ENGINE ROOM",
+    ];
+    let mut text =
+        String::from("Title: The Long Night\nAuthor: A. Writer\nDraft date: 1/1/2020\n\n");
+    for scene in 0..150 {
+        if scene % 40 == 0 {
+            text += &format!("# ACT {}\n\n", scene / 40 + 1);
+        }
+        let time = if scene % 3 == 0 { "NIGHT" } else { "DAY" };
+        text += &format!("INT. {} - {}\n\n", locations[scene % locations.len()], time);
+        text += "The lights flicker. Somewhere below, the engines labor against the dark, and \
+                 the crew moves through the corridors without speaking.\n\n";
+        for exchange in 0..8 {
+            let speaker = characters[(scene + exchange) % characters.len()];
+            if exchange % 5 == 4 {
+                let other = characters[(scene + exchange + 1) % characters.len()];
+                text += &format!(
+                    "{}\nWe should go back.\n\n{} ^\nWe can't go back.\n\n",
+                    speaker, other
+                );
+            } else if exchange % 3 == 0 {
+                text += &format!(
+                    "{}\n(quietly)\nDid you hear that? It came from the vents, I'm sure of it.\n\n",
+                    speaker
+                );
+            } else {
+                text += &format!(
+                    "{}\nI don't know what you want me to say. We checked every deck twice.\n\n",
+                    speaker
+                );
+            }
+            if exchange % 4 == 1 {
+                text += &format!(
+                    "{} crosses to the console and studies the readout.\n\n",
+                    speaker
+                );
+            }
+        }
+        if scene % 10 == 9 {
+            text += "~Happy birthday to you\n\n";
+        }
+        text += "CUT TO:\n\n";
+    }
+    text += "FADE OUT.\n";
+    text
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let text = feature_length();
+    let (_, doc) = fountain::parse_document::<(&str, ErrorKind)>(&text).unwrap();
+
+    let mut group = c.benchmark_group("feature length");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("parse_document", |b| {
+        b.iter(|| fountain::parse_document::<(&str, ErrorKind)>(black_box(&text)))
+    });
+    group.bench_function("parse_document_borrowed", |b| {
+        b.iter(|| fountain::parse_document_borrowed::<(&str, ErrorKind)>(black_box(&text)))
+    });
+    group.bench_function("events", |b| {
+        b.iter(|| Events::new(black_box(text.as_bytes())).count())
+    });
+    group.bench_function("as_html", |b| b.iter(|| black_box(&doc).as_html()));
+    group.finish();
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
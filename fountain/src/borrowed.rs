@@ -0,0 +1,220 @@
+//! Zero-copy versions of the `data` types, which borrow their text from the parsed input instead of
+//! allocating a `String` for every line. Parse them with `parse_document_borrowed`, and convert them
+//! with `into_owned` if you need a `data::Document` that outlives the input.
+//!
+//! ```
+//! use fountain::borrowed::Line;
+//! use nom::error::VerboseError;
+//!
+//! let text = "KANE\nFood!\n";
+//! let (_, doc) = fountain::parse_document_borrowed::<VerboseError<&str>>(text).unwrap();
+//! assert_eq!(doc.lines[1], Line::Dialogue("Food!"));
+//! // The dialogue points into the input text.
+//! assert_eq!(doc.lines[1].text().as_ptr(), text[5..].as_ptr());
+//! assert_eq!(doc.into_owned(), fountain::parse_document::<VerboseError<&str>>(text).unwrap().1);
+//! ```
+use crate::data;
+use crate::prelude::*;
+#[cfg(feature = "use_serde")]
+use serde::Serialize;
+
+/// A line of a screenplay, borrowed from the text it was parsed from. See `data::Line` for what
+/// each variant means.
+/// This will impl Serialize if the feature "use_serde" is specified, as the same JSON as `data::Line`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub enum Line<'a> {
+    Scene(&'a str),
+    Action(&'a str),
+    Dialogue(&'a str),
+    Speaker { name: &'a str, is_dual: bool },
+    Parenthetical(&'a str),
+    Transition(&'a str),
+    Lyric(&'a str),
+    Section { title: &'a str, depth: usize },
+}
+
+impl<'a> Line<'a> {
+    /// The line's text, without any Fountain markup. For a Speaker this is their name, and for a
+    /// Section it's the title.
+    pub fn text(&self) -> &'a str {
+        match *self {
+            Line::Scene(s)
+            | Line::Action(s)
+            | Line::Dialogue(s)
+            | Line::Parenthetical(s)
+            | Line::Transition(s)
+            | Line::Lyric(s) => s,
+            Line::Speaker { name, .. } => name,
+            Line::Section { title, .. } => title,
+        }
+    }
+
+    pub fn into_owned(self) -> data::Line {
+        match self {
+            Line::Scene(s) => data::Line::Scene(s.to_owned()),
+            Line::Action(s) => data::Line::Action(s.to_owned()),
+            Line::Dialogue(s) => data::Line::Dialogue(s.to_owned()),
+            Line::Speaker { name, is_dual } => data::Line::Speaker {
+                name: name.to_owned(),
+                is_dual,
+            },
+            Line::Parenthetical(s) => data::Line::Parenthetical(s.to_owned()),
+            Line::Transition(s) => data::Line::Transition(s.to_owned()),
+            Line::Lyric(s) => data::Line::Lyric(s.to_owned()),
+            Line::Section { title, depth } => data::Line::Section {
+                title: title.to_owned(),
+                depth,
+            },
+        }
+    }
+}
+
+impl<'a> From<Line<'a>> for data::Line {
+    fn from(line: Line<'a>) -> Self {
+        line.into_owned()
+    }
+}
+
+/// Compares without copying, so a parsed line can be checked against an owned one.
+impl<'a> PartialEq<data::Line> for Line<'a> {
+    fn eq(&self, other: &data::Line) -> bool {
+        use data::Line as Owned;
+        match (*self, other) {
+            (Line::Scene(a), Owned::Scene(b))
+            | (Line::Action(a), Owned::Action(b))
+            | (Line::Dialogue(a), Owned::Dialogue(b))
+            | (Line::Parenthetical(a), Owned::Parenthetical(b))
+            | (Line::Transition(a), Owned::Transition(b))
+            | (Line::Lyric(a), Owned::Lyric(b)) => a == b,
+            (
+                Line::Speaker { name, is_dual },
+                Owned::Speaker {
+                    name: n,
+                    is_dual: d,
+                },
+            ) => name == n && is_dual == *d,
+            (Line::Section { title, depth }, Owned::Section { title: t, depth: d }) => {
+                title == t && depth == *d
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A document's title page, borrowed from the text it was parsed from.
+/// This will impl Serialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct TitlePage<'a> {
+    /// Document author
+    pub author: Option<&'a str>,
+    /// Document title
+    pub title: Option<&'a str>,
+    /// Other items, stored as a vec of key-value pairs.
+    pub other: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> TitlePage<'a> {
+    pub fn into_owned(self) -> data::TitlePage {
+        data::TitlePage {
+            author: self.author.map(str::to_owned),
+            title: self.title.map(str::to_owned),
+            other: self
+                .other
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<TitlePage<'a>> for data::TitlePage {
+    fn from(titlepage: TitlePage<'a>) -> Self {
+        titlepage.into_owned()
+    }
+}
+
+/// An entire screenplay, borrowed from the text it was parsed from.
+/// This will impl Serialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct Document<'a> {
+    /// Every line after the title page, in order.
+    pub lines: Vec<Line<'a>>,
+    pub titlepage: TitlePage<'a>,
+}
+
+impl<'a> Document<'a> {
+    /// Copies the document's text, so that it no longer borrows the input.
+    pub fn into_owned(self) -> data::Document {
+        data::Document {
+            lines: self.lines.into_iter().map(Line::into_owned).collect(),
+            titlepage: self.titlepage.into_owned(),
+        }
+    }
+}
+
+impl<'a> From<Document<'a>> for data::Document {
+    fn from(doc: Document<'a>) -> Self {
+        doc.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::ErrorKind;
+
+    const SCREENPLAY: &str = "\
+Title: Alien
+Author: Dan O'Bannon
+Draft: 8
+
+# ACT ONE
+
+INT. MESS
+
+KANE ^
+(eating)
+Food!
+
+~Happy birthday
+
+CUT TO:
+";
+
+    #[test]
+    fn test_into_owned_matches_owned_parse() {
+        let (_, borrowed) =
+            crate::parse_document_borrowed::<(&str, ErrorKind)>(SCREENPLAY).unwrap();
+        let (_, owned) = crate::parse_document::<(&str, ErrorKind)>(SCREENPLAY).unwrap();
+        assert_eq!(borrowed.titlepage.other, vec![("Draft", "8")]);
+        assert_eq!(borrowed.into_owned(), owned);
+    }
+
+    #[test]
+    fn test_lines_borrow_from_input() {
+        let (_, doc) = crate::parse_document_borrowed::<(&str, ErrorKind)>(SCREENPLAY).unwrap();
+        let range = SCREENPLAY.as_bytes().as_ptr_range();
+        for line in &doc.lines {
+            assert!(range.contains(&line.text().as_ptr()), "{:?}", line);
+        }
+        assert_eq!(
+            doc.lines[2],
+            Line::Speaker {
+                name: "KANE",
+                is_dual: true
+            }
+        );
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_serializes_like_owned() {
+        let (_, doc) = crate::parse_document_borrowed::<(&str, ErrorKind)>(SCREENPLAY).unwrap();
+        let borrowed = serde_json::to_value(&doc).unwrap();
+        let owned = serde_json::to_value(doc.into_owned()).unwrap();
+        assert_eq!(borrowed, owned);
+    }
+}
@@ -0,0 +1,159 @@
+//! Scene breakdowns, the starting point for scheduling a production.
+use super::characters::normalize_name;
+use super::data::*;
+use super::lock::Lock;
+use super::pages::{Eighths, Pagination};
+use super::prelude::*;
+use super::scenes::{scene_number, Scene, SceneHeading, Vocabulary};
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// Everything a production needs to know about one scene in order to schedule it.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct SceneBreakdown {
+    /// The scene's position in the document, counting from 1 (see `Document::scenes`).
+    pub scene: usize,
+    /// The scene number printed in the script: either the heading's explicit scene number, or
+    /// the scene's position. Locked scripts use their locked scene numbers instead.
+    pub number: String,
+    pub heading: SceneHeading,
+    /// The printed number of the page the scene starts on, e.g. "12" or "12A".
+    pub page: String,
+    /// How long the scene is.
+    pub length: Eighths,
+    /// Normalized names of every character who speaks in the scene, in order of first appearance.
+    pub characters: Vec<String>,
+}
+
+impl Document {
+    /// Breaks down every scene in the document.
+    pub fn scene_breakdown(&self) -> Vec<SceneBreakdown> {
+        self.scene_breakdown_with(&Vocabulary::english())
+    }
+
+    /// Like `scene_breakdown`, for Scene Headings written with the given words.
+    pub fn scene_breakdown_with(&self, words: &Vocabulary) -> Vec<SceneBreakdown> {
+        self.breakdown_from(&self.paginate(), words, |scene| {
+            scene_number(scene.heading).map_or_else(|| scene.number.to_string(), str::to_owned)
+        })
+    }
+
+    /// Breaks down every scene in the document, using the scene and page numbers from a locked
+    /// draft (see `Document::locked_scene_numbers`). OMITTED scenes aren't included.
+    pub fn locked_scene_breakdown(&self, lock: &Lock) -> Vec<SceneBreakdown> {
+        self.locked_scene_breakdown_with(lock, &Vocabulary::english())
+    }
+
+    /// Like `locked_scene_breakdown`, for Scene Headings written with the given words.
+    pub fn locked_scene_breakdown_with(
+        &self,
+        lock: &Lock,
+        words: &Vocabulary,
+    ) -> Vec<SceneBreakdown> {
+        let numbers = self.locked_scene_numbers(lock);
+        self.breakdown_from(&self.paginate_locked(lock), words, |scene| {
+            numbers
+                .iter()
+                .find(|n| n.scene == Some(scene.number))
+                .map_or_else(|| scene.number.to_string(), |n| n.number.clone())
+        })
+    }
+
+    fn breakdown_from<F>(
+        &self,
+        pagination: &Pagination,
+        words: &Vocabulary,
+        number: F,
+    ) -> Vec<SceneBreakdown>
+    where
+        F: Fn(&Scene) -> String,
+    {
+        self.scenes()
+            .into_iter()
+            .map(|scene| {
+                let heading = scene.parsed_heading_with(words);
+                let mut characters: Vec<String> = Vec::new();
+                for line in scene.lines {
+                    if let Line::Speaker { name, .. } = line {
+                        let name = normalize_name(name);
+                        if !characters.contains(&name) {
+                            characters.push(name);
+                        }
+                    }
+                }
+                SceneBreakdown {
+                    scene: scene.number,
+                    number: number(&scene),
+                    heading,
+                    page: pagination
+                        .page_of(scene.start)
+                        .map_or_else(String::new, |p| p.label.clone()),
+                    length: pagination.eighths(scene.start..scene.end()),
+                    characters,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::Setting;
+
+    #[test]
+    fn test_scene_breakdown() {
+        let doc = Document {
+            lines: vec![
+                Line::Scene("INT. MESS - NIGHT".to_owned()),
+                Line::Action("The crew eats.".to_owned()),
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false,
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Speaker {
+                    name: "KANE (CONT'D)".to_owned(),
+                    is_dual: false,
+                },
+                Line::Dialogue("Decent food!".to_owned()),
+                Line::Scene("EXT. NOSTROMO - SPACE #7#".to_owned()),
+                Line::Action("The ship drifts.".to_owned()),
+            ],
+            ..Default::default()
+        };
+        let breakdown = doc.scene_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[0].number, "1");
+        assert_eq!(breakdown[0].heading.setting, Some(Setting::Interior));
+        assert_eq!(breakdown[0].heading.location, "MESS");
+        assert_eq!(breakdown[0].heading.time, Some("NIGHT".to_owned()));
+        assert_eq!(breakdown[0].page, "1");
+        assert_eq!(breakdown[0].length, Eighths(1));
+        assert_eq!(breakdown[0].characters, vec!["KANE"]);
+        assert_eq!(breakdown[1].scene, 2);
+        assert_eq!(breakdown[1].number, "7");
+        assert_eq!(breakdown[1].heading.sub_location, Some("SPACE".to_owned()));
+        assert!(breakdown[1].characters.is_empty());
+    }
+
+    #[test]
+    fn test_scene_breakdown_with() {
+        let doc = Document {
+            lines: vec![
+                Line::Scene("INNEN. KÜCHE - NACHT #3#".to_owned()),
+                Line::Action("Anna kocht.".to_owned()),
+            ],
+            ..Default::default()
+        };
+        let breakdown = doc.scene_breakdown_with(&Vocabulary::german());
+        assert_eq!(breakdown[0].number, "3");
+        assert_eq!(breakdown[0].heading.setting, Some(Setting::Interior));
+        assert_eq!(breakdown[0].heading.location, "KÜCHE");
+        assert_eq!(breakdown[0].heading.time, Some("NACHT".to_owned()));
+        // The number doesn't depend on the words the heading is written with.
+        assert_eq!(doc.scene_breakdown()[0].number, "3");
+    }
+}
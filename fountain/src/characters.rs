@@ -0,0 +1,165 @@
+//! Builds an index of every character who speaks in a Fountain document.
+use super::data::*;
+use super::prelude::*;
+use super::utils::*;
+use alloc::collections::BTreeMap;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// Everything we know about one character, gathered from their Speaker lines.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Character {
+    /// The normalized name, e.g. "KANE" for both "KANE (V.O.)" and "KANE (CONT'D)".
+    pub name: String,
+    /// Every distinct Speaker name which was normalized into this character, in order of first use.
+    pub aliases: Vec<String>,
+    /// How many times this character speaks.
+    pub speeches: usize,
+    /// How many words of dialogue this character speaks.
+    pub words: usize,
+    /// Numbers of the scenes (see `Document::scenes`) in which this character speaks.
+    pub scenes: Vec<usize>,
+    /// Index into `Document::lines` of this character's first Speaker line.
+    pub first_appearance: usize,
+    /// How many of this character's speeches are part of a Dual Dialogue.
+    pub dual_dialogues: usize,
+}
+
+/// Normalizes a Speaker name so that the same character is always given the same name, by removing
+/// the dual dialogue caret and any extensions like (V.O.), (O.S.) or (CONT'D).
+/// ```
+/// use fountain::characters::normalize_name;
+/// assert_eq!(normalize_name("KANE (V.O.)"), "KANE");
+/// assert_eq!(normalize_name("MRS.  THOMPSON (CONT'D) ^"), "MRS. THOMPSON");
+/// ```
+pub fn normalize_name(name: &str) -> String {
+    let mut name = name.trim().trim_end_matches('^').trim_end();
+    while name.ends_with(')') {
+        match name.rfind('(') {
+            Some(open) => name = name[..open].trim_end(),
+            None => break,
+        }
+    }
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl Document {
+    /// Returns every character who speaks in the document, ordered by how often they speak (most
+    /// frequent first). Characters who speak equally often are ordered by first appearance.
+    pub fn characters(&self) -> Vec<Character> {
+        let mut index: BTreeMap<String, Character> = BTreeMap::new();
+        let mut scene = None;
+        for (i, line) in self.lines.iter().enumerate() {
+            let (raw_name, is_dual) = match line {
+                Line::Scene(_) => {
+                    scene = Some(scene.unwrap_or(0) + 1);
+                    continue;
+                }
+                Line::Speaker { name, is_dual } => (name, *is_dual),
+                _ => continue,
+            };
+            let name = normalize_name(raw_name);
+            let character = index.entry(name.clone()).or_insert_with(|| Character {
+                name,
+                aliases: Vec::new(),
+                speeches: 0,
+                words: 0,
+                scenes: Vec::new(),
+                first_appearance: i,
+                dual_dialogues: 0,
+            });
+            if !character.aliases.contains(raw_name) {
+                character.aliases.push(raw_name.to_owned());
+            }
+            character.speeches += 1;
+            character.words += self.lines[i + 1..]
+                .iter()
+                .take_while(|l| l.is_parenthetical() || l.is_dialogue())
+                .map(|l| match l {
+                    Line::Dialogue(s) => s.split_whitespace().count(),
+                    _ => 0,
+                })
+                .sum::<usize>();
+            if let Some(scene) = scene {
+                if character.scenes.last() != Some(&scene) {
+                    character.scenes.push(scene);
+                }
+            }
+            if is_dual {
+                character.dual_dialogues += 1;
+                // The speaker before the caret is the other half of the Dual Dialogue.
+                if let Some(Line::Speaker { name, .. }) =
+                    position_before(&self.lines, i, |l| l.is_speaker()).map(|p| &self.lines[p])
+                {
+                    if let Some(partner) = index.get_mut(&normalize_name(name)) {
+                        partner.dual_dialogues += 1;
+                    }
+                }
+            }
+        }
+        let mut characters: Vec<Character> = index.into_values().collect();
+        characters.sort_by_key(|c| (core::cmp::Reverse(c.speeches), c.first_appearance));
+        characters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speaker(name: &str, is_dual: bool) -> Line {
+        Line::Speaker {
+            name: name.to_owned(),
+            is_dual,
+        }
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("KANE"), "KANE");
+        assert_eq!(normalize_name("KANE (V.O.)"), "KANE");
+        assert_eq!(normalize_name("KANE (CONT'D)"), "KANE");
+        assert_eq!(normalize_name("KANE (O.S.) (CONT'D)"), "KANE");
+        assert_eq!(normalize_name("BRETT ^"), "BRETT");
+        assert_eq!(normalize_name(" ASH  "), "ASH");
+    }
+
+    #[test]
+    fn test_characters() {
+        let doc = Document {
+            lines: vec![
+                Line::Scene("INT. MESS".to_owned()),
+                speaker("KANE", false),
+                Line::Dialogue("I'm going to eat some decent food.".to_owned()),
+                speaker("PARKER", false),
+                Line::Dialogue("Hungry?".to_owned()),
+                Line::Scene("INT. BRIDGE".to_owned()),
+                speaker("KANE (V.O.)", false),
+                Line::Parenthetical("over the radio".to_owned()),
+                Line::Dialogue("Can you hear me?".to_owned()),
+                speaker("PARKER", false),
+                Line::Dialogue("Yes.".to_owned()),
+                speaker("KANE (CONT'D)", true),
+                Line::Dialogue("Good.".to_owned()),
+            ],
+            ..Default::default()
+        };
+        let characters = doc.characters();
+        assert_eq!(characters.len(), 2);
+        let kane = &characters[0];
+        assert_eq!(kane.name, "KANE");
+        assert_eq!(kane.aliases, vec!["KANE", "KANE (V.O.)", "KANE (CONT'D)"]);
+        assert_eq!(kane.speeches, 3);
+        assert_eq!(kane.words, 12);
+        assert_eq!(kane.scenes, vec![1, 2]);
+        assert_eq!(kane.first_appearance, 1);
+        assert_eq!(kane.dual_dialogues, 1);
+        let parker = &characters[1];
+        assert_eq!(parker.name, "PARKER");
+        assert_eq!(parker.speeches, 2);
+        assert_eq!(parker.first_appearance, 3);
+        assert_eq!(parker.dual_dialogues, 1);
+    }
+}
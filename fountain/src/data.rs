@@ -2,6 +2,8 @@
 //! If you'd like these types to derive `Serialize` and `Deserialize` using `serde`, please set your
 //! dependency on `fountain` to use the `use_serde` feature:
 //! `fountain = { version = <target version>, features = ["use_serde"] }`
+//! The JSON they serialize to is versioned and documented in the `schema` module.
+use super::prelude::*;
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Serialize};
 
@@ -9,10 +11,12 @@ use serde::{Deserialize, Serialize};
 /// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
 #[derive(PartialEq, Eq, Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
 pub enum Line {
     /// A [Scene Heading](https://fountain.io/syntax#section-slug) is any line that has a blank line
-    /// following it, and either begins with INT or EXT. A Scene Heading always has at least one
-    /// blank line preceding it.
+    /// following it, and begins with INT, EXT, EST, INT./EXT, INT/EXT or I/E. A Scene Heading
+    /// always has at least one blank line preceding it. Use `scenes::SceneHeading::parse` to split
+    /// it into its parts.
     Scene(String),
     /// [Action](https://fountain.io/syntax#section-action), or scene description, is any paragraph
     /// that doesn't meet criteria for another element (e.g. Scene Heading, Speaker, etc.)
@@ -33,6 +37,10 @@ pub enum Line {
     Transition(String),
     /// [Lyrics](https://fountain.io/syntax#section-lyrics) are lines starting with a tilde (~).
     Lyric(String),
+    /// [Sections](https://fountain.io/syntax#section-sections) are lines starting with one or more
+    /// pound-signs (#), which organize the screenplay into e.g. acts and sequences. The `depth` is
+    /// the number of pound-signs, so "# ACT ONE" has depth 1. Sections aren't printed.
+    Section { title: String, depth: usize },
 }
 
 impl Line {
@@ -57,6 +65,23 @@ impl Line {
     pub fn is_lyric(&self) -> bool {
         matches!(self, Line::Lyric(_))
     }
+    pub fn is_section(&self) -> bool {
+        matches!(self, Line::Section { .. })
+    }
+    /// The line's text, without any Fountain markup. For a Speaker this is their name, and for a
+    /// Section it's the title.
+    pub fn text(&self) -> &str {
+        match self {
+            Line::Scene(s)
+            | Line::Action(s)
+            | Line::Dialogue(s)
+            | Line::Parenthetical(s)
+            | Line::Transition(s)
+            | Line::Lyric(s) => s,
+            Line::Speaker { name, .. } => name,
+            Line::Section { title, .. } => title,
+        }
+    }
 }
 
 /// Defines a document's title page.
@@ -86,6 +111,7 @@ impl Line {
 /// ```
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
 pub struct TitlePage {
     /// Document author
     pub author: Option<String>,
@@ -99,7 +125,9 @@ pub struct TitlePage {
 /// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
 pub struct Document {
+    /// Every line after the title page, in order.
     pub lines: Vec<Line>,
     pub titlepage: TitlePage,
 }
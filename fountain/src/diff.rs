@@ -0,0 +1,554 @@
+//! Compares two drafts of a screenplay scene by scene, instead of line by line like a text diff.
+//!
+//! Scenes from the old draft are matched with scenes from the new draft. Scenes with the same
+//! explicit scene number (e.g. #12#) always match. Other scenes match the most similar unmatched
+//! scene, as long as they're similar enough. Matched scenes are then compared line by line.
+use super::characters::normalize_name;
+use super::data::*;
+use super::prelude::*;
+use alloc::collections::BTreeSet;
+use core::ops::Range;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// Scenes which share less than this fraction of their lines aren't considered the same scene.
+const MIN_SIMILARITY: f64 = 0.4;
+
+/// What happened to a scene between the old and new drafts.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum SceneStatus {
+    Unchanged,
+    /// The scene is in the same place, but some of its lines changed.
+    Changed,
+    /// The scene is somewhere else relative to the other scenes. Its lines may have changed too.
+    Moved,
+    Added,
+    Removed,
+}
+
+/// One line-level edit within a scene. Indices are into `Document::lines`.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum LineEdit {
+    /// The line is in both drafts.
+    Equal { old: usize, new: usize },
+    /// The line is only in the new draft.
+    Insert { new: usize },
+    /// The line is only in the old draft.
+    Delete { old: usize },
+}
+
+/// A speech which was added, removed or reworded.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct DialogueChange {
+    /// The normalized name of the character speaking.
+    pub speaker: String,
+    /// None if the dialogue was added.
+    pub old: Option<String>,
+    /// None if the dialogue was removed.
+    pub new: Option<String>,
+}
+
+/// How one scene changed between drafts.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct SceneDiff {
+    pub status: SceneStatus,
+    /// The scene's number in the old draft (see `Document::scenes`). None if it was added. Lines
+    /// before the first Scene Heading are treated as scene 0.
+    pub old: Option<usize>,
+    /// The scene's number in the new draft. None if it was removed.
+    pub new: Option<usize>,
+    /// The scene's heading in the new draft, or the old draft if it was removed.
+    pub heading: String,
+    /// Every line of the scene in both drafts, in order.
+    pub edits: Vec<LineEdit>,
+    pub dialogue: Vec<DialogueChange>,
+}
+
+/// The differences between two drafts of a document.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct DocumentDiff {
+    /// Every scene in the new draft in order, with removed scenes placed after the scene which
+    /// preceded them in the old draft.
+    pub scenes: Vec<SceneDiff>,
+}
+
+impl DocumentDiff {
+    /// True if the drafts have exactly the same lines.
+    pub fn is_unchanged(&self) -> bool {
+        self.scenes
+            .iter()
+            .all(|s| s.status == SceneStatus::Unchanged)
+    }
+
+    /// Indices into the new draft's lines of every line which was added or changed.
+    pub fn changed_lines(&self) -> BTreeSet<usize> {
+        self.scenes
+            .iter()
+            .flat_map(|s| &s.edits)
+            .filter_map(|edit| match edit {
+                LineEdit::Insert { new } => Some(*new),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A scene, or the lines before the first scene.
+struct Segment<'a> {
+    number: usize,
+    heading: &'a str,
+    lines: Range<usize>,
+}
+
+fn segments(doc: &Document) -> Vec<Segment<'_>> {
+    let scenes = doc.scenes();
+    let preamble_end = scenes.first().map_or(doc.lines.len(), |s| s.start);
+    let preamble = Segment {
+        number: 0,
+        heading: "",
+        lines: 0..preamble_end,
+    };
+    core::iter::once(preamble)
+        .chain(scenes.iter().map(|s| Segment {
+            number: s.number,
+            heading: s.heading,
+            lines: s.start..s.end(),
+        }))
+        .collect()
+}
+
+/// Compares two drafts of a document.
+/// ```
+/// use fountain::diff::{diff, SceneStatus};
+/// use nom::error::VerboseError;
+///
+/// let old = "INT. MESS - NIGHT\n\nKANE\nFood!\n";
+/// let new = "INT. MESS - NIGHT\n\nKANE\nDecent food!\n";
+/// let old = fountain::parse_document::<VerboseError<&str>>(old).unwrap().1;
+/// let new = fountain::parse_document::<VerboseError<&str>>(new).unwrap().1;
+/// let diff = diff(&old, &new);
+/// let changed: Vec<_> = diff.scenes.iter().filter(|s| s.status != SceneStatus::Unchanged).collect();
+/// assert_eq!(changed.len(), 1);
+/// assert_eq!(changed[0].dialogue[0].speaker, "KANE");
+/// assert_eq!(changed[0].dialogue[0].new, Some("Decent food!".to_owned()));
+/// ```
+pub fn diff(old: &Document, new: &Document) -> DocumentDiff {
+    let old_segments = segments(old);
+    let new_segments = segments(new);
+    let pairs = match_segments(old, &old_segments, new, &new_segments);
+
+    // Matched scenes which aren't in the longest run of scenes kept in order have moved.
+    let in_order = longest_increasing(&pairs.iter().map(|p| p.0).collect::<Vec<_>>());
+
+    let mut scenes = Vec::new();
+    let mut matched_old = vec![None; old_segments.len()];
+    for (n, &(o, _)) in pairs.iter().enumerate() {
+        matched_old[o] = Some(n);
+    }
+    let mut new_to_old = vec![None; new_segments.len()];
+    for &(o, n) in &pairs {
+        new_to_old[n] = Some(o);
+    }
+    let mut removed_after: Vec<Vec<usize>> = vec![Vec::new(); new_segments.len()];
+    let mut last_matched_new = 0;
+    for (o, matched) in matched_old.iter().enumerate() {
+        match matched {
+            Some(n) => last_matched_new = pairs[*n].1,
+            None => removed_after[last_matched_new].push(o),
+        }
+    }
+
+    for (n, segment) in new_segments.iter().enumerate() {
+        if let Some(o) = new_to_old[n] {
+            let old_segment = &old_segments[o];
+            let edits = diff_lines(old, old_segment.lines.clone(), new, segment.lines.clone());
+            let changed = edits.iter().any(|e| !matches!(e, LineEdit::Equal { .. }));
+            let pair_index = pairs.iter().position(|&p| p == (o, n)).unwrap();
+            let status = if !in_order.contains(&pair_index) {
+                SceneStatus::Moved
+            } else if changed {
+                SceneStatus::Changed
+            } else {
+                SceneStatus::Unchanged
+            };
+            scenes.push(SceneDiff {
+                status,
+                old: Some(old_segment.number),
+                new: Some(segment.number),
+                heading: segment.heading.to_owned(),
+                dialogue: dialogue_changes(old, new, &edits),
+                edits,
+            });
+        } else if !segment.lines.is_empty() {
+            let edits: Vec<_> = segment
+                .lines
+                .clone()
+                .map(|new| LineEdit::Insert { new })
+                .collect();
+            scenes.push(SceneDiff {
+                status: SceneStatus::Added,
+                old: None,
+                new: Some(segment.number),
+                heading: segment.heading.to_owned(),
+                dialogue: dialogue_changes(old, new, &edits),
+                edits,
+            });
+        }
+        for &o in &removed_after[n] {
+            let segment = &old_segments[o];
+            if segment.lines.is_empty() {
+                continue;
+            }
+            let edits: Vec<_> = segment
+                .lines
+                .clone()
+                .map(|old| LineEdit::Delete { old })
+                .collect();
+            scenes.push(SceneDiff {
+                status: SceneStatus::Removed,
+                old: Some(segment.number),
+                new: None,
+                heading: segment.heading.to_owned(),
+                dialogue: dialogue_changes(old, new, &edits),
+                edits,
+            });
+        }
+    }
+    // Documents without a title page or preamble would otherwise always have an empty scene 0.
+    scenes.retain(|s| !s.edits.is_empty());
+    DocumentDiff { scenes }
+}
+
+/// Pairs up old and new segments, returning (old index, new index) pairs sorted by new index.
+fn match_segments(
+    old: &Document,
+    old_segments: &[Segment],
+    new: &Document,
+    new_segments: &[Segment],
+) -> Vec<(usize, usize)> {
+    // The lines before the first scene always match each other.
+    let mut pairs = vec![(0, 0)];
+    let old_matched = |pairs: &[(usize, usize)], o: usize| pairs.iter().any(|p| p.0 == o);
+    let new_matched = |pairs: &[(usize, usize)], n: usize| pairs.iter().any(|p| p.1 == n);
+
+    // Scenes with the same explicit scene number match.
+    let number = |doc: &Document, s: &Segment| -> Option<String> {
+        match &doc.lines[s.lines.start] {
+            Line::Scene(h) => crate::scenes::scene_number(h).map(str::to_owned),
+            _ => None,
+        }
+    };
+    for (n, new_segment) in new_segments.iter().enumerate().skip(1) {
+        if let Some(num) = number(new, new_segment) {
+            let found =
+                old_segments.iter().enumerate().skip(1).find(|&(o, s)| {
+                    !old_matched(&pairs, o) && number(old, s).as_ref() == Some(&num)
+                });
+            if let Some((o, _)) = found {
+                pairs.push((o, n));
+            }
+        }
+    }
+
+    // Everything else matches the most similar scene, best matches first.
+    let mut candidates = Vec::new();
+    for (o, old_segment) in old_segments.iter().enumerate().skip(1) {
+        for (n, new_segment) in new_segments.iter().enumerate().skip(1) {
+            if old_matched(&pairs, o) || new_matched(&pairs, n) {
+                continue;
+            }
+            let score = similarity(
+                &old.lines[old_segment.lines.clone()],
+                &new.lines[new_segment.lines.clone()],
+            );
+            if score >= MIN_SIMILARITY {
+                candidates.push((score, o, n));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .unwrap()
+            .then((a.1, a.2).cmp(&(b.1, b.2)))
+    });
+    for (_, o, n) in candidates {
+        if !old_matched(&pairs, o) && !new_matched(&pairs, n) {
+            pairs.push((o, n));
+        }
+    }
+    pairs.sort_by_key(|&(_, n)| n);
+    pairs
+}
+
+/// What fraction of their lines two scenes have in common, from 0 to 1.
+fn similarity(a: &[Line], b: &[Line]) -> f64 {
+    let common = lcs_table(a, b)[0][0];
+    (2 * common) as f64 / (a.len() + b.len()) as f64
+}
+
+/// lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+fn lcs_table(a: &[Line], b: &[Line]) -> Vec<Vec<usize>> {
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    lcs
+}
+
+/// Line-by-line diff of two ranges of lines.
+fn diff_lines(
+    old: &Document,
+    old_lines: Range<usize>,
+    new: &Document,
+    new_lines: Range<usize>,
+) -> Vec<LineEdit> {
+    let a = &old.lines[old_lines.clone()];
+    let b = &new.lines[new_lines.clone()];
+    let lcs = lcs_table(a, b);
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            edits.push(LineEdit::Equal {
+                old: old_lines.start + i,
+                new: new_lines.start + j,
+            });
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] > lcs[i + 1][j]) {
+            edits.push(LineEdit::Insert {
+                new: new_lines.start + j,
+            });
+            j += 1;
+        } else {
+            edits.push(LineEdit::Delete {
+                old: old_lines.start + i,
+            });
+            i += 1;
+        }
+    }
+    edits
+}
+
+/// Finds which dialogue was added, removed or reworded, pairing up removed and added dialogue
+/// from the same speaker.
+fn dialogue_changes(old: &Document, new: &Document, edits: &[LineEdit]) -> Vec<DialogueChange> {
+    let mut changes: Vec<DialogueChange> = Vec::new();
+    for edit in edits {
+        match *edit {
+            LineEdit::Delete { old: i } => {
+                if let (Line::Dialogue(text), Some(speaker)) = (&old.lines[i], speaker_of(old, i)) {
+                    let reworded = changes
+                        .iter_mut()
+                        .find(|c| c.speaker == speaker && c.old.is_none());
+                    match reworded {
+                        Some(change) => change.old = Some(text.clone()),
+                        None => changes.push(DialogueChange {
+                            speaker,
+                            old: Some(text.clone()),
+                            new: None,
+                        }),
+                    }
+                }
+            }
+            LineEdit::Insert { new: i } => {
+                if let (Line::Dialogue(text), Some(speaker)) = (&new.lines[i], speaker_of(new, i)) {
+                    let reworded = changes
+                        .iter_mut()
+                        .find(|c| c.speaker == speaker && c.new.is_none());
+                    match reworded {
+                        Some(change) => change.new = Some(text.clone()),
+                        None => changes.push(DialogueChange {
+                            speaker,
+                            old: None,
+                            new: Some(text.clone()),
+                        }),
+                    }
+                }
+            }
+            LineEdit::Equal { .. } => {}
+        }
+    }
+    changes
+}
+
+/// The normalized name of whoever speaks the dialogue at the given index.
+fn speaker_of(doc: &Document, dialogue: usize) -> Option<String> {
+    doc.lines[..dialogue]
+        .iter()
+        .rev()
+        .take_while(|l| l.is_speaker() || l.is_parenthetical() || l.is_dialogue())
+        .find_map(|l| match l {
+            Line::Speaker { name, .. } => Some(normalize_name(name)),
+            _ => None,
+        })
+}
+
+/// Returns the indices of a longest strictly increasing subsequence of the values.
+fn longest_increasing(values: &[usize]) -> BTreeSet<usize> {
+    // best[i] is the length of the longest increasing subsequence ending at i.
+    let mut best = vec![1; values.len()];
+    let mut prev = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && best[j] + 1 > best[i] {
+                best[i] = best[j] + 1;
+                prev[i] = Some(j);
+            }
+        }
+    }
+    let mut result = BTreeSet::new();
+    let mut i = (0..values.len()).max_by_key(|&i| (best[i], core::cmp::Reverse(i)));
+    while let Some(index) = i {
+        result.insert(index);
+        i = prev[index];
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    fn parse(text: &str) -> Document {
+        crate::parse_document::<VerboseError<&str>>(text).unwrap().1
+    }
+
+    fn statuses(diff: &DocumentDiff) -> Vec<(SceneStatus, Option<usize>, Option<usize>)> {
+        diff.scenes
+            .iter()
+            .filter(|s| s.new != Some(0))
+            .map(|s| (s.status, s.old, s.new))
+            .collect()
+    }
+
+    const DRAFT: &str = "\
+INT. MESS - NIGHT
+
+The crew eats.
+
+KANE
+Food!
+
+EXT. NOSTROMO - NIGHT
+
+The ship drifts through space.
+
+INT. BRIDGE - NIGHT
+
+Mother beeps.
+";
+
+    #[test]
+    fn test_unchanged() {
+        let diff = diff(&parse(DRAFT), &parse(DRAFT));
+        assert!(diff.is_unchanged());
+        assert!(diff.changed_lines().is_empty());
+    }
+
+    #[test]
+    fn test_changed_dialogue() {
+        let new = DRAFT.replace("Food!", "Decent food!");
+        let diff = diff(&parse(DRAFT), &parse(&new));
+        assert_eq!(
+            statuses(&diff),
+            vec![
+                (SceneStatus::Changed, Some(1), Some(1)),
+                (SceneStatus::Unchanged, Some(2), Some(2)),
+                (SceneStatus::Unchanged, Some(3), Some(3)),
+            ]
+        );
+        let changed = &diff.scenes[0];
+        assert_eq!(
+            changed.dialogue,
+            vec![DialogueChange {
+                speaker: "KANE".to_owned(),
+                old: Some("Food!".to_owned()),
+                new: Some("Decent food!".to_owned()),
+            }]
+        );
+        assert_eq!(diff.changed_lines(), vec![3].into_iter().collect());
+    }
+
+    #[test]
+    fn test_added_and_removed() {
+        let new = DRAFT.replace(
+            "EXT. NOSTROMO - NIGHT\n\nThe ship drifts through space.\n\n",
+            "",
+        ) + "\nEXT. PLANET - DAY\n\nWind howls.\n";
+        let diff = diff(&parse(DRAFT), &parse(&new));
+        assert_eq!(
+            statuses(&diff),
+            vec![
+                (SceneStatus::Unchanged, Some(1), Some(1)),
+                (SceneStatus::Removed, Some(2), None),
+                (SceneStatus::Unchanged, Some(3), Some(2)),
+                (SceneStatus::Added, None, Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moved() {
+        let new = "\
+INT. BRIDGE - NIGHT
+
+Mother beeps.
+
+INT. MESS - NIGHT
+
+The crew eats.
+
+KANE
+Food!
+
+EXT. NOSTROMO - NIGHT
+
+The ship drifts through space.
+";
+        let diff = diff(&parse(DRAFT), &parse(new));
+        assert_eq!(
+            statuses(&diff),
+            vec![
+                (SceneStatus::Moved, Some(3), Some(1)),
+                (SceneStatus::Unchanged, Some(1), Some(2)),
+                (SceneStatus::Unchanged, Some(2), Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_by_scene_number() {
+        let old = "INT. MESS - NIGHT #5#\n\nThe crew eats.\n";
+        let new = "INT. GALLEY - DAY #5#\n\nThe crew eats lunch.\n";
+        let diff = diff(&parse(old), &parse(new));
+        assert_eq!(
+            statuses(&diff),
+            vec![(SceneStatus::Changed, Some(1), Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_longest_increasing() {
+        let expected: BTreeSet<usize> = vec![1, 2, 4].into_iter().collect();
+        assert_eq!(longest_increasing(&[3, 0, 1, 0, 2]), expected);
+        assert!(longest_increasing(&[]).is_empty());
+    }
+}
@@ -0,0 +1,557 @@
+//! Structural edits to a document: renaming characters and rearranging scenes. Every edit keeps
+//! the document writable as Fountain (see `Document::as_fountain`) which parses back to the same
+//! document.
+use super::characters::normalize_name;
+use super::data::*;
+use super::parse::block;
+use super::prelude::*;
+use super::scenes::without_scene_number;
+use super::source::Span;
+use super::utils::{eq_ignore_case, strip_prefix_ignore_case};
+use core::fmt;
+use nom::error::ErrorKind;
+
+/// Why an edit couldn't be made. The document is left unchanged.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EditError {
+    /// There's no scene with this number (see `Document::scenes`).
+    NoSuchScene(usize),
+    /// Two scenes can't be merged if they're the same scene.
+    SameScene(usize),
+    /// The line at this index isn't inside a scene, or is a Scene Heading itself.
+    NotInScene(usize),
+    /// This text wouldn't be read back as a Scene Heading.
+    InvalidHeading(String),
+    /// This name wouldn't be read back as a character's name.
+    InvalidName(String),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NoSuchScene(n) => write!(f, "There is no scene {}", n),
+            EditError::SameScene(n) => write!(f, "Can't merge scene {} with itself", n),
+            EditError::NotInScene(i) => {
+                write!(f, "Line {} isn't part of a scene's body", i)
+            }
+            EditError::InvalidHeading(h) => write!(f, "'{}' isn't a valid Scene Heading", h),
+            EditError::InvalidName(n) => write!(f, "'{}' isn't a valid character name", n),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EditError {}
+
+/// Whether renaming a character should also rename them where they're named in action.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mentions {
+    /// Only rename the character's Speaker lines.
+    Keep,
+    /// Also rename whole-word mentions of the character in action.
+    Rename,
+}
+
+impl Document {
+    /// Renames a character everywhere they speak, keeping extensions like (V.O.) and (CONT'D).
+    /// Names are normalized (see `normalize_name`) and matched case-insensitively. Returns how
+    /// many lines were changed.
+    /// ```
+    /// use fountain::edit::Mentions;
+    /// use nom::error::VerboseError;
+    ///
+    /// let text = "\
+    /// INT. MESS - NIGHT
+    ///
+    /// Kane eats.
+    ///
+    /// KANE (CONT'D)
+    /// Food!
+    /// ";
+    /// let mut doc = fountain::parse_document::<VerboseError<&str>>(text).unwrap().1;
+    /// assert_eq!(doc.rename_character("Kane", "Dallas", Mentions::Rename), Ok(2));
+    /// assert_eq!(
+    ///     doc.as_fountain(),
+    ///     "INT. MESS - NIGHT\n\nDallas eats.\n\nDALLAS (CONT'D)\nFood!\n"
+    /// );
+    /// ```
+    pub fn rename_character(
+        &mut self,
+        from: &str,
+        to: &str,
+        mentions: Mentions,
+    ) -> Result<usize, EditError> {
+        let (from, to) = match (normalize_name(from), normalize_name(to)) {
+            (name, _) if name.is_empty() => return Err(EditError::InvalidName(from.to_owned())),
+            names => names,
+        };
+        let speaker = to.to_uppercase();
+        if !parses_to(&format!("{}\nx", speaker), &speaker_lines(&speaker)) {
+            return Err(EditError::InvalidName(to));
+        }
+        let mut changed = 0;
+        for line in self.lines.iter_mut() {
+            match line {
+                Line::Speaker { name, .. } if eq_ignore_case(&normalize_name(name), &from) => {
+                    *name = format!("{}{}", speaker, &name[name_end(name)..]);
+                    changed += 1;
+                }
+                Line::Action(text) if mentions == Mentions::Rename => {
+                    if let Some(renamed) = replace_name(text, &from, &to) {
+                        *text = renamed;
+                        changed += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Moves a scene so that it comes just before another scene.
+    pub fn move_scene_before(&mut self, scene: usize, target: usize) -> Result<(), EditError> {
+        let (start, _) = self.scene_range(target)?;
+        self.move_scene_to(scene, start)
+    }
+
+    /// Moves a scene so that it comes just after another scene.
+    pub fn move_scene_after(&mut self, scene: usize, target: usize) -> Result<(), EditError> {
+        let (_, end) = self.scene_range(target)?;
+        self.move_scene_to(scene, end)
+    }
+
+    /// Moves a scene so that it starts at the given line index (counted before the move).
+    fn move_scene_to(&mut self, scene: usize, index: usize) -> Result<(), EditError> {
+        let (start, end) = self.scene_range(scene)?;
+        if (start..=end).contains(&index) {
+            return Ok(());
+        }
+        let lines: Vec<Line> = self.lines.drain(start..end).collect();
+        let index = if index > end {
+            index - lines.len()
+        } else {
+            index
+        };
+        self.lines.splice(index..index, lines);
+        Ok(())
+    }
+
+    /// Removes a scene, returning its lines.
+    pub fn delete_scene(&mut self, scene: usize) -> Result<Vec<Line>, EditError> {
+        let (start, end) = self.scene_range(scene)?;
+        Ok(self.lines.drain(start..end).collect())
+    }
+
+    /// Inserts a new scene, so that it becomes scene number `scene`. Its lines must start with a
+    /// Scene Heading, and contain no other Scene Headings.
+    pub fn insert_scene(&mut self, scene: usize, lines: Vec<Line>) -> Result<(), EditError> {
+        match lines.first() {
+            Some(Line::Scene(heading)) if parses_to(heading, &lines[..1]) => {}
+            Some(line) => return Err(EditError::InvalidHeading(line.text().to_owned())),
+            None => return Err(EditError::InvalidHeading(String::new())),
+        }
+        if let Some(line) = lines[1..].iter().find(|l| l.is_scene()) {
+            return Err(EditError::InvalidHeading(line.text().to_owned()));
+        }
+        let count = self.scenes().len();
+        let index = if scene == count + 1 {
+            self.lines.len()
+        } else {
+            self.scene_range(scene)?.0
+        };
+        self.lines.splice(index..index, lines);
+        Ok(())
+    }
+
+    /// Merges the body of scene `from` onto the end of scene `into`, dropping `from`'s Scene
+    /// Heading.
+    pub fn merge_scenes(&mut self, into: usize, from: usize) -> Result<(), EditError> {
+        if into == from {
+            return Err(EditError::SameScene(into));
+        }
+        let (_, end) = self.scene_range(into)?;
+        let (start, from_end) = self.scene_range(from)?;
+        let body: Vec<Line> = self.lines.drain(start + 1..from_end).collect();
+        self.lines.remove(start);
+        let index = if end > start {
+            end - body.len() - 1
+        } else {
+            end
+        };
+        self.lines.splice(index..index, body);
+        Ok(())
+    }
+
+    /// Splits a scene in two, starting a new scene with the given heading at the line with the
+    /// given index.
+    pub fn split_scene(&mut self, index: usize, heading: &str) -> Result<(), EditError> {
+        let line = Line::Scene(heading.to_owned());
+        if !parses_to(heading, core::slice::from_ref(&line)) {
+            return Err(EditError::InvalidHeading(heading.to_owned()));
+        }
+        let in_body = index < self.lines.len()
+            && !self.lines[index].is_scene()
+            && self.scene_number_at(index).is_some();
+        if !in_body {
+            return Err(EditError::NotInScene(index));
+        }
+        self.lines.insert(index, line);
+        Ok(())
+    }
+
+    /// Gives every scene an explicit scene number (e.g. "INT. MESS #3#") matching its position in
+    /// the document, replacing any numbers it already had.
+    pub fn renumber_scenes(&mut self) {
+        let mut n = 0;
+        for line in self.lines.iter_mut() {
+            if let Line::Scene(heading) = line {
+                n += 1;
+                *heading = format!("{} #{}#", without_scene_number(heading), n);
+            }
+        }
+    }
+
+    /// The range of `Document::lines` which a scene covers.
+    fn scene_range(&self, scene: usize) -> Result<(usize, usize), EditError> {
+        self.scenes()
+            .get(scene.wrapping_sub(1))
+            .map(|s| (s.start, s.end()))
+            .ok_or(EditError::NoSuchScene(scene))
+    }
+}
+
+/// True if the text (as a block of its own) parses to exactly the given lines.
+fn parses_to(text: &str, lines: &[Line]) -> bool {
+    let text = format!("{}\n\n", text);
+    matches!(block::<(&str, ErrorKind)>(&text), Ok(("\n", parsed)) if parsed == lines)
+}
+
+fn speaker_lines(name: &str) -> [Line; 2] {
+    [
+        Line::Speaker {
+            name: name.to_owned(),
+            is_dual: false,
+        },
+        Line::Dialogue("x".to_owned()),
+    ]
+}
+
+/// Where a Speaker's name ends and its extensions (e.g. " (V.O.)") begin.
+fn name_end(name: &str) -> usize {
+    let mut base = name.trim_end();
+    while base.ends_with(')') {
+        match base.rfind('(') {
+            Some(open) => base = base[..open].trim_end(),
+            None => break,
+        }
+    }
+    base.len()
+}
+
+/// Where the name is mentioned as a whole word in the text, ignoring case in any script (so
+/// "José" mentions JOSÉ). Mentions can have a different length to the name, since some letters
+/// change length when their case does.
+pub fn mentions(text: &str, name: &str) -> Vec<Span> {
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut found = Vec::new();
+    if name.is_empty() {
+        return found;
+    }
+    let mut start = 0;
+    while start < text.len() {
+        let rest = &text[start..];
+        let end = strip_prefix_ignore_case(rest, name).map(|after| text.len() - after.len());
+        match end {
+            Some(end)
+                if !is_word(text[..start].chars().next_back())
+                    && !is_word(text[end..].chars().next()) =>
+            {
+                found.push(Span { start, end });
+                start = end;
+            }
+            _ => start += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    found
+}
+
+/// Replaces whole-word mentions of a name in the text, ignoring case. Mentions written in capitals
+/// (as characters are when they're introduced) are replaced in capitals too. Returns None if the
+/// name isn't mentioned.
+fn replace_name(text: &str, from: &str, to: &str) -> Option<String> {
+    let found = mentions(text, from);
+    if found.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    let mut last = 0;
+    for span in found {
+        let mention = &text[span.start..span.end];
+        let capitals =
+            mention.chars().any(char::is_alphabetic) && !mention.chars().any(char::is_lowercase);
+        out.push_str(&text[last..span.start]);
+        if capitals {
+            out.push_str(&to.to_uppercase());
+        } else {
+            out.push_str(to);
+        }
+        last = span.end;
+    }
+    out.push_str(&text[last..]);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    const DOC: &str = "\
+Title: Alien
+
+INT. MESS - NIGHT
+
+KANE eats. Kanega doesn't.
+
+KANE (O.S.)
+Food!
+
+EXT. NOSTROMO - NIGHT
+
+Silence.
+
+INT. INFIRMARY - NIGHT
+
+Ash examines Kane.
+
+ASH ^
+(quietly)
+Alive.
+";
+
+    fn parse(text: &str) -> Document {
+        match crate::parse_document::<VerboseError<&str>>(text) {
+            Ok(("", doc)) => doc,
+            other => panic!("couldn't parse {:?}: {:?}", text, other),
+        }
+    }
+
+    /// The headings of every scene, after checking the document still round-trips.
+    fn headings(doc: &Document) -> Vec<String> {
+        assert_eq!(&parse(&doc.as_fountain()), doc);
+        doc.scenes().iter().map(|s| s.heading.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_rename_character() {
+        let mut doc = parse(DOC);
+        assert_eq!(
+            doc.rename_character("kane", "Dallas", Mentions::Keep),
+            Ok(1)
+        );
+        assert_eq!(
+            doc.lines[2],
+            Line::Speaker {
+                name: "DALLAS (O.S.)".to_owned(),
+                is_dual: false
+            }
+        );
+        assert_eq!(
+            doc.lines[1],
+            Line::Action("KANE eats. Kanega doesn't.".to_owned())
+        );
+        headings(&doc);
+
+        let mut doc = parse(DOC);
+        assert_eq!(
+            doc.rename_character("KANE", "Dallas", Mentions::Rename),
+            Ok(3)
+        );
+        assert_eq!(
+            doc.lines[1],
+            Line::Action("DALLAS eats. Kanega doesn't.".to_owned())
+        );
+        assert_eq!(
+            doc.lines[7],
+            Line::Action("Ash examines Dallas.".to_owned())
+        );
+
+        assert_eq!(
+            doc.rename_character("ASH", "Bishop 2", Mentions::Keep),
+            Ok(1)
+        );
+        assert_eq!(
+            doc.lines[8],
+            Line::Speaker {
+                name: "BISHOP 2".to_owned(),
+                is_dual: true
+            }
+        );
+        headings(&doc);
+    }
+
+    #[test]
+    fn test_rename_invalid() {
+        let mut doc = parse(DOC);
+        assert_eq!(
+            doc.rename_character("KANE", "INT. MESS", Mentions::Keep),
+            Err(EditError::InvalidName("INT. MESS".to_owned()))
+        );
+        assert!(doc.rename_character("KANE", "", Mentions::Keep).is_err());
+        assert_eq!(
+            doc.rename_character(" ", "Dallas", Mentions::Keep),
+            Err(EditError::InvalidName(" ".to_owned()))
+        );
+        assert_eq!(doc, parse(DOC));
+    }
+
+    #[test]
+    fn test_rename_non_ascii() {
+        let mut doc = parse("INT. CASA - DÍA\n\nJosé entra. JOSÉ se sienta.\n\nJOSÉ\nHola.\n");
+        assert_eq!(
+            doc.rename_character("josé", "Élodie", Mentions::Rename),
+            Ok(2)
+        );
+        assert_eq!(
+            doc.lines[1],
+            Line::Action("Élodie entra. ÉLODIE se sienta.".to_owned())
+        );
+        assert_eq!(
+            doc.lines[2],
+            Line::Speaker {
+                name: "ÉLODIE".to_owned(),
+                is_dual: false
+            }
+        );
+        let found: Vec<_> = mentions("Straße, STRASSE", "STRAßE")
+            .iter()
+            .map(|span| (span.start, span.end))
+            .collect();
+        assert_eq!(found, vec![(0, 7)]);
+    }
+
+    #[test]
+    fn test_move_scene() {
+        let mut doc = parse(DOC);
+        doc.move_scene_before(3, 1).unwrap();
+        assert_eq!(
+            headings(&doc),
+            vec![
+                "INT. INFIRMARY - NIGHT",
+                "INT. MESS - NIGHT",
+                "EXT. NOSTROMO - NIGHT"
+            ]
+        );
+        doc.move_scene_after(1, 3).unwrap();
+        assert_eq!(
+            headings(&doc),
+            vec![
+                "INT. MESS - NIGHT",
+                "EXT. NOSTROMO - NIGHT",
+                "INT. INFIRMARY - NIGHT"
+            ]
+        );
+        doc.move_scene_after(1, 1).unwrap();
+        doc.move_scene_before(2, 3).unwrap();
+        assert_eq!(doc, parse(DOC));
+        assert_eq!(doc.move_scene_before(4, 1), Err(EditError::NoSuchScene(4)));
+        assert_eq!(doc.move_scene_before(1, 0), Err(EditError::NoSuchScene(0)));
+    }
+
+    #[test]
+    fn test_delete_and_insert_scene() {
+        let mut doc = parse(DOC);
+        let deleted = doc.delete_scene(2).unwrap();
+        assert_eq!(
+            headings(&doc),
+            vec!["INT. MESS - NIGHT", "INT. INFIRMARY - NIGHT"]
+        );
+        doc.insert_scene(2, deleted.clone()).unwrap();
+        assert_eq!(doc, parse(DOC));
+        doc.insert_scene(4, deleted).unwrap();
+        assert_eq!(headings(&doc).len(), 4);
+        assert_eq!(
+            doc.insert_scene(1, vec![Line::Action("Nothing.".to_owned())]),
+            Err(EditError::InvalidHeading("Nothing.".to_owned()))
+        );
+        assert_eq!(
+            doc.insert_scene(1, vec![Line::Scene("MESS".to_owned())]),
+            Err(EditError::InvalidHeading("MESS".to_owned()))
+        );
+        assert_eq!(
+            doc.insert_scene(6, vec![Line::Scene("INT. MESS".to_owned())]),
+            Err(EditError::NoSuchScene(6))
+        );
+    }
+
+    #[test]
+    fn test_merge_scenes() {
+        let mut doc = parse(DOC);
+        doc.merge_scenes(1, 2).unwrap();
+        assert_eq!(
+            headings(&doc),
+            vec!["INT. MESS - NIGHT", "INT. INFIRMARY - NIGHT"]
+        );
+        assert_eq!(doc.lines[4], Line::Action("Silence.".to_owned()));
+
+        let mut doc = parse(DOC);
+        doc.merge_scenes(2, 1).unwrap();
+        assert_eq!(
+            headings(&doc),
+            vec!["EXT. NOSTROMO - NIGHT", "INT. INFIRMARY - NIGHT"]
+        );
+        assert_eq!(doc.lines[2..5], parse(DOC).lines[1..4]);
+        assert_eq!(doc.lines[1], Line::Action("Silence.".to_owned()));
+        assert_eq!(doc.merge_scenes(1, 1), Err(EditError::SameScene(1)));
+    }
+
+    #[test]
+    fn test_split_scene() {
+        let mut doc = parse(DOC);
+        doc.split_scene(2, "INT. MESS - LATER").unwrap();
+        assert_eq!(headings(&doc)[1], "INT. MESS - LATER");
+        assert_eq!(
+            doc.split_scene(0, "INT. MESS"),
+            Err(EditError::NotInScene(0))
+        );
+        assert_eq!(
+            doc.split_scene(100, "INT. MESS"),
+            Err(EditError::NotInScene(100))
+        );
+        assert_eq!(
+            doc.split_scene(2, "LATER"),
+            Err(EditError::InvalidHeading("LATER".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_renumber_scenes() {
+        let mut doc = parse(DOC);
+        doc.delete_scene(1).unwrap();
+        doc.renumber_scenes();
+        doc.insert_scene(1, vec![Line::Scene("INT. MESS #9#".to_owned())])
+            .unwrap();
+        doc.renumber_scenes();
+        assert_eq!(
+            headings(&doc),
+            vec![
+                "INT. MESS #1#",
+                "EXT. NOSTROMO - NIGHT #2#",
+                "INT. INFIRMARY - NIGHT #3#"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_name() {
+        assert_eq!(
+            replace_name("Kane's helmet. KANE!", "KANE", "Dallas"),
+            Some("Dallas's helmet. DALLAS!".to_owned())
+        );
+        assert_eq!(
+            replace_name("Mrs. Thompson waves.", "MRS. THOMPSON", "Ripley"),
+            Some("Ripley waves.".to_owned())
+        );
+        assert_eq!(replace_name("Kanega. Ékane.", "KANE", "Dallas"), None);
+    }
+}
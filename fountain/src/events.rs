@@ -0,0 +1,649 @@
+//! A pull parser, which reads a Fountain document from a `BufRead` and yields it as a stream of
+//! events, without ever holding the whole document in memory. It only reads a few lines ahead of
+//! the events it yields, so it's suited to very large inputs, or to piping scripts through a
+//! program one at a time.
+//!
+//! ```
+//! use fountain::data::Line;
+//! use fountain::events::{Event, Events};
+//!
+//! let text = "INT. MESS\n\nKANE\nFood!\n";
+//! let events: Vec<_> = Events::new(text.as_bytes()).collect::<Result<_, _>>().unwrap();
+//! assert_eq!(
+//!     events,
+//!     vec![
+//!         Event::StartScene,
+//!         Event::Line(Line::Scene("INT. MESS".to_owned())),
+//!         Event::StartDialogue,
+//!         Event::Line(Line::Speaker { name: "KANE".to_owned(), is_dual: false }),
+//!         Event::Line(Line::Dialogue("Food!".to_owned())),
+//!         Event::EndDialogue,
+//!         Event::EndScene,
+//!     ]
+//! );
+//! ```
+use super::data::*;
+use super::html::as_nodes;
+use super::options::ParseOptions;
+use super::parse::{
+    blank_line, block_with, expand_tabs, lines_inclusive, titlepage_item, titlepage_prefix,
+    without_bom, without_trailing_blank_lines,
+};
+use nom::error::ErrorKind;
+use std::io::{self, BufRead, Write};
+
+/// Something the parser found in the document, in the order it appears.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Event {
+    /// The title page. This comes first, and only if the document has one.
+    TitlePage(TitlePage),
+    /// A line of the screenplay. Speakers, Parentheticals and Dialogue only appear between a
+    /// `StartDialogue` and an `EndDialogue`.
+    Line(Line),
+    /// The start of a block of dialogue, i.e. a Speaker, maybe a Parenthetical, then Dialogue.
+    StartDialogue,
+    EndDialogue,
+    /// The start of a scene, just before its Scene Heading. A scene runs until the next one starts,
+    /// like in `Document::scenes`, so lines before the first Scene Heading aren't in a scene.
+    StartScene,
+    EndScene,
+    /// The rest of the input, from where it stopped parsing as Fountain. This is always the last
+    /// event. `parse_document` returns this text as its unparsed remainder, except where it fails
+    /// the whole document, e.g. for a Parenthetical followed by more text than a line of Dialogue.
+    Unparsed(String),
+}
+
+/// How many lines past the start of the next block are read before parsing it: the line ending
+/// which separates it from the previous block, and the three lines of the longest block.
+const LOOKAHEAD: usize = 4;
+
+/// Reads a document and yields its `Event`s. It's an `Iterator`, which stops after the first
+/// I/O error. It reads no more than a block ahead, though a block of Dialogue can be many lines
+/// long.
+pub struct Events<R> {
+    reader: R,
+    /// Text which has been read but not yet parsed, from `pos` onwards.
+    buf: String,
+    pos: usize,
+    state: State,
+    /// Whether the reader has run out of input.
+    eof: bool,
+    /// The Lines of the block being yielded, in reverse order.
+    pending: Vec<Line>,
+    /// Whether a `StartDialogue` has been yielded, but not its `EndDialogue`.
+    in_dialogue: bool,
+    /// Whether a `StartScene` has been yielded, but not its `EndScene`.
+    in_scene: bool,
+    /// Whether the `StartScene` before the next pending line, a Scene Heading, has been yielded.
+    at_heading: bool,
+    /// Whether the first block comes after a blank line (see `parse::block_with`).
+    body_after_blank_line: bool,
+    options: ParseOptions,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum State {
+    Start,
+    FirstBlock,
+    Block,
+    Done,
+}
+
+impl<R: BufRead> Events<R> {
+    pub fn new(reader: R) -> Self {
+        Events::with_options(reader, ParseOptions::default())
+    }
+
+    /// Reads the document the way the options say to, like `parse_document_with_options`.
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Events {
+            reader,
+            buf: String::new(),
+            pos: 0,
+            state: State::Start,
+            eof: false,
+            pending: Vec::new(),
+            in_dialogue: false,
+            in_scene: false,
+            at_heading: false,
+            body_after_blank_line: true,
+            options,
+        }
+    }
+
+    /// Reads until the unparsed text holds enough lines to parse the next block exactly the way
+    /// `parse_document` would, given the whole text.
+    fn fill(&mut self) -> io::Result<()> {
+        while !self.eof && !self.has_lookahead() {
+            if read_line(&mut self.reader, &mut self.buf)? == 0 {
+                self.eof = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_lookahead(&self) -> bool {
+        let rest = &self.buf[self.pos..];
+        // A Parenthetical continues across lines until its closing parenthesis, so count the lines
+        // after that instead.
+        let mut from = 0;
+        let mut line_start = 0;
+        for line in lines_inclusive(rest).take(LOOKAHEAD) {
+            if line.starts_with('(') {
+                match rest[line_start..].find(')') {
+                    Some(close) => from = line_start + close,
+                    None => return false,
+                }
+            }
+            line_start += line.len();
+        }
+        // A title page value can be any number of blank lines after its key, so there must be a
+        // line which isn't blank after those too.
+        let complete_lines =
+            lines_inclusive(&rest[from..]).take_while(|line| line.ends_with(['\n', '\r']));
+        for (i, line) in complete_lines.enumerate() {
+            if i + 1 >= LOOKAHEAD && !line.trim().is_empty() {
+                // Dialogue can run on for more lines than that.
+                return has_block_end(rest);
+            }
+        }
+        false
+    }
+
+    /// Drops the text which has already been parsed.
+    fn consume(&mut self, len: usize) {
+        self.buf.drain(..self.pos + len);
+        self.pos = 0;
+    }
+
+    fn titlepage(&mut self) -> io::Result<Option<TitlePage>> {
+        self.fill()?;
+        let start = self.buf.len() - without_bom(&self.buf).len();
+        self.pos = start;
+        loop {
+            self.fill()?;
+            match titlepage_item::<(&str, ErrorKind)>(&self.buf[self.pos..]) {
+                Ok((rest, _)) => self.pos = self.buf.len() - rest.len(),
+                Err(_) => break,
+            }
+        }
+        let has_items = self.pos > start;
+        // Every item is in the buffer now, so the title page can be parsed in one go. Like in
+        // `parse_document`, this also skips a blank line after it, even if it has no items.
+        let (titlepage, len, after_blank_line) = titlepage_prefix(&self.buf);
+        self.body_after_blank_line = after_blank_line;
+        self.pos = 0;
+        self.consume(len);
+        Ok(has_items.then_some(titlepage))
+    }
+
+    /// Parses the next block into `pending`, or returns false if there isn't one.
+    fn block(&mut self) -> io::Result<bool> {
+        self.fill()?;
+        let mut rest = &self.buf[self.pos..];
+        let mut separator = 0;
+        if self.state == State::Block {
+            // Blocks are separated by a blank line.
+            match blank_line::<(&str, ErrorKind)>(rest) {
+                Ok((after, _)) => {
+                    separator = rest.len() - after.len();
+                    rest = after;
+                }
+                Err(_) => return Ok(false),
+            }
+        }
+        let after_blank_line = self.state == State::Block || self.body_after_blank_line;
+        let parsed = block_with::<(&str, ErrorKind)>(&self.options, after_blank_line)(rest);
+        let (after, lines) = match parsed {
+            Ok(parsed) => parsed,
+            Err(_) => return Ok(false),
+        };
+        let len = separator + rest.len() - after.len();
+        let mut lines: Vec<Line> = lines.into_iter().rev().map(Into::into).collect();
+        expand_tabs(&mut lines, &self.options);
+        self.pending = lines;
+        self.consume(len);
+        Ok(true)
+    }
+
+    fn next_event(&mut self) -> io::Result<Option<Event>> {
+        loop {
+            if let Some(line) = self.pending.last() {
+                // The previous scene ends where the next one starts.
+                if line.is_scene() && !self.at_heading {
+                    if self.in_scene {
+                        self.in_scene = false;
+                        return Ok(Some(Event::EndScene));
+                    }
+                    self.in_scene = true;
+                    self.at_heading = true;
+                    return Ok(Some(Event::StartScene));
+                }
+                self.at_heading = false;
+                // Dialogue starts at its Speaker, after any blank lines before the block.
+                if line.is_speaker() && !self.in_dialogue {
+                    self.in_dialogue = true;
+                    return Ok(Some(Event::StartDialogue));
+                }
+                return Ok(self.pending.pop().map(Event::Line));
+            }
+            if self.in_dialogue {
+                self.in_dialogue = false;
+                return Ok(Some(Event::EndDialogue));
+            }
+            match self.state {
+                State::Start => {
+                    self.state = State::FirstBlock;
+                    if let Some(titlepage) = self.titlepage()? {
+                        return Ok(Some(Event::TitlePage(titlepage)));
+                    }
+                }
+                State::FirstBlock | State::Block => {}
+                State::Done => return Ok(None),
+            }
+            if !self.block()? {
+                break;
+            }
+            self.state = State::Block;
+        }
+        if self.in_scene {
+            self.in_scene = false;
+            return Ok(Some(Event::EndScene));
+        }
+        self.state = State::Done;
+        self.reader.read_to_string(&mut self.buf)?;
+        let unparsed = without_trailing_blank_lines(&self.buf[self.pos..]);
+        Ok((!unparsed.is_empty()).then(|| Event::Unparsed(unparsed.to_owned())))
+    }
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.next_event();
+        if event.is_err() {
+            self.state = State::Done;
+            self.pending.clear();
+            self.in_dialogue = false;
+            self.in_scene = false;
+            self.at_heading = false;
+        }
+        event.transpose()
+    }
+}
+
+/// Reads a line into the buffer, like `BufRead::read_line`, but a line can end in "\n", "\r\n" or a
+/// lone "\r", as in `parse_document`. Returns the number of bytes read, which is 0 at the end.
+fn read_line(reader: &mut impl BufRead, buf: &mut String) -> io::Result<usize> {
+    let mut line = Vec::new();
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        if line.last() == Some(&b'\r') {
+            // A "\r" ends the line, along with a "\n" right after it.
+            if available[0] == b'\n' {
+                line.push(b'\n');
+                reader.consume(1);
+            }
+            break;
+        }
+        match available.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(i) => {
+                line.extend_from_slice(&available[..=i]);
+                reader.consume(i + 1);
+                if line.ends_with(b"\n") {
+                    break;
+                }
+            }
+            None => {
+                let len = available.len();
+                line.extend_from_slice(available);
+                reader.consume(len);
+            }
+        }
+    }
+    let line =
+        String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    buf.push_str(&line);
+    Ok(line.len())
+}
+
+/// The length of the first line of the text, with its line ending, if it's a whole line.
+fn line_len(text: &str) -> Option<usize> {
+    lines_inclusive(text)
+        .next()
+        .filter(|line| line.ends_with(['\n', '\r']))
+        .map(str::len)
+}
+
+/// Whether the text holds the blank line which ends the block at its start. A block of Dialogue
+/// runs until a blank line, though a line of two spaces within it doesn't count, as it's part of
+/// the Dialogue.
+fn has_block_end(text: &str) -> bool {
+    let mut pos = 0;
+    let mut block_lines = 0;
+    while let Some(len) = line_len(&text[pos..]) {
+        let line = &text[pos..pos + len];
+        let is_blank = line.trim().is_empty();
+        if block_lines == 0 {
+            // Blank lines before the block are skipped.
+            if !is_blank {
+                block_lines = 1;
+            }
+        } else if is_blank && line.trim_end_matches(['\r', '\n']) != "  " {
+            return true;
+        } else if block_lines == 1 && line.starts_with('(') {
+            // The Parenthetical after a Speaker runs up to its closing parenthesis.
+            let close = match text[pos..].find(')') {
+                Some(close) => pos + close,
+                None => return false,
+            };
+            match line_len(&text[close..]) {
+                Some(end) => pos = close + end,
+                None => return false,
+            }
+            block_lines += 1;
+            continue;
+        } else {
+            block_lines += 1;
+        }
+        pos += len;
+    }
+    false
+}
+
+/// Writes the events as HTML, exactly as `Document::as_html` renders the document they came from,
+/// without holding the whole document in memory. Returns the unparsed text, if there was any.
+/// Lines are held back from each Speaker until the next one, since the next Speaker's Dialogue
+/// might be dual dialogue with theirs.
+/// ```
+/// use fountain::events::{write_html, Events};
+/// use nom::error::VerboseError;
+///
+/// let text = "INT. MESS\n\nKANE\nFood!\n";
+/// let mut html = Vec::new();
+/// let unparsed = write_html(Events::new(text.as_bytes()), &mut html).unwrap();
+/// let doc = fountain::parse_document::<VerboseError<&str>>(text).unwrap().1;
+/// assert_eq!(String::from_utf8(html).unwrap(), doc.as_html());
+/// assert_eq!(unparsed, "");
+/// ```
+pub fn write_html(
+    events: impl IntoIterator<Item = io::Result<Event>>,
+    mut out: impl Write,
+) -> io::Result<String> {
+    let mut titlepage = None;
+    let mut started = false;
+    let mut wrote_lines = false;
+    // The lines since the last Speaker who isn't dual.
+    let mut held: Vec<Line> = Vec::new();
+    let mut unparsed = String::new();
+    let mut write_lines = |out: &mut dyn Write, lines: &[Line]| -> io::Result<()> {
+        for node in as_nodes(lines) {
+            writeln!(out, "{}", node)?;
+            wrote_lines = true;
+        }
+        Ok(())
+    };
+    for event in events {
+        let line = match event? {
+            Event::TitlePage(tp) => {
+                titlepage = Some(tp);
+                continue;
+            }
+            Event::Unparsed(text) => {
+                unparsed = text;
+                continue;
+            }
+            Event::StartDialogue | Event::EndDialogue | Event::StartScene | Event::EndScene => {
+                continue
+            }
+            Event::Line(line) => line,
+        };
+        if !started {
+            write_start(&mut out, titlepage.take())?;
+            started = true;
+        }
+        match line {
+            Line::Speaker { is_dual: false, .. } => {
+                write_lines(&mut out, &held)?;
+                held = vec![line];
+            }
+            _ if !held.is_empty() => held.push(line),
+            _ => write_lines(&mut out, core::slice::from_ref(&line))?,
+        }
+    }
+    if !started {
+        write_start(&mut out, titlepage)?;
+    }
+    write_lines(&mut out, &held)?;
+    if !wrote_lines {
+        writeln!(out)?;
+    }
+    writeln!(out, "</div>")?;
+    Ok(unparsed)
+}
+
+fn write_start(out: &mut impl Write, titlepage: Option<TitlePage>) -> io::Result<()> {
+    match titlepage.filter(|tp| *tp != TitlePage::default()) {
+        Some(titlepage) => writeln!(out, "<div>\n{}", titlepage.as_html()),
+        None => writeln!(out, "<div>\n"),
+    }
+}
+
+impl Document {
+    /// Builds a Document from the events, and returns it with the unparsed text, if there was any.
+    /// For the events of a whole document, this is the same as `parse_document` would return.
+    /// ```
+    /// use fountain::data::Document;
+    /// use fountain::events::Events;
+    /// use nom::error::VerboseError;
+    ///
+    /// let text = "INT. MESS\n\nKANE\nFood!\n";
+    /// let (doc, unparsed) = Document::from_events(Events::new(text.as_bytes())).unwrap();
+    /// let (_, expected) = fountain::parse_document::<VerboseError<&str>>(text).unwrap();
+    /// assert_eq!(doc, expected);
+    /// assert_eq!(unparsed, "");
+    /// ```
+    pub fn from_events(
+        events: impl IntoIterator<Item = io::Result<Event>>,
+    ) -> io::Result<(Document, String)> {
+        let mut doc = Document::default();
+        let mut unparsed = String::new();
+        for event in events {
+            match event? {
+                Event::TitlePage(titlepage) => doc.titlepage = titlepage,
+                Event::Line(line) => doc.lines.push(line),
+                Event::Unparsed(text) => unparsed = text,
+                Event::StartDialogue | Event::EndDialogue | Event::StartScene | Event::EndScene => {
+                }
+            }
+        }
+        Ok((doc, unparsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    /// Parses the text with a one-byte buffer, so that every line is read separately.
+    fn events(text: &str) -> Vec<Event> {
+        let reader = BufReader::with_capacity(1, text.as_bytes());
+        Events::new(reader).collect::<io::Result<_>>().unwrap()
+    }
+
+    fn assert_same_as_parse_document(text: &str) {
+        let expected = crate::parse_document::<(&str, ErrorKind)>(text);
+        let events = events(text);
+        let (doc, unparsed) = Document::from_events(events.iter().cloned().map(Ok)).unwrap();
+        if let Ok((expected_unparsed, expected)) = expected {
+            assert_eq!(doc, expected, "{:?}", text);
+            assert_eq!(unparsed, expected_unparsed, "{:?}", text);
+            let mut html = Vec::new();
+            write_html(events.into_iter().map(Ok), &mut html).unwrap();
+            assert_eq!(
+                String::from_utf8(html).unwrap(),
+                expected.as_html(),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_parse_document() {
+        let texts = [
+            "",
+            "\n",
+            "A\n",
+            "A\n\n",
+            "A\n\n\nB\n",
+            "INT. MESS\r\n\r\nKANE ^\r\n(eating)\r\nFood!\r\n",
+            "Title: Alien\nAuthor:\n\n\n    Dan O'Bannon\n\nINT. MESS\n",
+            "Title: Alien\nINT. MESS\n",
+            "{{include: act1.fountain}}\n",
+            "KANE\n(scared\nstill\nscared)\nHi\n\nCUT TO:\n",
+            "KANE\n(scared)\n",
+            "# ACT ONE\n\n~La la\n\n> Fade out.\n\n!LOUD\nNot a new block\n\nLost\n",
+            "No final newline",
+            "\u{feff}Title: Alien\r\n\r\nINT. MESS\rKANE\r\n",
+            "Title: Alien\rAuthor:\r\r    Dan O'Bannon\r\rINT. MESS\r\rKANE\r(eating\rslowly)\rFood!\r",
+            "DEALER\rTen.\r  \rFour.\r\r\rCUT TO:\r\n\r",
+            "ONE.\n\n\n \t\nTwo.\n\n  \n",
+            "DEALER\nTen.\n(beat)\nFour.\n  \nHit?\n\nMONKEY\n(\n)\nDude.\nHey.\n",
+            "KANE  \n\nFood!\n\n\n",
+            "BRICK\nHi.\n\nSTEEL ^\nHi.\n\nINT. MESS\n\nKANE\nFood!\n\nPARKER ^\n(chewing)\nFood!\n",
+        ];
+        for text in texts {
+            assert_same_as_parse_document(text);
+        }
+    }
+
+    #[test]
+    fn test_every_prefix_matches_parse_document() {
+        let text = "\u{feff}Title: Alien\nAuthor:\n    Dan O'Bannon\n\n# ACT ONE\n\nINT. MESS\n\n\
+            The crew eats.\n\nKANE ^\n(eating\nslowly)\nFood!\n\n\nDEALER \r\nTen.\r\n  \r\n\
+            (beat)\nFour.\n \nCUT TO:\n";
+        for (end, _) in text.char_indices() {
+            assert_same_as_parse_document(&text[..end]);
+        }
+        let text = text.replace("\r\n", "\n").replace('\n', "\r");
+        for (end, _) in text.char_indices() {
+            assert_same_as_parse_document(&text[..end]);
+        }
+    }
+
+    #[test]
+    fn test_dialogue_events() {
+        let text = "Title: Spans\n\nINT. MESS\n\nKANE\n(eating)\nFood!\n\nCUT TO:\n";
+        let titlepage = TitlePage {
+            title: Some("Spans".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            events(text),
+            vec![
+                Event::TitlePage(titlepage),
+                Event::StartScene,
+                Event::Line(Line::Scene("INT. MESS".to_owned())),
+                Event::StartDialogue,
+                Event::Line(Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false
+                }),
+                Event::Line(Line::Parenthetical("eating".to_owned())),
+                Event::Line(Line::Dialogue("Food!".to_owned())),
+                Event::EndDialogue,
+                Event::Line(Line::Transition("CUT TO:".to_owned())),
+                Event::EndScene,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scene_events() {
+        let text = "# ACT ONE\n\nINT. MESS\n\nThe crew eats.\n\nEXT. SPACE\n\n!Nothing\nmore";
+        let events: Vec<_> = events(text)
+            .into_iter()
+            .map(|event| match event {
+                Event::Line(line) => format!("{:?}", line.as_fountain()),
+                event => format!("{:?}", event),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                "\"# ACT ONE\"",
+                "StartScene",
+                "\"INT. MESS\"",
+                "\"The crew eats.\"",
+                "EndScene",
+                "StartScene",
+                "\"EXT. SPACE\"",
+                "\"Nothing\"",
+                "EndScene",
+                "Unparsed(\"more\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_dialogue() {
+        // Dialogue runs until a blank line, however far away that is.
+        let speech: String = (0..50).map(|i| format!("Line {}.\n  \n", i)).collect();
+        let text = format!("KANE\n{}\nCUT TO:\n", speech);
+        let events = events(&text);
+        assert_eq!(events.len(), 1 + 100 + 2 + 1);
+        assert_eq!(events[102], Event::EndDialogue);
+    }
+
+    #[test]
+    fn test_unparsed() {
+        let text = "!LOUD\nNot a new block\n";
+        assert_eq!(
+            events(text),
+            vec![
+                Event::Line(Line::Action("LOUD".to_owned())),
+                Event::Unparsed("Not a new block\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reads_lines_ending_in_cr() {
+        // The reader fails after the text, so any event before the error was parsed without
+        // reading the whole input.
+        struct Failing;
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("failed"))
+            }
+        }
+        let text = "INT. MESS\r\rKANE\rFood!\r\rCUT TO:\r\r".repeat(10);
+        let reader = BufReader::new(io::Read::chain(text.as_bytes(), Failing));
+        let events: Vec<_> = Events::new(reader)
+            .take(3)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::StartScene,
+                Event::Line(Line::Scene("INT. MESS".to_owned())),
+                Event::StartDialogue,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_io_error() {
+        let bytes: &[u8] = b"INT. MESS\n\n\xff\n";
+        let results: Vec<_> = Events::new(bytes).collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+}
@@ -1,5 +1,11 @@
 use super::data::*;
-use super::utils::*;
+use super::lock::{Lock, NumberedScene};
+use super::pages::Pagination;
+use super::prelude::*;
+use super::revisions::Revision;
+use super::scenes::without_scene_number;
+use super::sides::{SideScene, Unfeatured};
+use alloc::collections::BTreeMap;
 
 const DD_START: &str = "<div class='dual-dialogue'>";
 const DD_END: &str = "</div> <!-- end dual dialogue -->";
@@ -8,16 +14,19 @@ fn line_as_html(line: &Line) -> String {
     match line {
         Line::Scene(s) => format!("<p class='scene'>{}</p>", s),
         Line::Action(s) => format!("<p class='action'>{}</p>", s),
+        Line::Dialogue(s) if s.is_empty() => "<p class='dialogue'><br></p>".to_owned(),
         Line::Dialogue(s) => format!("<p class='dialogue'>{}</p>", s),
         Line::Speaker { name, is_dual: _ } => format!("<p class='speaker'>{}</p>", name),
         Line::Parenthetical(s) => format!("<p class='parenthetical'>({})</p>", s),
         Line::Transition(s) => format!("<p class='transition'>({})</p>", s),
         Line::Lyric(s) => format!("<p class='lyric'>({})</p>", s),
+        // Sections aren't printed, and `as_nodes_with` skips them.
+        Line::Section { .. } => String::new(),
     }
 }
 
 impl TitlePage {
-    fn as_html(&self) -> String {
+    pub(crate) fn as_html(&self) -> String {
         let title = format!(
             "<h1 class='titlepage'>{}</h1>",
             self.title.clone().unwrap_or_else(|| "Untitled".to_string())
@@ -57,40 +66,296 @@ impl Document {
             as_nodes(&self.lines).join("\n")
         )
     }
+
+    /// Renders the document page by page, like `as_html`, marking every line which changed since
+    /// the previous draft with an asterisk in the right margin. Each page has a header with its
+    /// page number, and pages with changes are also stamped with the revision's name and date.
+    /// Root element is a div.
+    pub fn as_revised_html(&self, previous: &Document, revision: &Revision) -> String {
+        self.as_paginated_html(&self.paginate(), None, Some((previous, revision)))
+    }
+
+    /// Renders the document page by page using the scene and page numbers of a locked draft (see
+    /// `Document::lock`). Scene Headings are numbered, deleted scenes are shown as OMITTED, and
+    /// inserted pages are numbered like "45A". Revisions since the previous draft are marked too,
+    /// like `as_revised_html`, if a previous draft is given. Root element is a div.
+    pub fn as_locked_html(
+        &self,
+        lock: &Lock,
+        revised_from: Option<(&Document, &Revision)>,
+    ) -> String {
+        let numbers = self.locked_scene_numbers(lock);
+        self.as_paginated_html(&self.paginate_locked(lock), Some(&numbers), revised_from)
+    }
+
+    fn as_paginated_html(
+        &self,
+        pagination: &Pagination,
+        numbers: Option<&[NumberedScene]>,
+        revised_from: Option<(&Document, &Revision)>,
+    ) -> String {
+        let revised = revised_from
+            .map(|(previous, _)| self.revised_lines(previous))
+            .unwrap_or_default();
+        let (stamp, color) = match revised_from {
+            Some((_, revision)) => (
+                match &revision.date {
+                    Some(date) => format!("{} - {}", revision.name, date),
+                    None => revision.name.clone(),
+                },
+                revision
+                    .color()
+                    .map(|c| format!(" revision-{}", c.to_lowercase()))
+                    .unwrap_or_default(),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        // Scene numbers by the index of their Scene Heading, and the OMITTED scenes before them.
+        let scenes = self.scenes();
+        let mut scene_numbers: BTreeMap<usize, &str> = BTreeMap::new();
+        let mut omitted_before: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        let mut omitted: Vec<&str> = Vec::new();
+        for numbered in numbers.unwrap_or_default() {
+            match numbered
+                .scene
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| scenes.get(i))
+            {
+                Some(scene) => {
+                    scene_numbers.insert(scene.start, &numbered.number);
+                    omitted_before.insert(scene.start, core::mem::take(&mut omitted));
+                }
+                None => omitted.push(&numbered.number),
+            }
+        }
+
+        let last_page = pagination.pages.len().saturating_sub(1);
+        let pages: Vec<_> = pagination
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(p, page)| {
+                let is_revised = revised.range(page.lines.clone()).next().is_some();
+                let mut nodes = as_nodes_with(&self.lines[page.lines.clone()], |i, line| {
+                    let index = page.lines.start + i;
+                    let mut html = match (line, scene_numbers.get(&index)) {
+                        (Line::Scene(heading), Some(number)) => numbered_scene(heading, number),
+                        _ => line_as_html(line),
+                    };
+                    if revised.contains(&index) {
+                        html = mark_revised(&html);
+                    }
+                    match omitted_before.get(&index) {
+                        Some(before) if !before.is_empty() => {
+                            let before: Vec<_> = before.iter().map(|n| omitted_scene(n)).collect();
+                            format!("{}\n{}", before.join("\n"), html)
+                        }
+                        _ => html,
+                    }
+                });
+                if p == last_page {
+                    nodes.extend(omitted.iter().map(|n| omitted_scene(n)));
+                }
+                let header = format!(
+                    "<div class='page-header'><span class='revision'>{}</span><span class='page-number'>{}.</span></div>",
+                    if is_revised { stamp.as_str() } else { "" },
+                    page.label,
+                );
+                format!(
+                    "<div class='page{}'>\n{}\n{}\n</div>",
+                    if is_revised { color.as_str() } else { "" },
+                    header,
+                    nodes.join("\n"),
+                )
+            })
+            .collect();
+        format!(
+            "<div>\n{}\n{}\n</div>\n",
+            if self.titlepage == TitlePage::default() {
+                "".to_owned()
+            } else {
+                self.titlepage.as_html()
+            },
+            pages.join("\n<p class='page-break'></p>\n")
+        )
+    }
+}
+
+impl Document {
+    /// Renders sides for a character (see `Document::sides`). Each scene keeps its scene number
+    /// and shows the page it was on in the full script. Scenes which don't feature the character
+    /// are left out or struck through. Root element is a div.
+    pub fn as_sides_html(
+        &self,
+        character: &str,
+        sides: &[SideScene],
+        unfeatured: Unfeatured,
+    ) -> String {
+        let scenes = self.scenes();
+        let rendered: Vec<_> = sides
+            .iter()
+            .filter(|side| side.featured || unfeatured == Unfeatured::CrossOut)
+            .filter_map(|side| scenes.get(side.scene - 1).map(|scene| (side, scene)))
+            .map(|(side, scene)| {
+                let nodes = as_nodes_with(scene.lines, |_, line| match line {
+                    Line::Scene(heading) => numbered_scene(heading, &side.number),
+                    _ => line_as_html(line),
+                });
+                format!(
+                    "<div class='side{}'>\n<div class='page-header'><span></span><span class='page-number'>Page {}</span></div>\n{}\n</div>",
+                    if side.featured { "" } else { " crossed-out" },
+                    side.page,
+                    nodes.join("\n"),
+                )
+            })
+            .collect();
+        format!(
+            "<div>\n<h1 class='titlepage'>{}</h1>\n<h3 class='titlepage'>Sides: {}</h3>\n{}\n</div>\n",
+            self.titlepage.title.as_deref().unwrap_or("Untitled"),
+            character,
+            rendered.join("\n")
+        )
+    }
+}
+
+/// A Scene Heading with its scene number printed in both margins.
+fn numbered_scene(heading: &str, number: &str) -> String {
+    // Don't print an explicit scene number (e.g. "INT. MESS #12#") twice.
+    let heading = without_scene_number(heading);
+    format!(
+        "<p class='scene'><span class='scene-number'>{n}</span>{}<span class='scene-number'>{n}</span></p>",
+        heading,
+        n = number
+    )
+}
+
+/// The placeholder for a scene which was deleted after the script was locked.
+fn omitted_scene(number: &str) -> String {
+    format!(
+        "<p class='scene omitted'><span class='scene-number'>{n}</span>OMITTED<span class='scene-number'>{n}</span></p>",
+        n = number
+    )
+}
+
+/// Adds a revision mark to the end of a rendered line.
+fn mark_revised(html: &str) -> String {
+    let mark = "<span class='revision-mark'>*</span>";
+    match html.strip_suffix("</p>") {
+        Some(start) => format!("{}{}</p>", start, mark),
+        None => format!("{}{}", html, mark),
+    }
+}
+
+pub(crate) fn as_nodes(lines: &[Line]) -> Vec<String> {
+    as_nodes_with(lines, |_, line| line_as_html(line))
 }
 
-fn as_nodes(lines: &[Line]) -> Vec<String> {
-    // Render all the lines
-    let mut nodes: Vec<String> = lines.iter().map(line_as_html).collect();
-
-    // Now go back and add dual dialogue elements
-    let n = lines.len();
-    for i in 0..n {
-        if let Line::Speaker { is_dual: true, .. } = lines[i] {
-            if let Some(dd) = dual_dialogue_bounds(lines, i) {
-                nodes.insert(dd.start, DD_START.to_owned());
-                nodes.insert(dd.end + 1, DD_END.to_owned());
+/// Renders lines using the given function, which is passed each line's index within `lines`.
+/// Sections are left out, since they aren't printed. Dual dialogue, i.e. a carated Speaker's
+/// block and the Speaker's block before it, is wrapped in a div.
+fn as_nodes_with<F>(lines: &[Line], render: F) -> Vec<String>
+where
+    F: Fn(usize, &Line) -> String,
+{
+    // How many dual dialogue divs open and close before each line (or at the end).
+    let mut opens = vec![0; lines.len() + 1];
+    let mut closes = vec![0; lines.len() + 1];
+    let next_dialogue = next_dialogue(lines);
+    let mut last_speaker = None;
+    for (i, line) in lines.iter().enumerate() {
+        if let Line::Speaker { is_dual, .. } = line {
+            if let (true, Some(start), Some(mut end)) = (*is_dual, last_speaker, next_dialogue[i]) {
+                // The Speaker's lines run on past their first line of Dialogue.
+                while matches!(lines.get(end + 1), Some(l) if l.is_dialogue() || l.is_parenthetical())
+                {
+                    end += 1;
+                }
+                opens[start] += 1;
+                closes[end + 1] += 1;
             }
+            last_speaker = Some(i);
         }
     }
+
+    let mut nodes = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        nodes.extend(core::iter::repeat_n(DD_END.to_owned(), closes[i]));
+        nodes.extend(core::iter::repeat_n(DD_START.to_owned(), opens[i]));
+        if !line.is_section() {
+            nodes.push(render(i, line));
+        }
+    }
+    nodes.extend(core::iter::repeat_n(DD_END.to_owned(), closes[lines.len()]));
     nodes
 }
 
-// Find the start/end bounds of the dual dialogue block, indicated by a carated Speaker block
-// at the index provided.
-fn dual_dialogue_bounds(lines: &[Line], dual_dialogue_carat: usize) -> Option<DualDialogue> {
-    let start = position_before(lines, dual_dialogue_carat, |line| line.is_speaker());
-    let end = position_after(lines, dual_dialogue_carat, |line| line.is_dialogue());
-    match (start, end) {
-        (Some(start), Some(end)) => Some(DualDialogue {
-            start,
-            end: end + 1,
-        }),
-        _ => None,
+/// For each line, the index of the first Dialogue after it, if there is one.
+fn next_dialogue(lines: &[Line]) -> Vec<Option<usize>> {
+    let mut next = vec![None; lines.len()];
+    let mut following = None;
+    for (i, line) in lines.iter().enumerate().rev() {
+        next[i] = following;
+        if line.is_dialogue() {
+            following = Some(i);
+        }
     }
+    next
 }
 
-struct DualDialogue {
-    pub start: usize,
-    pub end: usize,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::ErrorKind;
+
+    #[test]
+    fn test_dual_dialogue() {
+        let text = "\
+BRICK
+Screw retirement.
+
+STEEL ^
+Screw retirement.
+
+INT. MESS
+
+KANE
+Food!
+
+PARKER ^
+(chewing)
+Food!
+Good food.
+";
+        let (_, doc) = crate::parse_document::<(&str, ErrorKind)>(text).unwrap();
+        let nodes = as_nodes(&doc.lines);
+        let divs: Vec<_> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| *node == DD_START || *node == DD_END)
+            .map(|(i, node)| (i, node == DD_START))
+            .collect();
+        // Each div wraps both speakers' blocks, however many came before it or lines they have.
+        assert_eq!(divs, vec![(0, true), (5, false), (7, true), (14, false)]);
+        assert_eq!(nodes.len(), doc.lines.len() + 4);
+    }
+
+    #[test]
+    fn test_sections_not_printed() {
+        let text = "\
+# ACT ONE
+
+INT. MESS
+
+## The meal
+
+KANE
+Food!
+";
+        let (_, doc) = crate::parse_document::<(&str, ErrorKind)>(text).unwrap();
+        let nodes = as_nodes(&doc.lines);
+        assert_eq!(nodes.len(), doc.lines.len() - 2);
+        assert!(nodes.iter().all(|node| !node.contains("ACT ONE")));
+        assert!(!doc.as_html().contains("The meal"));
+    }
 }
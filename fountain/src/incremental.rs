@@ -0,0 +1,400 @@
+//! Reparses a document after an edit, for editors which reparse on every keystroke. Only the
+//! blocks (see `parse::block`) around the edit are parsed again, so an edit costs about the same
+//! however long the script is.
+use super::data::*;
+use super::options::ParseOptions;
+use super::parse::{blocks_from, titlepage_prefix, ParsedBlock};
+use super::prelude::*;
+use super::source::Span;
+use core::fmt;
+use core::ops::Range;
+
+/// Replaces the text in `span` (byte offsets into the old text) with `text`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: usize, end: usize, text: impl Into<String>) -> Self {
+        TextEdit {
+            span: Span { start, end },
+            text: text.into(),
+        }
+    }
+}
+
+/// How an edit changed the document's lines: `removed` lines starting at `start` were replaced by
+/// `inserted` new ones. Lines after them are unchanged, though their spans may have moved.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Change {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: usize,
+    /// Whether the title page changed.
+    pub titlepage: bool,
+}
+
+impl Change {
+    /// Indices of the new lines within `Document::lines`.
+    pub fn inserted_lines(&self) -> Range<usize> {
+        self.start..self.start + self.inserted
+    }
+
+    /// Whether the edit changed the document at all.
+    pub fn is_empty(&self) -> bool {
+        self.removed == 0 && self.inserted == 0 && !self.titlepage
+    }
+}
+
+/// An edit whose span isn't within the text, or doesn't start and end between characters.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct InvalidEdit(pub Span);
+
+impl fmt::Display for InvalidEdit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Can't edit {}..{}, it isn't a range of the text",
+            self.0.start, self.0.end
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidEdit {}
+
+/// Where a block of the body is, and the index of its first line within `Document::lines`.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    span: Span,
+    first_line: usize,
+}
+
+/// A parsed document which can be edited and reparsed. It gives the same result as
+/// `parse_document_with_spans` on the edited text, except that text which would make that fail is
+/// left unparsed instead.
+/// ```
+/// use fountain::incremental::{Parsed, TextEdit};
+/// use fountain::data::Line;
+///
+/// let mut parsed = Parsed::new("INT. MESS\n\nKANE\nFood!\n");
+/// let change = parsed.edit(&TextEdit::new(11, 15, "PARKER")).unwrap();
+/// assert_eq!(parsed.text(), "INT. MESS\n\nPARKER\nFood!\n");
+/// assert_eq!(change.inserted_lines(), 1..2);
+/// assert_eq!(
+///     parsed.document().lines[1],
+///     Line::Speaker { name: "PARKER".to_owned(), is_dual: false }
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Parsed {
+    text: String,
+    document: Document,
+    /// The nth span is where `document.lines[n]` came from.
+    spans: Vec<Span>,
+    blocks: Vec<Block>,
+    /// Where parsing stopped.
+    end: usize,
+    options: ParseOptions,
+}
+
+impl Parsed {
+    pub fn new(text: impl Into<String>) -> Self {
+        Parsed::with_options(text, ParseOptions::default())
+    }
+
+    /// Parses the text, and any edits to it, the way the options say to (like
+    /// `parse_document_with_spans_and_options`).
+    pub fn with_options(text: impl Into<String>, options: ParseOptions) -> Self {
+        let text = text.into();
+        let (titlepage, body_start, after_blank_line) = titlepage_prefix(&text);
+        let parsed = blocks_from(&text, body_start, after_blank_line, &options, |_| false);
+        let mut parsed_doc = Parsed {
+            document: Document {
+                lines: Vec::new(),
+                titlepage,
+            },
+            spans: Vec::new(),
+            blocks: Vec::new(),
+            end: parsed.end,
+            text,
+            options,
+        };
+        parsed_doc.push_blocks(parsed.blocks);
+        parsed_doc
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Where each of the document's lines came from in the text.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// The text after the point where parsing stopped, which is empty if all of it was parsed.
+    pub fn unparsed(&self) -> &str {
+        &self.text[self.end..]
+    }
+
+    /// Applies the edit to the text, and reparses the blocks it could have changed.
+    pub fn edit(&mut self, edit: &TextEdit) -> Result<Change, InvalidEdit> {
+        let Span { start, end } = edit.span;
+        let in_text = |i| self.text.is_char_boundary(i);
+        if start > end || !in_text(start) || !in_text(end) {
+            return Err(InvalidEdit(edit.span));
+        }
+        let text = format!("{}{}{}", &self.text[..start], edit.text, &self.text[end..]);
+        let removed_len = end - start;
+        let inserted_end = start + edit.text.len();
+        // Moves an offset after the edit in the old text to the same place in the new text.
+        let shift = |offset: usize| offset - removed_len + edit.text.len();
+
+        // Parsing a block can look ahead at the first line of the next one, so the block before
+        // the edit is reparsed too. Edits near the start might change the title page, so they
+        // reparse everything.
+        let touched = self.blocks.partition_point(|b| b.span.end < start);
+        if touched < 2 {
+            let reparsed = Parsed::with_options(text, self.options.clone());
+            let old = core::mem::replace(self, reparsed);
+            let old_lines = &old.document.lines;
+            let mut change = changed_lines(old_lines, &self.document.lines, 0);
+            change.titlepage = old.document.titlepage != self.document.titlepage;
+            return Ok(change);
+        }
+        let restart = self.blocks[touched - 1];
+
+        // Blocks are parsed until one starts where an old block started after the edit, since
+        // from there on the text (and so the parse) is the same as before.
+        let mut resynced_block = None;
+        let old_blocks = &self.blocks;
+        // The restart block is never the first, which is only reparsed along with the title page.
+        let parsed = blocks_from(&text, restart.span.start, true, &self.options, |offset| {
+            if offset < inserted_end {
+                return false;
+            }
+            let old_offset = offset + removed_len - edit.text.len();
+            match old_blocks.binary_search_by_key(&old_offset, |b| b.span.start) {
+                Ok(i) => {
+                    resynced_block = Some(i);
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+        let resumed = resynced_block.unwrap_or(self.blocks.len());
+        let old_lines_end = self
+            .blocks
+            .get(resumed)
+            .map_or(self.document.lines.len(), |b| b.first_line);
+
+        let new_lines: Vec<Line> = parsed
+            .blocks
+            .iter()
+            .flat_map(|b| b.lines.iter().cloned())
+            .collect();
+        let old_lines = &self.document.lines[restart.first_line..old_lines_end];
+        let change = changed_lines(old_lines, &new_lines, restart.first_line);
+
+        // Keep everything after the resync point, moved to where it is in the new text.
+        let tail_lines = self.document.lines.split_off(old_lines_end);
+        let tail_spans = self.spans.split_off(old_lines_end);
+        let tail_blocks = self.blocks.split_off(resumed);
+        self.document.lines.truncate(restart.first_line);
+        self.spans.truncate(restart.first_line);
+        self.blocks.truncate(touched - 1);
+        self.push_blocks(parsed.blocks);
+        let first_line = self.document.lines.len();
+        self.blocks
+            .extend(tail_blocks.into_iter().map(|block| Block {
+                span: shift_span(block.span, shift),
+                first_line: first_line + block.first_line - old_lines_end,
+            }));
+        self.spans
+            .extend(tail_spans.into_iter().map(|span| shift_span(span, shift)));
+        self.document.lines.extend(tail_lines);
+        self.end = if parsed.resynced {
+            shift(self.end)
+        } else {
+            parsed.end
+        };
+        self.text = text;
+        Ok(change)
+    }
+
+    fn push_blocks(&mut self, blocks: Vec<ParsedBlock>) {
+        for block in blocks {
+            self.blocks.push(Block {
+                span: block.span,
+                first_line: self.document.lines.len(),
+            });
+            self.document.lines.extend(block.lines);
+            self.spans.extend(block.spans);
+        }
+    }
+}
+
+fn shift_span(span: Span, shift: impl Fn(usize) -> usize) -> Span {
+    Span {
+        start: shift(span.start),
+        end: shift(span.end),
+    }
+}
+
+/// The change from replacing the `old` lines at `start` with `new`, ignoring any lines at either
+/// end which didn't actually change.
+fn changed_lines(old: &[Line], new: &[Line], start: usize) -> Change {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    Change {
+        start: start + prefix,
+        removed: old.len() - prefix - suffix,
+        inserted: new.len() - prefix - suffix,
+        titlepage: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::ErrorKind;
+
+    const TEXT: &str = "\
+Title: Alien
+
+INT. MESS - NIGHT
+
+The crew eats.
+
+KANE
+(coughing)
+Food!
+
+PARKER ^
+More food!
+
+EXT. NOSTROMO - NIGHT
+
+> CUT TO:
+
+~Lyric
+
+# ACT TWO
+
+INT. GALLEY - DAY
+
+Ash watches.
+";
+
+    /// The incremental parse must always agree with parsing the edited text from scratch.
+    fn check(parsed: &Parsed) {
+        let full = crate::parse_document_with_spans::<(&str, ErrorKind)>(parsed.text());
+        if let Ok((unparsed, (doc, spans))) = full {
+            assert_eq!(parsed.document(), &doc, "text: {:?}", parsed.text());
+            assert_eq!(parsed.spans(), &spans[..], "text: {:?}", parsed.text());
+            assert_eq!(parsed.unparsed(), unparsed, "text: {:?}", parsed.text());
+        }
+        let fresh = Parsed::new(parsed.text());
+        assert_eq!(
+            parsed.document(),
+            fresh.document(),
+            "text: {:?}",
+            parsed.text()
+        );
+        assert_eq!(parsed.spans(), fresh.spans());
+        assert_eq!(parsed.unparsed(), fresh.unparsed());
+    }
+
+    fn apply(parsed: &mut Parsed, edit: TextEdit) {
+        let old = parsed.document().lines.clone();
+        let change = parsed.edit(&edit).unwrap();
+        check(parsed);
+        // Replaying the change on the old lines gives the new ones.
+        let mut replayed = old;
+        let new = &parsed.document().lines[change.inserted_lines()];
+        replayed.splice(
+            change.start..change.start + change.removed,
+            new.iter().cloned(),
+        );
+        assert_eq!(replayed, parsed.document().lines);
+    }
+
+    #[test]
+    fn test_new_matches_full_parse() {
+        check(&Parsed::new(TEXT));
+        check(&Parsed::new(""));
+        check(&Parsed::new("INT. MESS\n\nKANE\n(eating) loudly\nFood!\n"));
+    }
+
+    #[test]
+    fn test_every_single_character_edit() {
+        let boundaries = (0..=TEXT.len()).filter(|&i| TEXT.is_char_boundary(i));
+        for i in boundaries {
+            for inserted in ["x", "X", "\n", "(", "^", "\n\n"] {
+                apply(&mut Parsed::new(TEXT), TextEdit::new(i, i, inserted));
+            }
+            if i < TEXT.len() {
+                apply(&mut Parsed::new(TEXT), TextEdit::new(i, i + 1, ""));
+            }
+        }
+    }
+
+    #[test]
+    fn test_typing_a_scene() {
+        let mut parsed = Parsed::new(TEXT);
+        let typed = "\nINT. AIRLOCK - DAY\n\nDALLAS\n(quietly)\nOpen it.\n";
+        let at = TEXT.find("EXT. NOSTROMO").unwrap() - 1;
+        for (n, c) in typed.char_indices() {
+            apply(&mut parsed, TextEdit::new(at + n, at + n, c.to_string()));
+        }
+        let expected = TEXT.replacen("\nEXT. NOSTROMO", &format!("{}\nEXT. NOSTROMO", typed), 1);
+        assert_eq!(parsed.text(), expected);
+    }
+
+    #[test]
+    fn test_change() {
+        let mut parsed = Parsed::new(TEXT);
+        let at = TEXT.find("Food!").unwrap();
+        let change = parsed.edit(&TextEdit::new(at, at + 4, "Drink")).unwrap();
+        let index = parsed
+            .document()
+            .lines
+            .iter()
+            .position(|l| l == &Line::Dialogue("Drink!".to_owned()))
+            .unwrap();
+        assert_eq!(
+            change,
+            Change {
+                start: index,
+                removed: 1,
+                inserted: 1,
+                titlepage: false
+            }
+        );
+        let change = parsed.edit(&TextEdit::new(7, 12, "Aliens")).unwrap();
+        assert!(change.titlepage);
+        assert_eq!(change.removed, 0);
+        assert!(parsed.edit(&TextEdit::new(0, 0, "")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_edit() {
+        let mut parsed = Parsed::new("INT. CAFÉ\n");
+        assert!(parsed.edit(&TextEdit::new(9, 9, "x")).is_err());
+        assert!(parsed.edit(&TextEdit::new(3, 99, "x")).is_err());
+        assert!(parsed.edit(&TextEdit::new(3, 2, "x")).is_err());
+        assert_eq!(parsed.text(), "INT. CAFÉ\n");
+    }
+}
@@ -34,9 +34,49 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## no_std
+//!
+//! The parser, data model and renderers only need `alloc`. Turn off the default `std` feature to
+//! build the crate with `#![no_std]`, e.g. for embedded devices. The `project` module (which reads
+//! files) and the `events` module (which reads from a `BufRead`) need `std`, as does the
+//! `json_schema` feature.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
+pub mod borrowed;
+pub mod breakdown;
+pub mod characters;
 pub mod data;
+pub mod diff;
+pub mod edit;
+#[cfg(feature = "std")]
+pub mod events;
 mod html;
+pub mod incremental;
+pub mod lint;
+pub mod lock;
+pub mod options;
+pub mod pages;
 mod parse;
+mod prelude;
+#[cfg(feature = "std")]
+pub mod project;
+pub mod query;
+pub mod revisions;
+pub mod runtime;
+pub mod scenes;
+#[cfg(feature = "use_serde")]
+pub mod schema;
+pub mod sides;
+pub mod source;
+pub mod tokens;
 mod utils;
+mod write;
 pub use parse::document as parse_document;
+pub use parse::document_borrowed as parse_document_borrowed;
+pub use parse::document_borrowed_with_options as parse_document_borrowed_with_options;
+pub use parse::document_with_options as parse_document_with_options;
+pub use parse::document_with_spans as parse_document_with_spans;
+pub use parse::document_with_spans_and_options as parse_document_with_spans_and_options;
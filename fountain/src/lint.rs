@@ -0,0 +1,537 @@
+//! Checks Fountain documents for common mistakes, like misspelled character names or
+//! transitions which don't lead anywhere.
+use super::characters::normalize_name;
+use super::data::*;
+use super::options::ParseOptions;
+use super::prelude::*;
+use super::scenes::SceneHeading;
+use super::source::{position, Position, Span};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// A check which the linter can perform. Every rule is enabled by default.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum Rule {
+    /// Text which the parser couldn't understand.
+    Unparsed,
+    /// A character name which is very similar to a more common one, e.g. KAINE and KANE.
+    InconsistentCharacterName,
+    /// A Scene Heading like "INT. MESS" which doesn't say whether it's DAY or NIGHT.
+    MissingTimeOfDay,
+    /// A (parenthetical) which isn't part of any dialogue.
+    OrphanParenthetical,
+    /// A dual dialogue caret (^) on a Speaker who doesn't follow another Speaker's dialogue.
+    UnpairedDualDialogue,
+    /// A Transition which isn't followed by a Scene Heading.
+    TransitionWithoutScene,
+    /// A Speaker with no name, or a character's name with no dialogue after it, which is printed
+    /// as Action.
+    EmptySpeaker,
+    /// A Parenthetical longer than `LintConfig::max_parenthetical_length`.
+    LongParenthetical,
+    /// A /* boneyard */ with a missing start or end.
+    UnbalancedBoneyard,
+    /// A [[note]] with a missing start or end.
+    UnbalancedNote,
+}
+
+impl Rule {
+    /// Every rule, in the order they're documented.
+    pub const ALL: &'static [Rule] = &[
+        Rule::Unparsed,
+        Rule::InconsistentCharacterName,
+        Rule::MissingTimeOfDay,
+        Rule::OrphanParenthetical,
+        Rule::UnpairedDualDialogue,
+        Rule::TransitionWithoutScene,
+        Rule::EmptySpeaker,
+        Rule::LongParenthetical,
+        Rule::UnbalancedBoneyard,
+        Rule::UnbalancedNote,
+    ];
+
+    /// The rule's name, as used in config files, e.g. "missing-time-of-day".
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Unparsed => "unparsed",
+            Rule::InconsistentCharacterName => "inconsistent-character-name",
+            Rule::MissingTimeOfDay => "missing-time-of-day",
+            Rule::OrphanParenthetical => "orphan-parenthetical",
+            Rule::UnpairedDualDialogue => "unpaired-dual-dialogue",
+            Rule::TransitionWithoutScene => "transition-without-scene",
+            Rule::EmptySpeaker => "empty-speaker",
+            Rule::LongParenthetical => "long-parenthetical",
+            Rule::UnbalancedBoneyard => "unbalanced-boneyard",
+            Rule::UnbalancedNote => "unbalanced-note",
+        }
+    }
+
+    /// How bad it is to break this rule.
+    pub fn severity(self) -> Severity {
+        match self {
+            Rule::Unparsed => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .iter()
+            .find(|rule| rule.name() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown lint rule '{}'", s))
+    }
+}
+
+/// Errors mean the document probably won't render properly. Warnings are style problems.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Which rules the linter checks, and how strictly.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LintConfig {
+    disabled: Vec<Rule>,
+    /// Parentheticals with more characters than this break the LongParenthetical rule.
+    pub max_parenthetical_length: usize,
+    /// How to parse the text. Its vocabulary is also used to read Scene Headings, e.g. for the
+    /// MissingTimeOfDay rule.
+    pub parse_options: ParseOptions,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            disabled: Vec::new(),
+            max_parenthetical_length: 40,
+            parse_options: ParseOptions::default(),
+        }
+    }
+}
+
+impl LintConfig {
+    /// Turns the given rule on or off.
+    pub fn set_enabled(&mut self, rule: Rule, enabled: bool) {
+        self.disabled.retain(|&r| r != rule);
+        if !enabled {
+            self.disabled.push(rule);
+        }
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// One problem found by the linter.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    /// The text which breaks the rule.
+    pub span: Span,
+    /// Where the span starts.
+    pub position: Position,
+}
+
+/// Parses the Fountain text and checks it against every enabled rule. Diagnostics are returned in
+/// the order they appear in the text.
+/// ```
+/// use fountain::lint::{lint, LintConfig, Rule};
+/// let text = "INT. MESS\n\nThe crew eats.\n";
+/// let diagnostics = lint(text, &LintConfig::default());
+/// assert_eq!(diagnostics[0].rule, Rule::MissingTimeOfDay);
+/// assert_eq!(diagnostics[0].position.line, 1);
+/// ```
+pub fn lint(text: &str, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        text,
+        config,
+        diagnostics: Vec::new(),
+    };
+    match crate::parse_document_with_spans_and_options::<(&str, _)>(text, &config.parse_options) {
+        Ok((unparsed, (doc, spans))) => {
+            if !unparsed.is_empty() {
+                let start = text.len() - unparsed.len();
+                let end = start + unparsed.find('\n').unwrap_or(unparsed.len());
+                linter.report(
+                    Rule::Unparsed,
+                    Span { start, end },
+                    "Couldn't parse this text, or anything after it".to_owned(),
+                );
+            }
+            linter.check_document(&doc, &spans);
+        }
+        Err(e) => linter.report(
+            Rule::Unparsed,
+            Span::default(),
+            format!("Couldn't parse the document: {:?}", e),
+        ),
+    }
+    linter.check_balanced(Rule::UnbalancedBoneyard, "/*", "*/");
+    linter.check_balanced(Rule::UnbalancedNote, "[[", "]]");
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+struct Linter<'a> {
+    text: &'a str,
+    config: &'a LintConfig,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        if self.config.is_enabled(rule) {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                severity: rule.severity(),
+                message,
+                span,
+                position: position(self.text, span.start),
+            });
+        }
+    }
+
+    fn check_document(&mut self, doc: &Document, spans: &[Span]) {
+        let misspellings = misspelled_names(doc);
+        for (i, (line, &span)) in doc.lines.iter().zip(spans).enumerate() {
+            let next = doc.lines[i + 1..].iter().find(|l| !l.is_section());
+            match line {
+                Line::Scene(heading) => {
+                    let heading =
+                        SceneHeading::parse_with(heading, &self.config.parse_options.vocabulary);
+                    if heading.setting.is_some() && heading.time.is_none() {
+                        let msg = "Scene Heading doesn't give a time of day, e.g. DAY or NIGHT";
+                        self.report(Rule::MissingTimeOfDay, span, msg.to_owned());
+                    }
+                }
+                Line::Action(s) if s.trim().starts_with('(') && s.trim().ends_with(')') => {
+                    let msg =
+                        "Parenthetical isn't part of any dialogue, so it'll be printed as Action";
+                    self.report(Rule::OrphanParenthetical, span, msg.to_owned());
+                }
+                Line::Action(s) if self.is_lone_name(s, span) => {
+                    let msg = format!(
+                        "{} has no dialogue, so it'll be printed as Action. Start the line with ! \
+                         if that's what you meant",
+                        s.trim()
+                    );
+                    self.report(Rule::EmptySpeaker, span, msg);
+                }
+                Line::Speaker { name, is_dual } => {
+                    let normalized = normalize_name(name);
+                    if normalized.is_empty() {
+                        let msg = "Speaker has no name";
+                        self.report(Rule::EmptySpeaker, span, msg.to_owned());
+                    }
+                    if *is_dual && !(i > 0 && doc.lines[i - 1].is_dialogue()) {
+                        let msg = format!(
+                            "{} is marked as dual dialogue (^), but doesn't follow another \
+                             character's dialogue",
+                            name
+                        );
+                        self.report(Rule::UnpairedDualDialogue, span, msg);
+                    }
+                    if let Some((_, usual)) = misspellings.iter().find(|(n, _)| *n == normalized) {
+                        let msg = format!(
+                            "{} looks like a misspelling of {}, which speaks more often",
+                            normalized, usual
+                        );
+                        self.report(Rule::InconsistentCharacterName, span, msg);
+                    }
+                }
+                Line::Parenthetical(s)
+                    if s.chars().count() > self.config.max_parenthetical_length =>
+                {
+                    let msg = format!(
+                        "Parenthetical is {} characters long, the maximum is {}",
+                        s.chars().count(),
+                        self.config.max_parenthetical_length
+                    );
+                    self.report(Rule::LongParenthetical, span, msg);
+                }
+                Line::Transition(_) if next.is_some_and(|l| !l.is_scene()) => {
+                    let msg = "Transition should be followed by a Scene Heading";
+                    self.report(Rule::TransitionWithoutScene, span, msg.to_owned());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// True if the Action looks like a Speaker, i.e. it's all uppercase, but is on a line of its
+    /// own, with no dialogue after it. Forced Action and lines like "FADE IN:" don't count.
+    fn is_lone_name(&self, action: &str, span: Span) -> bool {
+        let looks_like_name = action.chars().any(char::is_uppercase)
+            && !action.chars().any(char::is_lowercase)
+            && !action.trim_end().ends_with(':');
+        if !looks_like_name || self.text[span.start..].starts_with('!') {
+            return false;
+        }
+        let is_blank = |line: &str| line.trim_matches([' ', '\t']).is_empty();
+        // Skip the line endings either side of this line, to get the lines around it.
+        let before = &self.text[..span.start];
+        let before = (before.strip_suffix("\r\n"))
+            .or_else(|| before.strip_suffix(['\r', '\n']))
+            .unwrap_or(before);
+        let after = &self.text[span.end..];
+        let after = (after.strip_prefix("\r\n"))
+            .or_else(|| after.strip_prefix(['\r', '\n']))
+            .unwrap_or(after);
+        let line_before = before.rsplit(['\r', '\n']).next().unwrap_or("");
+        let line_after = after.split(['\r', '\n']).next().unwrap_or("");
+        is_blank(line_before) && is_blank(line_after)
+    }
+
+    /// Checks that every `open` marker has a matching `close` marker after it. These markers
+    /// can't be nested.
+    fn check_balanced(&mut self, rule: Rule, open: &str, close: &str) {
+        let mut unclosed: Option<usize> = None;
+        let mut i = 0;
+        while i < self.text.len() {
+            let rest = &self.text[i..];
+            if rest.starts_with(open) && unclosed.is_none() {
+                unclosed = Some(i);
+                i += open.len();
+            } else if rest.starts_with(close) {
+                if unclosed.take().is_none() {
+                    let span = Span {
+                        start: i,
+                        end: i + close.len(),
+                    };
+                    self.report(rule, span, format!("{} without a matching {}", close, open));
+                }
+                i += close.len();
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        if let Some(start) = unclosed {
+            let span = Span {
+                start,
+                end: start + open.len(),
+            };
+            self.report(rule, span, format!("{} without a matching {}", open, close));
+        }
+    }
+}
+
+/// Finds character names which are probably misspellings of a more common character's name.
+/// Returns pairs of (misspelling, usual spelling).
+fn misspelled_names(doc: &Document) -> Vec<(String, String)> {
+    // Characters are sorted by how often they speak, so the usual spelling always comes first.
+    let characters = doc.characters();
+    let mut misspellings = Vec::new();
+    for (i, rare) in characters.iter().enumerate() {
+        let usual = characters[..i]
+            .iter()
+            .find(|usual| usual.speeches > rare.speeches && similar_names(&usual.name, &rare.name));
+        if let Some(usual) = usual {
+            misspellings.push((rare.name.clone(), usual.name.clone()));
+        }
+    }
+    misspellings
+}
+
+/// Names are similar if they only differ in punctuation and spacing (MRS. THOMPSON and
+/// MRS THOMPSON), or if they differ by a single letter (KANE and KAINE).
+fn similar_names(a: &str, b: &str) -> bool {
+    let letters = |s: &str| -> Vec<char> { s.chars().filter(|c| c.is_alphanumeric()).collect() };
+    let (a, b) = (letters(a), letters(b));
+    a == b || (a.len().min(b.len()) >= 4 && edit_distance(&a, &b) == 1)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr.push(substitution.min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes::Vocabulary;
+
+    fn rules(text: &str) -> Vec<Rule> {
+        lint(text, &LintConfig::default())
+            .into_iter()
+            .map(|d| d.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_document() {
+        let text = "\
+INT. MESS - NIGHT
+
+KANE
+(eating)
+Food!
+
+CUT TO:
+
+EXT. NOSTROMO - NIGHT
+";
+        assert_eq!(rules(text), vec![]);
+    }
+
+    #[test]
+    fn test_missing_time_of_day() {
+        let diagnostics = lint("Some action.\n\nINT. MESS\n", &LintConfig::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::MissingTimeOfDay);
+        assert_eq!(diagnostics[0].position, Position { line: 3, column: 1 });
+        assert_eq!(diagnostics[0].span, Span { start: 14, end: 23 });
+    }
+
+    #[test]
+    fn test_missing_time_of_day_with_vocabulary() {
+        let text = "INT. KÜCHE - NACHT\n\nAnna kocht.\n";
+        assert_eq!(lint(text, &LintConfig::default()).len(), 1);
+        let config = LintConfig {
+            parse_options: ParseOptions::default().with_vocabulary(&Vocabulary::german()),
+            ..LintConfig::default()
+        };
+        assert_eq!(lint(text, &config), vec![]);
+    }
+
+    #[test]
+    fn test_disabled_rule() {
+        let mut config = LintConfig::default();
+        config.set_enabled(Rule::MissingTimeOfDay, false);
+        assert!(lint("INT. MESS\n", &config).is_empty());
+        config.set_enabled(Rule::MissingTimeOfDay, true);
+        assert_eq!(lint("INT. MESS\n", &config).len(), 1);
+    }
+
+    #[test]
+    fn test_inconsistent_character_name() {
+        let text = "\
+KANE
+Food!
+
+KANE
+More food!
+
+KAINE
+Food please!
+";
+        let diagnostics = lint(text, &LintConfig::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::InconsistentCharacterName);
+        assert_eq!(diagnostics[0].position.line, 7);
+    }
+
+    #[test]
+    fn test_similar_names() {
+        assert!(similar_names("MRS. THOMPSON", "MRS THOMPSON"));
+        assert!(similar_names("KANE", "KAINE"));
+        assert!(!similar_names("ASH", "ASK"));
+        assert!(!similar_names("KANE", "LAMBERT"));
+    }
+
+    #[test]
+    fn test_dialogue_rules() {
+        let text = "\
+(beat)
+
+BRETT ^
+Right.
+
+PARKER
+
+
+ASH
+(very very very very very very long parenthetical)
+Hmm.
+";
+        assert_eq!(
+            rules(text),
+            vec![
+                Rule::OrphanParenthetical,
+                Rule::UnpairedDualDialogue,
+                Rule::EmptySpeaker,
+                Rule::LongParenthetical,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_speaker() {
+        // An empty line of Dialogue is fine.
+        assert_eq!(rules("PARKER\n  \nWell?\n"), vec![]);
+        // So are forced Action, and lines like FADE IN:.
+        assert_eq!(rules("!PARKER\n\nFADE IN:\n"), vec![]);
+        let diagnostics = lint("Ripley waits.\r\n\r\nPARKER\r\n", &LintConfig::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::EmptySpeaker);
+        assert_eq!(diagnostics[0].position, Position { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn test_transition_without_scene() {
+        let text = "The crew eats.\n\nCUT TO:\n\nThe crew sleeps.\n\nFADE TO:\n";
+        assert_eq!(rules(text), vec![Rule::TransitionWithoutScene]);
+    }
+
+    #[test]
+    fn test_unbalanced_markers() {
+        let text = "Action [[with a note]].\n\nMore /* cut this\n\nAnd ]] this.\n";
+        let diagnostics = lint(text, &LintConfig::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].rule, Rule::UnbalancedBoneyard);
+        assert_eq!(diagnostics[0].position, Position { line: 3, column: 6 });
+        assert_eq!(diagnostics[1].rule, Rule::UnbalancedNote);
+        assert_eq!(diagnostics[1].position, Position { line: 5, column: 5 });
+    }
+
+    #[test]
+    fn test_rule_names() {
+        for rule in Rule::ALL {
+            assert_eq!(rule.name().parse::<Rule>(), Ok(*rule));
+        }
+        assert!("no-such-rule".parse::<Rule>().is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! Locked scene and page numbers. Once a script is locked for production, its scene and page
+//! numbers never change: material inserted later gets numbers like "12A" (between scenes 12 and
+//! 13) or "45A" (between pages 45 and 46), and deleted scenes leave an OMITTED placeholder.
+use super::data::*;
+use super::diff::{diff, LineEdit};
+use super::pages::Pagination;
+use super::prelude::*;
+use super::scenes::scene_number;
+use alloc::collections::BTreeSet;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// A scene number, and the scene it belongs to.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct NumberedScene {
+    /// e.g. "12" or "12A"
+    pub number: String,
+    /// The scene's position in the document, counting from 1 (see `Document::scenes`). None if
+    /// the scene was deleted after the script was locked, so only "OMITTED" is printed.
+    pub scene: Option<usize>,
+}
+
+/// Where one locked page starts.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct LockedPage {
+    /// The page number as printed, e.g. "45", "45A" or "44-45" (when page 45 was deleted).
+    pub label: String,
+    /// Index into the locked draft's lines of the first line on the page.
+    pub start: usize,
+}
+
+/// The scene and page numbers of a locked draft. Later drafts are numbered by comparing them with
+/// the locked draft. Store this (e.g. as JSON) to keep the numbers stable over several rounds of
+/// revisions, or create it from a previous draft with `Document::lock`.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Lock {
+    pub draft: Document,
+    /// Every scene number, in order, including OMITTED scenes.
+    pub scenes: Vec<NumberedScene>,
+    /// Every page, in order.
+    pub pages: Vec<LockedPage>,
+}
+
+impl Document {
+    /// Locks this draft's scene and page numbers. Scenes keep their explicit scene numbers (e.g.
+    /// "#12#"), and are otherwise numbered in order.
+    pub fn lock(&self) -> Lock {
+        Lock {
+            draft: self.clone(),
+            scenes: self
+                .scenes()
+                .iter()
+                .map(|scene| NumberedScene {
+                    number: scene_number(scene.heading)
+                        .map_or_else(|| scene.number.to_string(), str::to_owned),
+                    scene: Some(scene.number),
+                })
+                .collect(),
+            pages: self
+                .paginate()
+                .pages
+                .into_iter()
+                .map(|page| LockedPage {
+                    label: page.label,
+                    start: page.lines.start,
+                })
+                .collect(),
+        }
+    }
+
+    /// Locks this draft's numbers, keeping every number from an earlier lock. Use this to carry
+    /// A-numbers and OMITTED scenes forward into the next round of revisions.
+    pub fn relock(&self, lock: &Lock) -> Lock {
+        Lock {
+            draft: self.clone(),
+            scenes: self.locked_scene_numbers(lock),
+            pages: self
+                .paginate_locked(lock)
+                .pages
+                .into_iter()
+                .map(|page| LockedPage {
+                    label: page.label,
+                    start: page.lines.start,
+                })
+                .collect(),
+        }
+    }
+
+    /// Numbers this document's scenes so that every scene from the locked draft keeps its number.
+    /// Inserted scenes are numbered after the scene before them, e.g. "12A" and "12B" after 12,
+    /// or "A1" before scene 1. Deleted scenes are kept as OMITTED placeholders.
+    /// ```
+    /// use nom::error::VerboseError;
+    ///
+    /// let locked = "INT. MESS\n\nThe crew eats.\n\nINT. BRIDGE\n\nMother beeps.\n";
+    /// let locked = fountain::parse_document::<VerboseError<&str>>(locked).unwrap().1;
+    /// let revised = "INT. MESS\n\nThe crew eats.\n\nEXT. SPACE\n\nThe ship drifts.\n";
+    /// let revised = fountain::parse_document::<VerboseError<&str>>(revised).unwrap().1;
+    /// let numbers: Vec<_> = revised
+    ///     .locked_scene_numbers(&locked.lock())
+    ///     .into_iter()
+    ///     .map(|s| (s.number, s.scene))
+    ///     .collect();
+    /// assert_eq!(
+    ///     numbers,
+    ///     vec![
+    ///         ("1".to_owned(), Some(1)),
+    ///         ("2".to_owned(), None),
+    ///         ("2A".to_owned(), Some(2)),
+    ///     ]
+    /// );
+    /// ```
+    pub fn locked_scene_numbers(&self, lock: &Lock) -> Vec<NumberedScene> {
+        let mut used: BTreeSet<String> = lock.scenes.iter().map(|s| s.number.clone()).collect();
+        let mut emitted = vec![false; lock.scenes.len()];
+        let mut numbers = Vec::new();
+        let mut prev: Option<String> = None;
+        for scene in diff(&lock.draft, self).scenes {
+            match (scene.old, scene.new) {
+                (Some(0), _) | (_, Some(0)) | (None, None) => {}
+                (Some(old), new) => {
+                    let Some(i) = lock.scenes.iter().position(|s| s.scene == Some(old)) else {
+                        continue;
+                    };
+                    // OMITTED scenes stay just before the scene which followed them.
+                    let first_omitted = (0..i)
+                        .rev()
+                        .take_while(|&k| lock.scenes[k].scene.is_none() && !emitted[k])
+                        .last()
+                        .unwrap_or(i);
+                    emitted[first_omitted..=i].fill(true);
+                    numbers.extend(lock.scenes[first_omitted..i].iter().cloned());
+                    numbers.push(NumberedScene {
+                        number: lock.scenes[i].number.clone(),
+                        scene: new,
+                    });
+                    prev = Some(lock.scenes[i].number.clone());
+                }
+                (None, Some(new)) => {
+                    let number = match &prev {
+                        Some(prev) => next_label(prev, &used),
+                        None => {
+                            let first = lock.scenes.first().map_or("1", |s| s.number.as_str());
+                            next_label(&format!("@{}", first), &used)
+                        }
+                    };
+                    used.insert(number.clone());
+                    numbers.push(NumberedScene {
+                        number: number.clone(),
+                        scene: Some(new),
+                    });
+                    prev = Some(number);
+                }
+            }
+        }
+        let remaining = lock.scenes.iter().zip(&emitted).filter(|(_, e)| !**e);
+        numbers.extend(remaining.map(|(s, _)| NumberedScene {
+            number: s.number.clone(),
+            scene: None,
+        }));
+        numbers
+    }
+
+    /// Lays the document out onto pages without moving any locked page breaks. A locked page
+    /// which grows too long spills onto pages "45A", "45B" etc, and a locked page whose text was
+    /// all deleted is combined with the page before it, e.g. "44-45".
+    pub fn paginate_locked(&self, lock: &Lock) -> Pagination {
+        // Where each line of the locked draft ended up in this document. Deleted lines map to the
+        // line which took their place.
+        let mut new_position = vec![self.lines.len(); lock.draft.lines.len()];
+        let mut next_new = 0;
+        for edit in diff(&lock.draft, self).scenes.iter().flat_map(|s| &s.edits) {
+            match *edit {
+                LineEdit::Equal { old, new } => {
+                    new_position[old] = new;
+                    next_new = new + 1;
+                }
+                LineEdit::Insert { new } => next_new = new + 1,
+                LineEdit::Delete { old } => new_position[old] = next_new,
+            }
+        }
+
+        // The locked pages which still have text on them, with their labels and starting lines.
+        let mut chunks: Vec<(String, usize)> = Vec::new();
+        let mut empty_before_first: Option<String> = None;
+        let mut prev_start = 0;
+        for (k, page) in lock.pages.iter().enumerate() {
+            let start = match k {
+                0 => 0,
+                _ => new_position
+                    .get(page.start)
+                    .copied()
+                    .unwrap_or(self.lines.len()),
+            }
+            .max(prev_start);
+            let end = lock
+                .pages
+                .get(k + 1)
+                .and_then(|next| new_position.get(next.start).copied())
+                .unwrap_or(self.lines.len())
+                .max(start);
+            prev_start = start;
+            if start < end {
+                let label = match empty_before_first.take() {
+                    Some(first) => combine(&first, &page.label),
+                    None => page.label.clone(),
+                };
+                chunks.push((label, start));
+            } else if let Some(last) = chunks.last_mut() {
+                last.0 = combine(&last.0, &page.label);
+            } else if empty_before_first.is_none() {
+                empty_before_first = Some(page.label.clone());
+            }
+        }
+
+        let breaks = chunks.iter().map(|c| c.1).collect();
+        let mut pagination = self.paginate_with_breaks(&breaks);
+        let mut used: BTreeSet<String> = lock.pages.iter().map(|p| p.label.clone()).collect();
+        let mut label = String::new();
+        for page in &mut pagination.pages {
+            match chunks.iter().find(|c| c.1 == page.lines.start) {
+                Some((chunk_label, _)) => label = chunk_label.clone(),
+                None if label.is_empty() => label = (page.number).to_string(),
+                None => label = next_label(last_part(&label), &used),
+            }
+            used.insert(label.clone());
+            page.label = label.clone();
+        }
+        pagination
+    }
+}
+
+/// The label of a page or scene inserted after the given one, e.g. "12A" after "12", and "12B"
+/// after "12A". A label starting with "@" means the first label, so "@1" gives "A1".
+fn next_label(prev: &str, used: &BTreeSet<String>) -> String {
+    let bump = |c: char| char::from(c as u8 + 1);
+    let candidate = if let Some(first) = prev.strip_prefix('@') {
+        format!("A{}", first)
+    } else {
+        let mut chars = prev.chars();
+        match (chars.next(), prev.chars().last()) {
+            // Inserted before the first scene, e.g. "B1" after "A1".
+            (Some(c @ 'A'..='Y'), Some('0'..='9')) => format!("{}{}", bump(c), chars.as_str()),
+            (_, Some(c @ 'A'..='Y')) => format!("{}{}", &prev[..prev.len() - 1], bump(c)),
+            _ => format!("{}A", prev),
+        }
+    };
+    if !used.contains(&candidate) {
+        return candidate;
+    }
+    let mut candidate = format!("{}A", prev.trim_start_matches('@'));
+    while used.contains(&candidate) {
+        candidate.push('A');
+    }
+    candidate
+}
+
+/// Combines two page labels into a range, e.g. "44" and "45" into "44-45".
+fn combine(first: &str, last: &str) -> String {
+    let first = first.split('-').next().unwrap_or(first);
+    format!("{}-{}", first, last_part(last))
+}
+
+/// The last page in a combined page label, e.g. "45" in "44-45".
+fn last_part(label: &str) -> &str {
+    label.rsplit('-').next().unwrap_or(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    fn parse(text: &str) -> Document {
+        crate::parse_document::<VerboseError<&str>>(text).unwrap().1
+    }
+
+    fn numbers(doc: &Document, lock: &Lock) -> Vec<(String, Option<usize>)> {
+        doc.locked_scene_numbers(lock)
+            .into_iter()
+            .map(|s| (s.number, s.scene))
+            .collect()
+    }
+
+    fn n(number: &str, scene: Option<usize>) -> (String, Option<usize>) {
+        (number.to_owned(), scene)
+    }
+
+    /// A scene which takes up most of a page.
+    fn long_scene(heading: &str, action: &str) -> String {
+        let mut scene = format!("{}\n\n", heading);
+        for _ in 0..20 {
+            scene += &format!("{}\n\n", action);
+        }
+        scene
+    }
+
+    #[test]
+    fn test_next_label() {
+        let used: BTreeSet<String> = vec!["12".to_owned(), "12B".to_owned(), "13".to_owned()]
+            .into_iter()
+            .collect();
+        assert_eq!(next_label("12", &BTreeSet::new()), "12A");
+        assert_eq!(next_label("12A", &BTreeSet::new()), "12B");
+        assert_eq!(next_label("12A", &used), "12AA");
+        assert_eq!(next_label("@1", &used), "A1");
+        assert_eq!(next_label("A1", &used), "B1");
+    }
+
+    #[test]
+    fn test_lock_keeps_explicit_numbers() {
+        let doc = parse("INT. MESS #5#\n\nThe crew eats.\n\nINT. BRIDGE\n\nMother beeps.\n");
+        assert_eq!(
+            numbers(&doc, &doc.lock()),
+            vec![n("5", Some(1)), n("2", Some(2))]
+        );
+    }
+
+    #[test]
+    fn test_inserted_and_omitted_scenes() {
+        let locked = parse(
+            "INT. MESS\n\nThe crew eats.\n\nINT. BRIDGE\n\nMother beeps.\n\nEXT. SPACE\n\nThe ship drifts.\n",
+        );
+        let lock = locked.lock();
+        let revised = parse(
+            "INT. AIRLOCK\n\nKane waits.\n\nINT. MESS\n\nThe crew eats.\n\nEXT. SPACE\n\nThe ship drifts.\n\nINT. GALLEY\n\nSteam.\n\nINT. CORRIDOR\n\nDark.\n",
+        );
+        assert_eq!(
+            numbers(&revised, &lock),
+            vec![
+                n("A1", Some(1)),
+                n("1", Some(2)),
+                n("2", None),
+                n("3", Some(3)),
+                n("3A", Some(4)),
+                n("3B", Some(5)),
+            ]
+        );
+
+        // Numbers and OMITTED scenes carry over to the next round of revisions.
+        let relocked = revised.relock(&lock);
+        let revised_again = parse(
+            "INT. AIRLOCK\n\nKane waits.\n\nINT. MESS\n\nThe crew eats.\n\nEXT. SPACE\n\nThe ship drifts.\n\nINT. HYPERSLEEP\n\nPods.\n\nINT. GALLEY\n\nSteam.\n\nINT. CORRIDOR\n\nDark.\n",
+        );
+        assert_eq!(
+            numbers(&revised_again, &relocked),
+            vec![
+                n("A1", Some(1)),
+                n("1", Some(2)),
+                n("2", None),
+                n("3", Some(3)),
+                n("3AA", Some(4)),
+                n("3A", Some(5)),
+                n("3B", Some(6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_a_pages() {
+        let locked = parse(&format!(
+            "{}{}",
+            long_scene("INT. MESS", "The crew eats."),
+            long_scene("INT. BRIDGE", "Mother beeps.")
+        ));
+        let lock = locked.lock();
+        assert_eq!(lock.pages.len(), 2);
+
+        // Page 1 grows onto a new page, but page 2 still starts with the same line.
+        let revised = parse(&format!(
+            "{}{}{}",
+            long_scene("INT. MESS", "The crew eats."),
+            long_scene("INT. GALLEY", "Steam rises."),
+            long_scene("INT. BRIDGE", "Mother beeps.")
+        ));
+        let pagination = revised.paginate_locked(&lock);
+        let labels: Vec<_> = pagination.pages.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["1", "1A", "2"]);
+        let locked_page_2 = locked.lines.len() - lock.pages[1].start;
+        assert_eq!(
+            pagination.pages[2].lines,
+            revised.lines.len() - locked_page_2..revised.lines.len()
+        );
+
+        // Page 2 is deleted entirely.
+        let revised = parse(&long_scene("INT. MESS", "The crew eats."));
+        let labels: Vec<_> = revised
+            .paginate_locked(&lock)
+            .pages
+            .into_iter()
+            .map(|p| p.label)
+            .collect();
+        assert_eq!(labels, vec!["1-2"]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Options for how strictly to parse Fountain, since apps which write it don't all agree. Pass them
+//! to `parse_document_with_options`.
+//!
+//! ```
+//! use fountain::data::Line;
+//! use fountain::options::ParseOptions;
+//! use nom::error::VerboseError;
+//!
+//! let text = "int. mess - day\n";
+//! let (_, doc) = fountain::parse_document::<VerboseError<&str>>(text).unwrap();
+//! assert_eq!(doc.lines, vec![Line::Action("int. mess - day".to_owned())]);
+//!
+//! let options = ParseOptions::spec();
+//! let (_, doc) =
+//!     fountain::parse_document_with_options::<VerboseError<&str>>(text, &options).unwrap();
+//! assert_eq!(doc.lines, vec![Line::Scene("int. mess - day".to_owned())]);
+//! ```
+use super::prelude::*;
+use super::scenes::Vocabulary;
+use super::utils::strip_prefix_ignore_case;
+
+/// How to parse a document. The `Default` is how `parse_document` parses, and `spec` and `lenient`
+/// are profiles for scripts from other apps.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseOptions {
+    /// A line starting with one of these, then a space or ". ", is a Scene Heading.
+    pub scene_prefixes: Vec<String>,
+    /// Whether Scene Headings can start with a lowercase prefix, e.g. "int. house".
+    pub lowercase_scene_headings: bool,
+    /// Whether a line ending in "TO:" is only a Transition if there's a blank line (or the start
+    /// or end of the text) before and after it. If not, it's read as a Speaker, as other all-caps
+    /// lines are.
+    pub transitions_need_blank_lines: bool,
+    /// If set, every tab in Action is replaced with this many spaces. Otherwise tabs are kept.
+    pub tab_width: Option<usize>,
+    /// The words Scene Headings are written with, for splitting them into their parts (see
+    /// `SceneHeading::parse_with`). Set it with `with_vocabulary`, which also accepts its prefixes.
+    pub vocabulary: Vocabulary,
+}
+
+impl Default for ParseOptions {
+    /// Accepts the Scene Heading prefixes listed in the Fountain spec, which are the English
+    /// vocabulary's.
+    fn default() -> Self {
+        let vocabulary = Vocabulary::english();
+        ParseOptions {
+            scene_prefixes: vocabulary.settings.iter().map(|(p, _)| p.clone()).collect(),
+            lowercase_scene_headings: false,
+            transitions_need_blank_lines: false,
+            tab_width: None,
+            vocabulary,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The rules of the [Fountain spec](https://fountain.io/syntax): Scene Headings are
+    /// case-insensitive, Transitions need a blank line before and after them, and tabs in Action
+    /// are converted to four spaces.
+    pub fn spec() -> Self {
+        ParseOptions {
+            lowercase_scene_headings: true,
+            transitions_need_blank_lines: true,
+            tab_width: Some(4),
+            ..Default::default()
+        }
+    }
+
+    /// For scripts which bend the spec's rules. Like `spec`, but it also accepts reversed and
+    /// dotted prefixes, like "EXT./INT." and "I./E.", and reads any line ending in "TO:" as a
+    /// Transition.
+    pub fn lenient() -> Self {
+        let mut options = ParseOptions {
+            transitions_need_blank_lines: false,
+            ..ParseOptions::spec()
+        };
+        let extra = ["EXT./INT", "EXT/INT", "I./E", "E/I"];
+        options
+            .scene_prefixes
+            .extend(extra.iter().map(|p| p.to_string()));
+        options
+    }
+
+    /// The options for a profile, by name: "spec" or "lenient", ignoring case.
+    /// ```
+    /// use fountain::options::ParseOptions;
+    /// assert_eq!(ParseOptions::for_profile("Spec"), Some(ParseOptions::spec()));
+    /// assert_eq!(ParseOptions::for_profile("lenient"), Some(ParseOptions::lenient()));
+    /// assert_eq!(ParseOptions::for_profile("strict"), None);
+    /// ```
+    pub fn for_profile(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "spec" => Some(ParseOptions::spec()),
+            "lenient" => Some(ParseOptions::lenient()),
+            _ => None,
+        }
+    }
+
+    /// Reads Scene Headings written with the vocabulary's words, e.g. for scripts written in
+    /// another language. Its prefixes are accepted too, and it's kept as `vocabulary`.
+    /// ```
+    /// use fountain::options::ParseOptions;
+    /// use fountain::scenes::Vocabulary;
+    /// let options = ParseOptions::spec().with_vocabulary(&Vocabulary::german());
+    /// assert!(options.scene_prefixes.contains(&"AUSSEN".to_owned()));
+    /// assert!(options.vocabulary.times_of_day.contains(&"NACHT".to_owned()));
+    /// ```
+    pub fn with_vocabulary(mut self, words: &Vocabulary) -> Self {
+        for (prefix, _) in &words.settings {
+            if !self.scene_prefixes.contains(prefix) {
+                self.scene_prefixes.push(prefix.clone());
+            }
+        }
+        self.vocabulary = words.clone();
+        self
+    }
+
+    /// Whether the line is a Scene Heading. Only its start is checked, so it can be followed by
+    /// the rest of the text.
+    /// ```
+    /// use fountain::options::ParseOptions;
+    /// assert!(ParseOptions::default().is_scene_heading("INT. MESS - NIGHT"));
+    /// assert!(!ParseOptions::default().is_scene_heading("INTERCUT WITH:"));
+    /// ```
+    pub fn is_scene_heading(&self, line: &str) -> bool {
+        self.scene_prefix_len(line).is_some()
+    }
+
+    /// If the line is a Scene Heading, the length of its prefix and the ". " or " " after it, e.g.
+    /// 5 for "INT. MESS". Editors can use it to tell when the location is being typed.
+    pub fn scene_prefix_len(&self, line: &str) -> Option<usize> {
+        // The longest prefix wins, so that "INT./EXT. CAR" isn't read as "INT" then "./EXT. CAR".
+        let (_, rest) = self
+            .scene_prefixes
+            .iter()
+            .filter_map(|prefix| Some((prefix.len(), self.strip_prefix(line, prefix)?)))
+            .max_by_key(|&(len, _)| len)?;
+        let separator = [". ", " "].iter().find(|sep| rest.starts_with(*sep))?;
+        Some(line.len() - rest.len() + separator.len())
+    }
+
+    fn strip_prefix<'a>(&self, line: &'a str, prefix: &str) -> Option<&'a str> {
+        if self.lowercase_scene_headings {
+            strip_prefix_ignore_case(line, prefix)
+        } else {
+            line.strip_prefix(prefix)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scene_prefixes() {
+        let options = ParseOptions::default();
+        assert!(options.is_scene_heading("INT./EXT. CAR - MOVING"));
+        assert!(options.is_scene_heading("I/E CAR"));
+        assert!(!options.is_scene_heading("INT./EXTRA"));
+        assert!(!options.is_scene_heading("INTERCUT WITH:"));
+        assert!(!options.is_scene_heading("int. house"));
+        assert!(!options.is_scene_heading("EXT./INT. CAR"));
+        assert!(ParseOptions::spec().is_scene_heading("int. house"));
+        assert!(ParseOptions::lenient().is_scene_heading("ext./int. car"));
+        assert!(!ParseOptions::lenient().is_scene_heading("internal memo"));
+        assert_eq!(options.scene_prefix_len("INT./EXT. CAR"), Some(10));
+        assert_eq!(options.scene_prefix_len("I/E CAR"), Some(4));
+        assert_eq!(options.scene_prefix_len("INT."), None);
+    }
+
+    #[test]
+    fn test_localized_prefixes() {
+        let german = ParseOptions::default().with_vocabulary(&Vocabulary::german());
+        assert!(german.is_scene_heading("INNEN. KÜCHE - TAG"));
+        assert!(german.is_scene_heading("AUßEN HAFEN"));
+        assert!(german.is_scene_heading("INT. KÜCHE - TAG"));
+        assert!(!german.is_scene_heading("innen. küche"));
+        assert!(!german.is_scene_heading("INNENHOF"));
+        let french = ParseOptions::spec().with_vocabulary(&Vocabulary::french());
+        assert!(french.is_scene_heading("intérieur cuisine - jour"));
+        assert!(french.is_scene_heading("EXT. PLAGE - NUIT"));
+        let count = |o: &ParseOptions, p: &str| o.scene_prefixes.iter().filter(|s| *s == p).count();
+        assert_eq!(count(&french, "INT"), 1);
+    }
+
+    #[test]
+    fn test_every_parser_takes_options() {
+        use crate::data::Line;
+        use nom::error::ErrorKind;
+
+        let text = "int. mess - day\n\nThe\tcrew eats.\n";
+        let options = ParseOptions::spec();
+        let heading = Line::Scene("int. mess - day".to_owned());
+        let (_, doc) =
+            crate::parse_document_with_options::<(&str, ErrorKind)>(text, &options).unwrap();
+        assert_eq!(doc.lines[0], heading);
+        assert_eq!(doc.lines[1], Line::Action("The    crew eats.".to_owned()));
+
+        let (_, (with_spans, _)) =
+            crate::parse_document_with_spans_and_options::<(&str, ErrorKind)>(text, &options)
+                .unwrap();
+        assert_eq!(with_spans, doc);
+        let (_, borrowed) =
+            crate::parse_document_borrowed_with_options::<(&str, ErrorKind)>(text, &options)
+                .unwrap();
+        assert_eq!(
+            borrowed.lines[0],
+            crate::borrowed::Line::Scene("int. mess - day")
+        );
+        let parsed = crate::incremental::Parsed::with_options(text, options.clone());
+        assert_eq!(parsed.document(), &doc);
+        let tokens = crate::tokens::tokenize_with_options(text, &options);
+        assert_eq!(tokens[0].kind, crate::tokens::TokenKind::SceneHeading);
+        #[cfg(feature = "std")]
+        {
+            let events = crate::events::Events::with_options(text.as_bytes(), options.clone());
+            let lines: Vec<_> = events
+                .filter_map(|event| match event.unwrap() {
+                    crate::events::Event::Line(line) => Some(line),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(lines, doc.lines);
+        }
+
+        // The spec needs a blank line before a Transition, and there's none after the title page.
+        let text = "Title: Alien\nCUT TO:\n";
+        let action = vec![Line::Action("CUT TO:".to_owned())];
+        for lines in every_parse(text, &options) {
+            assert_eq!(lines, action);
+        }
+        let transition = vec![Line::Transition("CUT TO:".to_owned())];
+        for lines in every_parse("Title: Alien\n\nCUT TO:\n", &options) {
+            assert_eq!(lines, transition);
+        }
+    }
+
+    /// The lines from every parser which takes options.
+    fn every_parse(text: &str, options: &ParseOptions) -> Vec<Vec<crate::data::Line>> {
+        use nom::error::ErrorKind;
+
+        let (_, doc) =
+            crate::parse_document_with_options::<(&str, ErrorKind)>(text, options).unwrap();
+        let (_, (with_spans, _)) =
+            crate::parse_document_with_spans_and_options::<(&str, ErrorKind)>(text, options)
+                .unwrap();
+        let (_, borrowed) =
+            crate::parse_document_borrowed_with_options::<(&str, ErrorKind)>(text, options)
+                .unwrap();
+        let parsed = crate::incremental::Parsed::with_options(text, options.clone());
+        #[allow(unused_mut)]
+        let mut parses = vec![
+            doc.lines,
+            with_spans.lines,
+            borrowed.into_owned().lines,
+            parsed.document().lines.clone(),
+        ];
+        #[cfg(feature = "std")]
+        parses.push(
+            crate::events::Events::with_options(text.as_bytes(), options.clone())
+                .filter_map(|event| match event.unwrap() {
+                    crate::events::Event::Line(line) => Some(line),
+                    _ => None,
+                })
+                .collect(),
+        );
+        parses
+    }
+}
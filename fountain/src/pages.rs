@@ -0,0 +1,310 @@
+//! Splits a Fountain document into printed pages. This follows the usual screenplay layout: 12pt
+//! Courier on US Letter, which fits 55 lines of text on a page, with action wrapped at 60
+//! characters and dialogue at 35.
+//!
+//! Pagination is an estimate, because every screenwriting app lays pages out slightly differently.
+//! Scene Headings are never left alone at the bottom of a page, and a Speaker's dialogue is kept
+//! on the same page as the Speaker unless the speech is longer than a whole page.
+use super::data::*;
+use super::prelude::*;
+use alloc::collections::BTreeSet;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, Range};
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// How many lines of text fit on one page.
+pub const LINES_PER_PAGE: usize = 55;
+const ACTION_WIDTH: usize = 60;
+const DIALOGUE_WIDTH: usize = 35;
+const PARENTHETICAL_WIDTH: usize = 28;
+
+/// A length measured in eighths of a page, which is how production schedules measure scenes.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+/// ```
+/// use fountain::pages::Eighths;
+/// assert_eq!(Eighths(3).to_string(), "3/8");
+/// assert_eq!(Eighths(8).to_string(), "1");
+/// assert_eq!(Eighths(13).to_string(), "1 5/8");
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Eighths(pub u32);
+
+impl Eighths {
+    /// Converts a number of lines of text into eighths of a page, rounding to the nearest eighth.
+    /// Anything which takes up any space at all is at least 1/8 long.
+    pub fn from_lines(lines: usize) -> Eighths {
+        if lines == 0 {
+            return Eighths(0);
+        }
+        let eighths = (lines * 8 + LINES_PER_PAGE / 2) / LINES_PER_PAGE;
+        Eighths(eighths.max(1) as u32)
+    }
+
+    /// The length in pages, e.g. 1.5 for 1 4/8.
+    pub fn pages(self) -> f64 {
+        f64::from(self.0) / 8.0
+    }
+}
+
+impl fmt::Display for Eighths {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.0 / 8, self.0 % 8) {
+            (pages, 0) => write!(f, "{}", pages),
+            (0, eighths) => write!(f, "{}/8", eighths),
+            (pages, eighths) => write!(f, "{} {}/8", pages, eighths),
+        }
+    }
+}
+
+impl Add for Eighths {
+    type Output = Eighths;
+    fn add(self, other: Eighths) -> Eighths {
+        Eighths(self.0 + other.0)
+    }
+}
+
+impl Sum for Eighths {
+    fn sum<I: Iterator<Item = Eighths>>(iter: I) -> Eighths {
+        iter.fold(Eighths(0), Add::add)
+    }
+}
+
+/// One printed page.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Page {
+    /// Pages are numbered from 1. The title page isn't numbered.
+    pub number: usize,
+    /// The page number as printed. This is the same as `number`, except when pages are locked
+    /// (see `Document::paginate_locked`), where inserted pages get labels like "45A".
+    pub label: String,
+    /// Indices into `Document::lines` of the lines printed on this page.
+    pub lines: Range<usize>,
+}
+
+/// The layout of a document's lines onto pages.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Pagination {
+    pub pages: Vec<Page>,
+    /// How many lines of text each Line takes up, including the blank line before it.
+    heights: Vec<usize>,
+}
+
+impl Pagination {
+    /// The page which the line at the given index is printed on.
+    pub fn page_of(&self, index: usize) -> Option<&Page> {
+        let i = self.pages.partition_point(|p| p.lines.end <= index);
+        self.pages.get(i).filter(|p| p.lines.contains(&index))
+    }
+
+    /// How many lines of text the given range of Lines takes up.
+    pub fn height(&self, lines: Range<usize>) -> usize {
+        self.heights[lines].iter().sum()
+    }
+
+    /// How many eighths of a page the given range of Lines takes up.
+    pub fn eighths(&self, lines: Range<usize>) -> Eighths {
+        Eighths::from_lines(self.height(lines))
+    }
+}
+
+impl Document {
+    /// Lays the document out onto pages.
+    pub fn paginate(&self) -> Pagination {
+        self.paginate_with_breaks(&BTreeSet::new())
+    }
+
+    /// Lays the document out onto pages, always starting a new page at the lines with the given
+    /// indices.
+    pub(crate) fn paginate_with_breaks(&self, breaks: &BTreeSet<usize>) -> Pagination {
+        let heights: Vec<usize> = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let prev = i.checked_sub(1).map(|p| &self.lines[p]);
+                spacing_before(prev, line) + text_height(line)
+            })
+            .collect();
+        let mut pages: Vec<Page> = Vec::new();
+        let mut start = 0;
+        let mut used = 0;
+        for unit in units(&self.lines, breaks) {
+            let height: usize = heights[unit.clone()].iter().sum();
+            // The blank line before a unit isn't printed at the top of a page.
+            let prev = unit.start.checked_sub(1).map(|p| &self.lines[p]);
+            let leading = spacing_before(prev, &self.lines[unit.start]);
+            let forced = breaks.contains(&unit.start) && start < unit.start;
+            if used == 0 && !forced {
+                used = height - leading;
+            } else if forced || used + height > LINES_PER_PAGE {
+                pages.push(Page {
+                    number: pages.len() + 1,
+                    label: (pages.len() + 1).to_string(),
+                    lines: start..unit.start,
+                });
+                start = unit.start;
+                used = height - leading;
+            } else {
+                used += height;
+            }
+        }
+        if start < self.lines.len() {
+            pages.push(Page {
+                number: pages.len() + 1,
+                label: (pages.len() + 1).to_string(),
+                lines: start..self.lines.len(),
+            });
+        }
+        Pagination { pages, heights }
+    }
+}
+
+/// Splits lines into groups which should be kept on the same page. Each group is either a Scene
+/// Heading with the element after it, a Speaker with their parentheticals and dialogue, or a
+/// single line. Groups longer than a page are split into single lines, and groups are split
+/// before any of the given page breaks.
+fn units(lines: &[Line], breaks: &BTreeSet<usize>) -> Vec<Range<usize>> {
+    let mut units: Vec<Range<usize>> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let continues_unit = match units.last() {
+            None => false,
+            Some(_) if breaks.contains(&i) => false,
+            Some(unit) => {
+                let prev = &lines[i - 1];
+                let in_dialogue = (line.is_dialogue() || line.is_parenthetical())
+                    && (prev.is_speaker() || prev.is_parenthetical() || prev.is_dialogue());
+                let after_heading = lines[unit.start].is_scene() && unit.len() == 1;
+                in_dialogue || after_heading
+            }
+        };
+        match units.last_mut() {
+            Some(unit) if continues_unit => unit.end = i + 1,
+            _ => units.push(i..i + 1),
+        }
+    }
+    units
+        .into_iter()
+        .flat_map(|unit| {
+            let height: usize = lines[unit.clone()].iter().map(text_height).sum();
+            if height > LINES_PER_PAGE {
+                unit.map(|i| i..i + 1).collect()
+            } else {
+                vec![unit]
+            }
+        })
+        .collect()
+}
+
+/// How many blank lines are printed before this line. Sections aren't printed at all.
+fn spacing_before(prev: Option<&Line>, line: &Line) -> usize {
+    match (prev, line) {
+        (None, _) | (_, Line::Section { .. }) => 0,
+        (Some(Line::Speaker { .. }), Line::Parenthetical(_) | Line::Dialogue(_)) => 0,
+        (Some(Line::Parenthetical(_)), Line::Parenthetical(_) | Line::Dialogue(_)) => 0,
+        (Some(Line::Dialogue(_)), Line::Parenthetical(_) | Line::Dialogue(_)) => 0,
+        _ => 1,
+    }
+}
+
+/// How many lines of text this line takes up once it's been wrapped.
+fn text_height(line: &Line) -> usize {
+    match line {
+        Line::Scene(s) | Line::Action(s) | Line::Transition(s) => wrapped_height(s, ACTION_WIDTH),
+        Line::Dialogue(s) | Line::Lyric(s) => wrapped_height(s, DIALOGUE_WIDTH),
+        Line::Parenthetical(s) => wrapped_height(s, PARENTHETICAL_WIDTH),
+        Line::Speaker { .. } => 1,
+        Line::Section { .. } => 0,
+    }
+}
+
+/// How many lines the text takes up when word-wrapped to the given width.
+fn wrapped_height(text: &str, width: usize) -> usize {
+    let mut lines = 1;
+    let mut current = 0;
+    for word in text.split_whitespace() {
+        let len = word.chars().count();
+        if current == 0 {
+            current = len;
+        } else if current + 1 + len <= width {
+            current += 1 + len;
+        } else {
+            lines += 1;
+            current = len;
+        }
+        // Words longer than a whole line get broken up.
+        while current > width {
+            lines += 1;
+            current -= width;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eighths() {
+        assert_eq!(Eighths::from_lines(0), Eighths(0));
+        assert_eq!(Eighths::from_lines(1), Eighths(1));
+        assert_eq!(Eighths::from_lines(LINES_PER_PAGE), Eighths(8));
+        assert_eq!(Eighths::from_lines(LINES_PER_PAGE * 3 / 2), Eighths(12));
+        assert_eq!(Eighths(12).to_string(), "1 4/8");
+        assert_eq!(
+            vec![Eighths(3), Eighths(6)].into_iter().sum::<Eighths>(),
+            Eighths(9)
+        );
+    }
+
+    #[test]
+    fn test_wrapped_height() {
+        assert_eq!(wrapped_height("", 10), 1);
+        assert_eq!(wrapped_height("one two", 10), 1);
+        assert_eq!(wrapped_height("one two three", 10), 2);
+        assert_eq!(wrapped_height("abcdefghijklmnopqrstuvwxy", 10), 3);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let mut lines = vec![Line::Scene("INT. MESS".to_owned())];
+        for _ in 0..30 {
+            lines.push(Line::Action("The crew eats.".to_owned()));
+        }
+        lines.push(Line::Scene("INT. BRIDGE".to_owned()));
+        lines.push(Line::Action("Mother beeps.".to_owned()));
+        let doc = Document {
+            lines,
+            ..Default::default()
+        };
+        let pagination = doc.paginate();
+        // 1 heading and 27 actions take 55 lines, so the 28th action starts page 2.
+        assert_eq!(pagination.pages.len(), 2);
+        assert_eq!(pagination.pages[0].lines, 0..28);
+        assert_eq!(pagination.pages[1].lines, 28..33);
+        assert_eq!(pagination.page_of(0).unwrap().number, 1);
+        assert_eq!(pagination.page_of(31).unwrap().number, 2);
+        assert_eq!(pagination.page_of(33), None);
+    }
+
+    #[test]
+    fn test_paginate_keeps_heading_with_next_line() {
+        let mut lines = Vec::new();
+        for _ in 0..27 {
+            lines.push(Line::Action("The crew eats.".to_owned()));
+        }
+        lines.push(Line::Scene("INT. BRIDGE".to_owned()));
+        lines.push(Line::Action("Mother beeps.".to_owned()));
+        let doc = Document {
+            lines,
+            ..Default::default()
+        };
+        let pagination = doc.paginate();
+        // The heading would fit at the bottom of page 1, but the action after it wouldn't.
+        assert_eq!(pagination.pages[0].lines, 0..27);
+        assert_eq!(pagination.pages[1].lines, 27..29);
+    }
+}
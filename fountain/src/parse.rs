@@ -1,21 +1,25 @@
-use super::data::*;
+use super::borrowed::{Document, Line, TitlePage};
+use super::data;
+use super::options::ParseOptions;
+use super::prelude::*;
+use super::source::Span;
 use nom::{
     branch::alt,
     bytes::complete::{is_not, tag, take_while1},
-    character::complete::{char, line_ending, multispace1, not_line_ending},
-    combinator::{cut, map, opt, verify},
-    error::{context, ContextError, ParseError},
-    multi::{many0, separated_list0},
+    character::complete::{char, multispace1, space0},
+    combinator::{consumed, cut, eof, map, not, opt, peek, verify},
+    error::{context, ContextError, ErrorKind, ParseError},
+    multi::many0,
     sequence::{delimited, pair, preceded, terminated, tuple},
-    IResult,
+    IResult, Offset,
 };
 
-/// Matches strings that contain no lower-case English letters.
+/// Matches strings that contain no lower-case letters, in any script, e.g. "JOSÉ" but not "José".
 fn no_lower<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, &'a str, E> {
-    let chars = "abcdefghijklmnopqrstuvwxyz\n\r";
-    context("no_lower", take_while1(move |c| !chars.contains(c)))(i)
+    let parser = take_while1(|c: char| !(c.is_lowercase() || c == '\n' || c == '\r'));
+    context("no_lower", parser)(i)
 }
 
 /// Parses an Action. Action, or scene description, is any paragraph that doesn't meet criteria for another
@@ -23,38 +27,133 @@ fn no_lower<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 /// https://fountain.io/syntax#section-action
 fn action<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    map(context("action", some_line), |s: &str| {
-        Line::Action(s.to_string())
-    })(i)
+) -> IResult<&'a str, Line<'a>, E> {
+    map(context("action", some_line), Line::Action)(i)
+}
+
+/// Parses a Forced Action, which starts with ! so that it isn't mistaken for another element,
+/// e.g. an all-caps line of action which would otherwise be a Speaker.
+/// https://fountain.io/syntax#section-action
+fn action_forced<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, Line<'a>, E> {
+    let parser = preceded(char('!'), rest_of_line);
+    map(context("action_forced", parser), Line::Action)(i)
+}
+
+/// Matches a line ending: "\n", "\r\n", or a "\r" on its own, as some older apps write.
+pub(crate) fn line_ending<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    let len = match i.as_bytes() {
+        [b'\r', b'\n', ..] => 2,
+        [b'\n' | b'\r', ..] => 1,
+        _ => return Err(nom::Err::Error(E::from_error_kind(i, ErrorKind::CrLf))),
+    };
+    Ok((&i[len..], &i[..len]))
+}
+
+/// Matches everything up to the next line ending, or the end of the text.
+fn not_line_ending<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    // Line endings are ASCII, so there's no need to decode the text.
+    let end = i.bytes().position(|b| b == b'\n' || b == b'\r');
+    let (line, rest) = i.split_at(end.unwrap_or(i.len()));
+    Ok((rest, line))
+}
+
+/// Matches the end of a line: a line ending, or the end of the text, so that the text doesn't
+/// need a final newline.
+fn line_end<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    if i.is_empty() {
+        Ok((i, i))
+    } else {
+        line_ending(i)
+    }
+}
+
+/// Matches a blank line, i.e. one with nothing but spaces or tabs on it.
+pub(crate) fn blank_line<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    let spaces = i.bytes().take_while(|&b| is_space(b)).count();
+    let (rest, _) = line_ending(&i[spaces..])?;
+    Ok((rest, &i[..i.len() - rest.len()]))
 }
 
-/// Matches any sequence of non-line-ending characters, terminated by a line ending.
+/// True for the characters which can be on a blank line.
+fn is_space(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+/// Matches the rest of the line, terminated by a line ending or the end of the text.
+fn rest_of_line<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    terminated(not_line_ending, line_end)(i)
+}
+
+/// Matches a line which isn't blank, terminated by a line ending or the end of the text.
 fn some_line<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, &'a str, E> {
-    terminated(not_line_ending, line_ending)(i)
+    let (rest, line) = rest_of_line(i)?;
+    // This usually stops at the line's first character, so it's cheaper than trimming it.
+    if line.bytes().all(is_space) {
+        return Err(nom::Err::Error(E::from_error_kind(i, ErrorKind::Verify)));
+    }
+    Ok((rest, line))
 }
 
-/// Parses a Dialogue. Dialogue is any text following a Character or Parenthetical element.
+/// Parses a line of Dialogue. Dialogue is any text following a Character or Parenthetical element,
+/// up to the next blank line. A line of exactly two spaces doesn't end it, but is an empty line
+/// within it.
 /// https://fountain.io/syntax#section-dialogue
 fn dialogue<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    map(terminated(not_line_ending, line_ending), |s: &str| {
-        Line::Dialogue(s.to_string())
-    })(i)
+) -> IResult<&'a str, Line<'a>, E> {
+    if let Some(after) = i.strip_prefix("  ") {
+        if let Ok((rest, _)) = line_end::<E>(after) {
+            return Ok((rest, Line::Dialogue(&i[..0])));
+        }
+    }
+    map(some_line, Line::Dialogue)(i)
+}
+
+/// Parses the rest of a Speaker's lines after their first line of Dialogue into `lines`: more
+/// Dialogue, and Parentheticals, up to the next blank line. Returns the text after them.
+fn more_dialogue<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    mut i: &'a str,
+    lines: &mut Vec<Line<'a>>,
+) -> &'a str {
+    loop {
+        let parsed = if i.starts_with('(') {
+            one_line_parenthetical::<E>(i).or_else(|_| dialogue::<E>(i))
+        } else {
+            dialogue::<E>(i)
+        };
+        match parsed {
+            Ok((rest, line)) => {
+                lines.push(line);
+                i = rest;
+            }
+            Err(_) => return i,
+        }
+    }
+}
+
+/// Parses a Parenthetical within Dialogue. Unlike the Parenthetical after the Speaker, these have
+/// to fit on one line.
+fn one_line_parenthetical<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, Line<'a>, E> {
+    let in_parens = delimited(char('('), is_not(")\r\n"), char(')'));
+    let parser = terminated(in_parens, pair(space0, line_end));
+    map(parser, Line::Parenthetical)(i)
 }
 
 /// Parses a Parenthetical. Parentheticals are wrapped in parentheses () and end in newline.
 /// https://fountain.io/syntax#section-paren
 fn parenthetical<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let parser = terminated(in_parens, cut(line_ending));
-    map(context("parenthetical", parser), |s: &str| {
-        Line::Parenthetical(s.to_string())
-    })(i)
+) -> IResult<&'a str, Line<'a>, E> {
+    let parser = terminated(in_parens, cut(pair(space0, line_end)));
+    map(context("parenthetical", parser), Line::Parenthetical)(i)
 }
 
 /// Matches "(x)" and returns "x"
@@ -65,52 +164,83 @@ fn in_parens<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 }
 
 /// Parses a Speaker. A speaker is simply a Fountain "Character" element,
-/// i.e. any line entirely in uppercase and ends in newline. I renamed it "Speaker" interally
-/// to avoid confusion with a CS character i.e. a byte.
+/// i.e. any line entirely in uppercase, with at least one uppercase letter, and ends in newline.
+/// I renamed it "Speaker" interally
+/// to avoid confusion with a CS character i.e. a byte. Whitespace around the name is ignored. A
+/// line starting with "@" is a Speaker whatever its case, and the "@" isn't part of the name.
 /// https://fountain.io/syntax#section-character
 fn speaker<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let parser = terminated(no_lower, line_ending);
-    map(context("speaker", parser), |s| Line::Speaker {
-        name: strip_suffix(" ^", s),
-        is_dual: s.ends_with('^'),
+) -> IResult<&'a str, Line<'a>, E> {
+    // Scripts without case, like Chinese or Japanese, have no uppercase letters to tell a name by.
+    let name = verify(no_lower, |s: &str| s.chars().any(char::is_uppercase));
+    // "@" forces a Speaker, e.g. for names with lowercase letters like McCLANE.
+    let forced = preceded(
+        char('@'),
+        verify(not_line_ending, |s: &str| !s.trim().is_empty()),
+    );
+    let parser = terminated(alt((forced, name)), line_end);
+    map(context("speaker", parser), |s| {
+        let s = s.trim();
+        Line::Speaker {
+            name: strip_suffix(" ^", s).trim_end(),
+            is_dual: s.ends_with('^'),
+        }
     })(i)
 }
 
-/// Parses a Transition, which ends with "TO:"
+/// Parses a Transition, which ends with "TO:". If the options say so, it also needs a blank line
+/// before and after it.
 /// https://fountain.io/syntax#section-trans
-fn transition_to<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let p = verify(terminated(no_lower, line_ending), |s: &str| {
-        s.ends_with("TO:")
-    });
-    let parser = map(p, |s| Line::Transition(s.to_owned()));
-    context("transition_to", parser)(i)
+fn transition_to<'a, 'o, E: ParseError<&'a str> + ContextError<&'a str>>(
+    options: &'o ParseOptions,
+    after_blank_line: bool,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Line<'a>, E> + 'o {
+    move |i| {
+        let need_blank_lines = options.transitions_need_blank_lines;
+        if need_blank_lines && !after_blank_line {
+            return Err(nom::Err::Error(E::from_error_kind(i, ErrorKind::Verify)));
+        }
+        let p = verify(terminated(no_lower, line_end), |s: &str| {
+            s.trim_end().ends_with("TO:")
+        });
+        let (rest, line) = context("transition_to", map(p, |s| Line::Transition(s.trim())))(i)?;
+        if need_blank_lines {
+            context("transition_to", peek(alt((blank_line, eof))))(rest)?;
+        }
+        Ok((rest, line))
+    }
 }
 
 /// Parses a Forced Transition, which either starts with >
 /// https://fountain.io/syntax#section-trans
 fn transition_forced<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let p = preceded(tag("> "), some_line);
-    let parser = map(p, |s| Line::Transition(s.to_owned()));
+) -> IResult<&'a str, Line<'a>, E> {
+    let p = preceded(tag("> "), rest_of_line);
+    let parser = map(p, Line::Transition);
     context("transition_forced", parser)(i)
 }
 
-/// Parses a Scene Heading. A Scene Heading is any line that has a blank line following it, and either begins with INT or EXT.
-/// A Scene Heading always has at least one blank line preceding it.
+/// Parses a Scene Heading. A Scene Heading is any line that has a blank line following it, and
+/// begins with one of the options' prefixes (by default INT, EXT, EST, INT./EXT, INT/EXT or I/E),
+/// followed by a dot or a space. A Scene Heading always has at least one blank line preceding it.
 /// https://fountain.io/syntax#section-slug
-fn scene<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
-    i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let parse_scene_type = alt((tag("INT"), tag("EXT")));
-    let parser = tuple((parse_scene_type, tag(". "), not_line_ending, line_ending));
-    map(context("scene", parser), |(scene_type, _, desc, _)| {
-        Line::Scene(format!("{}. {}", scene_type, desc))
-    })(i)
+fn scene<'a, 'o, E: ParseError<&'a str> + ContextError<&'a str>>(
+    options: &'o ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Line<'a>, E> + 'o {
+    move |i| {
+        // Checking the prefix first saves reading every other kind of line to its end.
+        let heading = |i: &'a str| {
+            if options.is_scene_heading(i) {
+                not_line_ending(i)
+            } else {
+                Err(nom::Err::Error(E::from_error_kind(i, ErrorKind::Tag)))
+            }
+        };
+        let parser = terminated(heading, line_end);
+        map(context("scene", parser), Line::Scene)(i)
+    }
 }
 
 /// Parses a Lyric. You create a Lyric by starting with a line with a tilde ~. Fountain will remove
@@ -119,25 +249,42 @@ fn scene<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 /// https://fountain.io/syntax#section-lyric
 fn lyric<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Line, E> {
-    let parser = preceded(char('~'), some_line);
-    map(context("lyric", parser), |s| Line::Lyric(s.to_owned()))(i)
+) -> IResult<&'a str, Line<'a>, E> {
+    let parser = preceded(char('~'), rest_of_line);
+    map(context("lyric", parser), Line::Lyric)(i)
 }
+
+/// Parses a Section, which starts with one or more #, e.g. "# ACT ONE".
+/// https://fountain.io/syntax#section-sections
+fn section<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, Line<'a>, E> {
+    let parser = pair(take_while1(|c| c == '#'), preceded(space0, rest_of_line));
+    map(
+        context("section", parser),
+        |(depth, title): (&str, &str)| Line::Section {
+            title,
+            depth: depth.len(),
+        },
+    )(i)
+}
+
 fn titlepage_val<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, &'a str, E> {
-    let chars = "\n\r:";
-    let parser = take_while1(move |c| !chars.contains(c));
+    let parser = take_while1(|c| !matches!(c, '\n' | '\r' | ':'));
     context("titlepage_val", parser)(i)
 }
 
 /// Match a single key-value titlepage item, e.g.
 /// Title:
 ///     THE RING
-fn titlepage_item<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+pub(crate) fn titlepage_item<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, (&str, &str), E> {
-    let parser = tuple((titlepage_val, char(':'), multispace1, some_line));
+) -> IResult<&'a str, (&'a str, &'a str), E> {
+    // An include directive like "{{include: act1.fountain}}" isn't a key-value pair.
+    let key = preceded(not(tag("{{")), titlepage_val);
+    let parser = tuple((key, char(':'), multispace1, some_line));
     map(context("titlepage_item", parser), |(key, _, _, val)| {
         (key, val)
     })(i)
@@ -146,22 +293,24 @@ fn titlepage_item<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 /// Matches the document's TitlePage
 fn titlepage<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, TitlePage, E> {
+) -> IResult<&'a str, TitlePage<'a>, E> {
     map(context("Title page", many0(titlepage_item)), |items| {
         let mut m = TitlePage::default();
         for (k, v) in items {
             match k {
-                "Title" => m.title = Some(v.to_string()),
-                "Author" => m.author = Some(v.to_string()),
-                _ => m.other.push((k.to_string(), v.to_string())),
+                "Title" => m.title = Some(v),
+                "Author" => m.author = Some(v),
+                _ => m.other.push((k, v)),
             }
         }
         m
     })(i)
 }
 
-/// Parses a string slice into a Fountain document. Your input string should end in a
-/// newline for parsing to succeed.
+/// Parses a string slice into a Fountain document. Lines can end in "\n", "\r\n" or "\r", the
+/// last line doesn't need to end in one, and a UTF-8 byte order mark at the start is skipped. Any
+/// line with only spaces or tabs on it is a blank line, except that a line of exactly two spaces
+/// in Dialogue is an empty line of the Dialogue.
 /// ```
 /// use fountain::data::{Document, Line};
 /// use nom::error::VerboseError;
@@ -183,40 +332,304 @@ fn titlepage<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 /// ```
 pub fn document<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     text: &'a str,
-) -> IResult<&'a str, Document, E> {
-    let parser = pair(
-        opt(terminated(titlepage, opt(line_ending))), // Documents may begin with a title page
-        separated_list0(line_ending, block), // Documents must then contain screenplay lines
-    );
+) -> IResult<&'a str, data::Document, E> {
+    document_with_options(text, &ParseOptions::default())
+}
 
-    map(parser, |(titlepage, blocks)| {
-        let lines: Vec<_> = blocks.into_iter().flatten().collect();
-        Document {
-            lines,
-            titlepage: titlepage.unwrap_or_default(),
+/// Like `document`, but parses the way the options say to, e.g. for a different app's dialect of
+/// Fountain. See the `options` module.
+pub fn document_with_options<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+    options: &ParseOptions,
+) -> IResult<&'a str, data::Document, E> {
+    let (rest, (doc, _)) = document_with_spans_and_options(text, options)?;
+    Ok((rest, doc))
+}
+
+/// Replaces tabs in Action, if the options say to.
+pub(crate) fn expand_tabs(lines: &mut [data::Line], options: &ParseOptions) {
+    if let Some(width) = options.tab_width {
+        let spaces = " ".repeat(width);
+        for line in lines {
+            if let data::Line::Action(s) = line {
+                if s.contains('\t') {
+                    *s = s.replace('\t', &spaces);
+                }
+            }
         }
-    })(text)
+    }
+}
+
+/// Like `document`, but also returns where each of the document's lines came from in the text.
+/// The nth span is the location of `Document::lines[n]`.
+/// ```
+/// use fountain::source::Span;
+/// use nom::error::VerboseError;
+///
+/// let text = "INT. MESS\n\nKANE\nFood!\n";
+/// let (_, (doc, spans)) = fountain::parse_document_with_spans::<VerboseError<&str>>(text).unwrap();
+/// assert_eq!(doc.lines.len(), 3);
+/// assert_eq!(spans[2], Span { start: 16, end: 21 });
+/// assert_eq!(&text[spans[2].start..spans[2].end], "Food!");
+/// ```
+pub fn document_with_spans<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+) -> IResult<&'a str, (data::Document, Vec<Span>), E> {
+    document_with_spans_and_options(text, &ParseOptions::default())
+}
+
+/// Like `document_with_spans`, but parses the way the options say to.
+pub fn document_with_spans_and_options<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+    options: &ParseOptions,
+) -> IResult<&'a str, (data::Document, Vec<Span>), E> {
+    let (rest, (doc, spans)) = borrowed_with_spans(text, options)?;
+    let mut doc = doc.into_owned();
+    expand_tabs(&mut doc.lines, options);
+    Ok((rest, (doc, spans)))
+}
+
+/// Like `document`, but the document borrows its text from the input instead of copying it.
+/// ```
+/// use fountain::borrowed::Line;
+/// use nom::error::VerboseError;
+///
+/// let text = "INT. MESS\n\nKANE\nFood!\n";
+/// let (_, doc) = fountain::parse_document_borrowed::<VerboseError<&str>>(text).unwrap();
+/// assert_eq!(doc.lines[0], Line::Scene("INT. MESS"));
+/// ```
+pub fn document_borrowed<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+) -> IResult<&'a str, Document<'a>, E> {
+    document_borrowed_with_options(text, &ParseOptions::default())
+}
+
+/// Like `document_borrowed`, but parses the way the options say to. Tabs are always kept, whatever
+/// `ParseOptions::tab_width` is, since the lines are borrowed from the text.
+pub fn document_borrowed_with_options<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+    options: &ParseOptions,
+) -> IResult<&'a str, Document<'a>, E> {
+    let (rest, (doc, _)) = borrowed_with_spans(text, options)?;
+    Ok((rest, doc))
+}
+
+fn borrowed_with_spans<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    text: &'a str,
+    options: &ParseOptions,
+) -> IResult<&'a str, (Document<'a>, Vec<Span>), E> {
+    let body = without_bom(text);
+    // Documents may begin with a title page
+    let (rest, titlepage) = opt(titlepage)(body)?;
+    let (rest, blank) = opt(blank_line)(rest)?;
+    // The body starts after a blank line unless a title page runs straight into it.
+    let after_blank_line = blank.is_some() || rest.len() == body.len();
+    // Documents must then contain screenplay lines, in blocks separated by blank lines
+    let (rest, first) = opt(consumed(block_with(options, after_blank_line)))(rest)?;
+    let (rest, more) = match first {
+        Some(_) => many0(preceded(blank_line, consumed(block_with(options, true))))(rest)?,
+        None => (rest, Vec::new()),
+    };
+    let rest = without_trailing_blank_lines(rest);
+
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    for (source, block) in first.into_iter().chain(more) {
+        spans.extend(line_spans(text.offset(source), source).take(block.len()));
+        lines.extend(block);
+    }
+    let doc = Document {
+        lines,
+        titlepage: titlepage.unwrap_or_default(),
+    };
+    Ok((rest, (doc, spans)))
+}
+
+/// The text without a UTF-8 byte order mark at its start, which some editors write.
+pub(crate) fn without_bom(text: &str) -> &str {
+    text.strip_prefix('\u{feff}').unwrap_or(text)
+}
+
+/// Blank lines at the end of the text are parsed as nothing, rather than left unparsed.
+pub(crate) fn without_trailing_blank_lines(rest: &str) -> &str {
+    if rest.trim().is_empty() {
+        &rest[rest.len()..]
+    } else {
+        rest
+    }
+}
+
+/// Splits the text into lines, keeping their line endings, which are any of "\n", "\r\n" or "\r".
+pub(crate) fn lines_inclusive(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = match rest.find(['\n', '\r']) {
+            Some(i) if rest[i..].starts_with("\r\n") => i + 2,
+            Some(i) => i + 1,
+            None => rest.len(),
+        };
+        let (line, after) = rest.split_at(end);
+        rest = after;
+        Some(line)
+    })
+}
+
+/// Where each line of a block came from, given the block's text and where it starts. Every Line in
+/// a block was parsed from one line of the source text.
+fn line_spans(start: usize, source: &str) -> impl Iterator<Item = Span> + '_ {
+    lines_inclusive(source).scan(start, |start, source_line| {
+        let content = source_line.trim_end_matches(['\r', '\n']);
+        let span = Span {
+            start: *start,
+            end: *start + content.len(),
+        };
+        *start += source_line.len();
+        Some(span)
+    })
+}
+
+/// Parses the title page at the start of the text, if there is one, and returns where the body
+/// after it starts, and whether it starts after a blank line (see `block_with`).
+pub(crate) fn titlepage_prefix(text: &str) -> (data::TitlePage, usize, bool) {
+    let body = without_bom(text);
+    match pair(opt(titlepage::<(&str, ErrorKind)>), opt(blank_line))(body) {
+        Ok((rest, (titlepage, blank))) => (
+            titlepage.unwrap_or_default().into_owned(),
+            text.len() - rest.len(),
+            // Like in `borrowed_with_spans`.
+            blank.is_some() || rest.len() == body.len(),
+        ),
+        Err(_) => (data::TitlePage::default(), text.len() - body.len(), true),
+    }
+}
+
+/// One block of a document's body, parsed by `blocks_from`.
+pub(crate) struct ParsedBlock {
+    /// The block's text, including its final line ending.
+    pub span: Span,
+    pub lines: Vec<data::Line>,
+    /// Where each of the lines came from.
+    pub spans: Vec<Span>,
+}
+
+/// The blocks parsed by `blocks_from`, and why it stopped.
+pub(crate) struct Blocks {
+    pub blocks: Vec<ParsedBlock>,
+    /// Where parsing stopped: either the end of the last block, or where `resync` said to stop.
+    pub end: usize,
+    pub resynced: bool,
+}
+
+/// Parses blocks the way `document_with_spans` does, starting from a block at `start`. Before each
+/// block after the first, `resync` is given its offset and can stop parsing there, e.g. because
+/// the rest of the text was already parsed. Unlike `document_with_spans`, text which would fail the
+/// whole document (like a Parenthetical followed by more text) just stops parsing.
+/// `after_blank_line` says whether the first block comes after a blank line (see `block_with`).
+pub(crate) fn blocks_from(
+    text: &str,
+    start: usize,
+    after_blank_line: bool,
+    options: &ParseOptions,
+    mut resync: impl FnMut(usize) -> bool,
+) -> Blocks {
+    let mut blocks = Vec::new();
+    let mut end = start;
+    loop {
+        let mut rest = &text[end..];
+        if !blocks.is_empty() {
+            // Blocks are separated by a blank line.
+            match blank_line::<(&str, ErrorKind)>(rest) {
+                Ok((after, _)) => rest = after,
+                Err(_) => break,
+            }
+            if resync(text.len() - rest.len()) {
+                return Blocks {
+                    blocks,
+                    end: text.len() - rest.len(),
+                    resynced: true,
+                };
+            }
+        }
+        let after_blank_line = after_blank_line || !blocks.is_empty();
+        let mut parser = block_with::<(&str, ErrorKind)>(options, after_blank_line);
+        let (after, lines) = match parser(rest) {
+            Ok(parsed) => parsed,
+            Err(_) => break,
+        };
+        let block_start = text.len() - rest.len();
+        end = text.len() - after.len();
+        let source = &text[block_start..end];
+        let mut lines: Vec<_> = lines.into_iter().map(Line::into_owned).collect();
+        expand_tabs(&mut lines, options);
+        blocks.push(ParsedBlock {
+            span: Span {
+                start: block_start,
+                end,
+            },
+            spans: line_spans(block_start, source).take(lines.len()).collect(),
+            lines,
+        });
+    }
+    Blocks {
+        blocks,
+        end: text.len() - without_trailing_blank_lines(&text[end..]).len(),
+        resynced: false,
+    }
 }
 
 /// A block is either:
 /// - Speaker then dialogue
 /// - Speaker then parenthetical then dialogue
 /// - Some Fountain element which is not speaker, dialogue or parenthetical.
-fn block<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+pub(crate) fn block<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Vec<Line>, E> {
-    context(
-        "block",
-        alt((
-            map(transition_forced, singleton),
-            map(transition_to, singleton),
-            map(lyric, singleton),
-            map(scene, singleton),
-            spd_block,
-            sd_block,
-            map(action, singleton),
-        )),
-    )(i)
+) -> IResult<&'a str, Vec<Line<'a>>, E> {
+    block_with(&ParseOptions::default(), true)(i)
+}
+
+/// Like `block`, but parses the way the options say to. Whether the block comes after a blank line
+/// matters for some options: the first block after a title page may not.
+///
+/// Blocks are separated by one blank line, so any more blank lines before the block are kept as
+/// empty Action, since Fountain leaves the spacing between paragraphs up to the writer.
+pub(crate) fn block_with<'a, 'o, E: ParseError<&'a str> + ContextError<&'a str>>(
+    options: &'o ParseOptions,
+    after_blank_line: bool,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Line<'a>>, E> + 'o {
+    move |i| {
+        let mut blanks = Vec::new();
+        let mut rest = i;
+        // Most blocks start with text, so only look for blank lines when they might be there.
+        if rest.starts_with([' ', '\t', '\r', '\n']) {
+            while let Ok((after, blank)) = blank_line::<E>(rest) {
+                blanks.push(Line::Action(&blank[..0]));
+                rest = after;
+            }
+        }
+        let after_blank_line = after_blank_line || !blanks.is_empty();
+        let (rest, lines) = context(
+            "block",
+            alt((
+                map(action_forced, singleton),
+                map(transition_forced, singleton),
+                map(transition_to(options, after_blank_line), singleton),
+                map(lyric, singleton),
+                map(section, singleton),
+                map(scene(options), singleton),
+                dialogue_block(options),
+                map(action, singleton),
+            )),
+        )(rest)?;
+        if blanks.is_empty() {
+            Ok((rest, lines))
+        } else {
+            blanks.extend(lines);
+            Ok((rest, blanks))
+        }
+    }
 }
 
 /// Creates a vector containing only the given element.
@@ -224,28 +637,45 @@ fn singleton<T>(t: T) -> Vec<T> {
     vec![t]
 }
 
+/// A Speaker's block, unless its line is indented and would be another element without the
+/// indent, e.g. "  INT. MESS". The Speaker's name is trimmed, so it would be written back as that
+/// other element.
+fn dialogue_block<'a, 'o, E: ParseError<&'a str> + ContextError<&'a str>>(
+    options: &'o ParseOptions,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Line<'a>>, E> + 'o {
+    move |i| {
+        let trimmed = i.trim_start_matches([' ', '\t']);
+        let marked = trimmed.starts_with(['!', '@', '~', '.', '>', '#']);
+        if trimmed.len() < i.len() && (marked || options.is_scene_heading(trimmed)) {
+            return Err(nom::Err::Error(E::from_error_kind(i, ErrorKind::Verify)));
+        }
+        alt((spd_block, sd_block))(i)
+    }
+}
+
 // Speaker then dialogue
 fn sd_block<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Vec<Line>, E> {
-    let parser = context("sd block", pair(speaker, dialogue));
-    map(parser, |lines| vec![lines.0, lines.1])(i)
+) -> IResult<&'a str, Vec<Line<'a>>, E> {
+    let (rest, (s, d)) = context("sd block", pair(speaker, dialogue))(i)?;
+    let mut lines = vec![s, d];
+    let rest = more_dialogue::<E>(rest, &mut lines);
+    Ok((rest, lines))
 }
 
 // Speaker then parenthetical then dialogue
 fn spd_block<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, Vec<Line>, E> {
-    let parser = context("spd block", tuple((speaker, parenthetical, dialogue)));
-    map(parser, |lines| vec![lines.0, lines.1, lines.2])(i)
+) -> IResult<&'a str, Vec<Line<'a>>, E> {
+    let parser = tuple((speaker, parenthetical, dialogue));
+    let (rest, (s, p, d)) = context("spd block", parser)(i)?;
+    let mut lines = vec![s, p, d];
+    let rest = more_dialogue::<E>(rest, &mut lines);
+    Ok((rest, lines))
 }
 
-fn strip_suffix(suffix: &str, string: &str) -> String {
-    if let Some(stripped) = string.strip_suffix(suffix) {
-        stripped.to_owned()
-    } else {
-        string.to_owned()
-    }
+fn strip_suffix<'a>(suffix: &str, string: &'a str) -> &'a str {
+    string.strip_suffix(suffix).unwrap_or(string)
 }
 
 #[cfg(test)]
@@ -270,20 +700,30 @@ Pages:
 ";
         let output = titlepage::<VerboseError<&str>>(input_text);
         let expected = TitlePage {
-            title: Some("MUPPET TREASURE ISLAND".to_string()),
-            author: Some("Jerry Juhl".to_string()),
-            other: vec![("Pages".to_string(), "223".to_string())],
+            title: Some("MUPPET TREASURE ISLAND"),
+            author: Some("Jerry Juhl"),
+            other: vec![("Pages", "223")],
         };
         let expected = Ok(("", expected));
         assert_eq!(output, expected)
     }
 
+    #[test]
+    fn test_titlepage_include() {
+        let input_text = "{{include: act1.fountain}}\n";
+        let output = titlepage::<VerboseError<&str>>(input_text);
+        assert_eq!(output, Ok((input_text, TitlePage::default())));
+    }
+
     #[test]
     fn test_no_lower() {
         let input_text = "ADAM CHALMERS";
         let output = no_lower::<(&str, ErrorKind)>(input_text);
         let expected = Ok(("", "ADAM CHALMERS"));
         assert_eq!(output, expected);
+        let output = no_lower::<(&str, ErrorKind)>("JOSÉ éLODIE");
+        assert_eq!(output, Ok(("éLODIE", "JOSÉ ")));
+        assert!(no_lower::<(&str, ErrorKind)>("привет").is_err());
     }
 
     #[test]
@@ -293,18 +733,33 @@ Pages:
         let expected = Ok((
             "What really caused the fall of Rome?\n",
             Line::Speaker {
-                name: "MRS. THOMPSON".to_owned(),
+                name: "MRS. THOMPSON",
                 is_dual: false,
             },
         ));
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn test_forced_speaker() {
+        let output = speaker::<(&str, ErrorKind)>("@McCLANE ^\nYippee ki-yay.\n");
+        let expected = Ok((
+            "Yippee ki-yay.\n",
+            Line::Speaker {
+                name: "McCLANE",
+                is_dual: true,
+            },
+        ));
+        assert_eq!(output, expected);
+        assert!(speaker::<(&str, ErrorKind)>("@\nHi.\n").is_err());
+    }
+
     #[test]
     fn test_transition() {
         let input_text = "FADE TO:\n";
-        let output = transition_to::<VerboseError<&str>>(input_text);
-        let expected = Ok(("", Line::Transition("FADE TO:".to_owned())));
+        let output =
+            transition_to::<VerboseError<&str>>(&ParseOptions::default(), true)(input_text);
+        let expected = Ok(("", Line::Transition("FADE TO:")));
         assert_eq!(output, expected);
     }
 
@@ -312,39 +767,75 @@ Pages:
     fn test_forced_transition() {
         let input_text = "> Burn to white.\n";
         let output = transition_forced::<(&str, ErrorKind)>(input_text);
-        let expected = Ok(("", Line::Transition("Burn to white.".to_owned())));
+        let expected = Ok(("", Line::Transition("Burn to white.")));
         assert_eq!(output, expected);
     }
 
     #[test]
     fn test_int_scene() {
         let input_text = "INT. Michael's house\n";
-        let output = scene::<(&str, ErrorKind)>(input_text);
-        let expected = Ok(("", Line::Scene("INT. Michael's house".to_owned())));
+        let output = scene::<(&str, ErrorKind)>(&ParseOptions::default())(input_text);
+        let expected = Ok(("", Line::Scene("INT. Michael's house")));
         assert_eq!(output, expected);
     }
 
     #[test]
     fn test_ext_scene() {
         let input_text = "EXT. Michael's garden\n";
-        let output = scene::<(&str, ErrorKind)>(input_text);
-        let expected = Ok(("", Line::Scene("EXT. Michael's garden".to_owned())));
+        let output = scene::<(&str, ErrorKind)>(&ParseOptions::default())(input_text);
+        let expected = Ok(("", Line::Scene("EXT. Michael's garden")));
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn test_other_scenes() {
+        for heading in [
+            "INT./EXT. CAR - MOVING - DAY",
+            "INT/EXT. CAR",
+            "I/E CAR",
+            "EST. NEW YORK CITY",
+            "INT HOUSE - NIGHT #1A#",
+        ] {
+            let input_text = format!("{}\n", heading);
+            let output = scene::<(&str, ErrorKind)>(&ParseOptions::default())(&input_text);
+            assert_eq!(output, Ok(("", Line::Scene(heading))));
+        }
+        let output = scene::<(&str, ErrorKind)>(&ParseOptions::default())("INTERCUT WITH:\n");
+        assert!(output.is_err());
+    }
+
     #[test]
     fn test_lyric() {
         let input_text = "~For he is an Englishman!\n";
         let output = lyric::<(&str, ErrorKind)>(input_text);
-        let expected = Ok(("", Line::Lyric("For he is an Englishman!".to_owned())));
+        let expected = Ok(("", Line::Lyric("For he is an Englishman!")));
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn test_section() {
+        let input_text = "## Sequence 2: The Chase\n";
+        let output = section::<(&str, ErrorKind)>(input_text);
+        let expected = Line::Section {
+            title: "Sequence 2: The Chase",
+            depth: 2,
+        };
+        assert_eq!(output, Ok(("", expected)));
+    }
+
     #[test]
     fn test_action() {
         let input_text = "MICHAEL drops the plate.\n";
         let output = action::<VerboseError<&str>>(input_text);
-        let expected = Ok(("", Line::Action("MICHAEL drops the plate.".to_owned())));
+        let expected = Ok(("", Line::Action("MICHAEL drops the plate.")));
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_forced_action() {
+        let input_text = "!THE END\n";
+        let output = block::<VerboseError<&str>>(input_text);
+        let expected = Ok(("", vec![Line::Action("THE END")]));
         assert_eq!(output, expected);
     }
 
@@ -369,10 +860,10 @@ Pages:
         let output = sd_block::<(&str, ErrorKind)>(input_text);
         let expected = vec![
             Line::Speaker {
-                name: "LIBRARIAN".to_string(),
+                name: "LIBRARIAN",
                 is_dual: false,
             },
-            Line::Dialogue("Is anyone there?".to_string()),
+            Line::Dialogue("Is anyone there?"),
         ];
         assert_eq!(output, Ok(("", expected)));
     }
@@ -383,11 +874,11 @@ Pages:
         let output = spd_block::<(&str, ErrorKind)>(input_text);
         let expected = vec![
             Line::Speaker {
-                name: "LIBRARIAN".to_string(),
+                name: "LIBRARIAN",
                 is_dual: false,
             },
-            Line::Parenthetical("scared".to_string()),
-            Line::Dialogue("Is anyone there?".to_string()),
+            Line::Parenthetical("scared"),
+            Line::Dialogue("Is anyone there?"),
         ];
         assert_eq!(output, Ok(("", expected)));
     }
@@ -396,7 +887,7 @@ Pages:
     fn test_parenthetical() {
         let input_text = "(gasping)\n";
         let output = parenthetical::<(&str, ErrorKind)>(input_text);
-        let expected = Ok(("", Line::Parenthetical("gasping".to_owned())));
+        let expected = Ok(("", Line::Parenthetical("gasping")));
         assert_eq!(output, expected);
     }
 
@@ -414,8 +905,20 @@ Lights up on a table, totally empty except for a book.
         assert_eq!(output.lines.len(), 2);
     }
 
+    #[test]
+    fn test_document_with_spans() {
+        let input_text = "Title: Spans\r\n\r\nINT. MESS\r\n\r\nKANE\r\n(eating)\r\nFood!\r\n";
+        let (unparsed, (doc, spans)) =
+            document_with_spans::<VerboseError<&str>>(input_text).unwrap();
+        assert_eq!(unparsed, "");
+        assert_eq!(doc.lines.len(), spans.len());
+        let sources: Vec<_> = spans.iter().map(|s| &input_text[s.start..s.end]).collect();
+        assert_eq!(sources, vec!["INT. MESS", "KANE", "(eating)", "Food!"]);
+    }
+
     #[test]
     fn test_document_small() {
+        use crate::data::Line;
         let input_text = "INT. Public library
 
 Lights up on a table, totally empty except for a book.
@@ -474,6 +977,7 @@ First thing I'm going to do when we get back is eat some decent food.
 
     #[test]
     fn test_document() {
+        use crate::data::{Document, Line, TitlePage};
         let input_text = "\
 Title:
     Stephen King Interview
@@ -515,4 +1019,253 @@ My pleasure. Now, I'm sure you get asked this all the time, but, where do you ge
         dbg!(&unparsed);
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn test_options_transitions_need_blank_lines() {
+        use crate::data::Line;
+        let options = ParseOptions::spec();
+        let parse = |text| document_with_options::<(&str, ErrorKind)>(text, &options);
+
+        let (_, doc) = parse("INT. MESS\n\nCUT TO:\n\nEXT. SPACE\n").unwrap();
+        assert_eq!(doc.lines[1], Line::Transition("CUT TO:".to_owned()));
+        let (_, doc) = parse("INT. MESS\n\nCUT TO:\n").unwrap();
+        assert!(doc.lines[1].is_transition());
+        // Not followed by a blank line, so it's someone speaking.
+        let (_, doc) = parse("INT. MESS\n\nCUT TO:\nThe chase.\n").unwrap();
+        assert!(doc.lines[1].is_speaker());
+        // A title page runs straight into it, so it isn't preceded by one either.
+        let (_, doc) = parse("Title: Alien\nCUT TO:\n").unwrap();
+        assert_eq!(doc.lines, vec![Line::Action("CUT TO:".to_owned())]);
+        // By default, a transition is read whatever follows it.
+        let text = "INT. MESS\n\nCUT TO:\nThe chase.\n";
+        let (unparsed, doc) = document::<(&str, ErrorKind)>(text).unwrap();
+        assert!(doc.lines[1].is_transition());
+        assert_eq!(unparsed, "The chase.\n");
+    }
+
+    #[test]
+    fn test_options_tabs() {
+        use crate::data::Line;
+        let text = "\tHe waits.\n";
+        let (_, doc) = document::<(&str, ErrorKind)>(text).unwrap();
+        assert_eq!(doc.lines, vec![Line::Action("\tHe waits.".to_owned())]);
+        let options = ParseOptions::spec();
+        let (_, doc) = document_with_options::<(&str, ErrorKind)>(text, &options).unwrap();
+        assert_eq!(doc.lines, vec![Line::Action("    He waits.".to_owned())]);
+    }
+
+    #[test]
+    fn test_options_scene_prefixes() {
+        let mut options = ParseOptions::default();
+        options.scene_prefixes.push("INNEN".to_owned());
+        let output = scene::<(&str, ErrorKind)>(&options)("INNEN. KÜCHE - TAG\n");
+        assert_eq!(output, Ok(("", Line::Scene("INNEN. KÜCHE - TAG"))));
+        let output = scene::<(&str, ErrorKind)>(&ParseOptions::lenient())("ext./int. car\n");
+        assert_eq!(output, Ok(("", Line::Scene("ext./int. car"))));
+    }
+
+    #[test]
+    fn test_non_english_script() {
+        use crate::data::Line;
+        use crate::scenes::{SceneHeading, Vocabulary};
+        let text = "AUSSEN. HAFEN - NACHT\n\nJOSÉ\n¿Dónde está?\n\nÉLODIE ^\nIci.\n\nИВАН\nПривет.\n\nélodie attend.\n";
+        let options = ParseOptions::default().with_vocabulary(&Vocabulary::german());
+        let (unparsed, doc) = document_with_options::<(&str, ErrorKind)>(text, &options).unwrap();
+        assert_eq!(unparsed, "");
+        let speaker = |name: &str, is_dual| Line::Speaker {
+            name: name.to_owned(),
+            is_dual,
+        };
+        assert_eq!(
+            doc.lines,
+            vec![
+                Line::Scene("AUSSEN. HAFEN - NACHT".to_owned()),
+                speaker("JOSÉ", false),
+                Line::Dialogue("¿Dónde está?".to_owned()),
+                speaker("ÉLODIE", true),
+                Line::Dialogue("Ici.".to_owned()),
+                speaker("ИВАН", false),
+                Line::Dialogue("Привет.".to_owned()),
+                Line::Action("élodie attend.".to_owned()),
+            ]
+        );
+        let heading = SceneHeading::parse_with("AUSSEN. HAFEN - NACHT", &Vocabulary::german());
+        assert_eq!(heading.time, Some("NACHT".to_owned()));
+        // Lowercase letters outside of English mean it isn't a Speaker either.
+        let (_, doc) = document::<(&str, ErrorKind)>("ÉTÉ À àé\nOui.\n").unwrap();
+        assert_eq!(doc.lines[0], Line::Action("ÉTÉ À àé".to_owned()));
+        // Nor is a line in a script without case.
+        let (unparsed, doc) = document::<(&str, ErrorKind)>("彼は走る。\n彼女も走る。\n").unwrap();
+        assert_eq!(doc.lines, vec![Line::Action("彼は走る。".to_owned())]);
+        assert_eq!(unparsed, "彼女も走る。\n");
+        let (_, doc) = document::<(&str, ErrorKind)>("彼は走る。\n\n彼女も走る。\n").unwrap();
+        assert!(doc.lines.iter().all(|line| matches!(line, Line::Action(_))));
+    }
+
+    fn lines(text: &str) -> Vec<data::Line> {
+        match document::<(&str, ErrorKind)>(text) {
+            Ok(("", doc)) => doc.lines,
+            other => panic!("couldn't parse {:?}: {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn test_no_final_newline() {
+        use crate::data::Line;
+        let speaker = Line::Speaker {
+            name: "KANE".to_owned(),
+            is_dual: false,
+        };
+        assert_eq!(
+            lines("INT. MESS\n\nKANE\nFood!"),
+            vec![
+                Line::Scene("INT. MESS".to_owned()),
+                speaker,
+                Line::Dialogue("Food!".to_owned())
+            ]
+        );
+        assert_eq!(
+            lines("INT. MESS"),
+            vec![Line::Scene("INT. MESS".to_owned())]
+        );
+        assert_eq!(
+            lines("> THE END"),
+            vec![Line::Transition("THE END".to_owned())]
+        );
+        // A Speaker needs Dialogue after them.
+        assert_eq!(lines("KANE"), vec![Line::Action("KANE".to_owned())]);
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        use crate::data::Line;
+        let text = "\u{feff}Title: Alien\n\nINT. MESS\n";
+        let (_, (doc, spans)) = document_with_spans::<(&str, ErrorKind)>(text).unwrap();
+        assert_eq!(doc.titlepage.title, Some("Alien".to_owned()));
+        assert_eq!(&text[spans[0].start..spans[0].end], "INT. MESS");
+        assert_eq!(
+            lines("\u{feff}INT. MESS\n"),
+            vec![Line::Scene("INT. MESS".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let expected = lines("Title: Alien\n\nINT. MESS\n\nKANE\n(eating)\nFood!\n\nCUT TO:\n");
+        for ending in ["\r\n", "\r"] {
+            let text = format!(
+                "Title: Alien{0}{0}INT. MESS{0}{0}KANE{0}(eating){0}Food!{0}{0}CUT TO:{0}",
+                ending
+            );
+            assert_eq!(lines(&text), expected, "{:?}", text);
+        }
+        let mixed = "Title: Alien\r\n\nINT. MESS\r\rKANE\n(eating)\r\nFood!\r\nCUT TO:\n";
+        assert_eq!(lines(mixed)[..3], expected[..3]);
+        let text = "INT. MESS\r\rKANE\rFood!\r";
+        let (_, (_, spans)) = document_with_spans::<(&str, ErrorKind)>(text).unwrap();
+        let spanned: Vec<_> = spans.iter().map(|s| &text[s.start..s.end]).collect();
+        assert_eq!(spanned, vec!["INT. MESS", "KANE", "Food!"]);
+    }
+
+    #[test]
+    fn test_whitespace_only_lines() {
+        use crate::data::Line;
+        let action = |s: &str| Line::Action(s.to_owned());
+        // Spaces or tabs on a line between blocks still make it blank.
+        assert_eq!(
+            lines("The crew eats.\n \t\nKANE ^ \n(eating) \nFood!\n"),
+            vec![
+                action("The crew eats."),
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: true
+                },
+                Line::Parenthetical("eating".to_owned()),
+                Line::Dialogue("Food!".to_owned()),
+            ]
+        );
+        // More than one blank line is kept as empty Action, but blank lines at the end aren't.
+        assert_eq!(
+            lines("\n\nONE.\n\n\n   \nTwo.\n\n \n"),
+            vec![
+                action(""),
+                action("ONE."),
+                action(""),
+                action(""),
+                action("Two.")
+            ]
+        );
+        assert_eq!(lines("  \n\t\n"), vec![]);
+        // An all-caps line followed by a blank line isn't a Speaker.
+        assert_eq!(
+            lines("BANG!\n\nThe crew jumps.\n"),
+            vec![action("BANG!"), action("The crew jumps.")]
+        );
+    }
+
+    #[test]
+    fn test_speaker_whitespace() {
+        use crate::data::Line;
+        let text = "  KANE  \nFood!\n\n\tPARKER ^\t\nFood!\n\nCUT TO: \n";
+        assert_eq!(
+            lines(text),
+            vec![
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Speaker {
+                    name: "PARKER".to_owned(),
+                    is_dual: true
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Transition("CUT TO:".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indented_element_not_speaker() {
+        for text in [
+            "  INT. MESS\nFood!\n",
+            "\t!BANG\nHi.\n",
+            "  ~LA\nHi\n",
+            "  @KANE\nHi\n",
+        ] {
+            let (rest, doc) = document::<(&str, ErrorKind)>(text).unwrap();
+            assert!(!doc.lines[0].is_speaker(), "{:?}", text);
+            assert!(!rest.is_empty(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_dialogue_paragraphs() {
+        use crate::data::Line;
+        let dialogue = |s: &str| Line::Dialogue(s.to_owned());
+        let text = "DEALER\nTen.\nFour.\n(beat)\nDealer gets a seven.\n  \nHit or stand sir?\n\nMONKEY\nDude, I'm a monkey.\n";
+        assert_eq!(
+            lines(text),
+            vec![
+                Line::Speaker {
+                    name: "DEALER".to_owned(),
+                    is_dual: false
+                },
+                dialogue("Ten."),
+                dialogue("Four."),
+                Line::Parenthetical("beat".to_owned()),
+                dialogue("Dealer gets a seven."),
+                dialogue(""),
+                dialogue("Hit or stand sir?"),
+                Line::Speaker {
+                    name: "MONKEY".to_owned(),
+                    is_dual: false
+                },
+                dialogue("Dude, I'm a monkey."),
+            ]
+        );
+        // Any other whitespace ends the Dialogue.
+        let text = "DEALER\nTen.\n   \nFour.\n";
+        assert_eq!(lines(text)[2], Line::Action("Four.".to_owned()));
+    }
 }
@@ -0,0 +1,7 @@
+//! The parts of the standard prelude which come from `alloc`, so that modules can use them whether
+//! or not the crate is built with `std`.
+pub(crate) use alloc::borrow::ToOwned;
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec::Vec;
+pub(crate) use alloc::{format, vec};
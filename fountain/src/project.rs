@@ -0,0 +1,391 @@
+//! Projects split across several Fountain files, e.g. one file per act or per episode. Files are
+//! joined either by listing them in order, or by an include directive on a line of its own:
+//! ```text
+//! {{include: act1.fountain}}
+//! ```
+//! Included paths are relative to the file which includes them.
+use super::data::*;
+use super::options::ParseOptions;
+use super::source::{position, Position, Span};
+use nom::error::ErrorKind;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// One of the files a project was loaded from.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// Where some text of a project came from: a span within one of its files.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Source {
+    /// Index of the file within `Project::files`.
+    pub file: usize,
+    pub span: Span,
+}
+
+/// A document assembled from one or more files.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Project {
+    /// Every line of every file, with include directives replaced by the included lines. The
+    /// title page is the first file's.
+    pub document: Document,
+    /// Every file which was read, in the order they were first read.
+    pub files: Vec<SourceFile>,
+    /// Where each line of the document came from, in the same order as `Document::lines`.
+    pub sources: Vec<Source>,
+    /// Text which the parser stopped at, if it couldn't parse the whole of a file.
+    pub unparsed: Vec<Source>,
+}
+
+/// Why a project couldn't be loaded.
+#[derive(Debug)]
+pub enum ProjectError {
+    /// A file couldn't be read. If it was included, `included_from` is where the include was.
+    Read {
+        path: PathBuf,
+        included_from: Option<(PathBuf, Position)>,
+        error: io::Error,
+    },
+    /// A file includes itself, directly or indirectly. Lists each file in the cycle, starting and
+    /// ending with the same file.
+    Cycle(Vec<PathBuf>),
+    /// A file couldn't be parsed at all.
+    Parse { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProjectError::Read {
+                path,
+                included_from,
+                error,
+            } => {
+                write!(f, "Couldn't read {}", path.display())?;
+                if let Some((from, pos)) = included_from {
+                    write!(
+                        f,
+                        " (included from {}:{}:{})",
+                        from.display(),
+                        pos.line,
+                        pos.column
+                    )?;
+                }
+                write!(f, ": {}", error)
+            }
+            ProjectError::Cycle(paths) => {
+                let paths: Vec<_> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "Files include each other in a cycle: {}",
+                    paths.join(" -> ")
+                )
+            }
+            ProjectError::Parse { path, message } => {
+                write!(f, "Couldn't parse {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl Project {
+    /// Loads the given files from disk, in order, along with any files they include.
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Project, ProjectError> {
+        Project::open_with_options(paths, &ParseOptions::default())
+    }
+
+    /// Like `open`, but parses every file with the given options.
+    pub fn open_with_options<P: AsRef<Path>>(
+        paths: &[P],
+        options: &ParseOptions,
+    ) -> Result<Project, ProjectError> {
+        Project::load_with_options(paths, options, |path| std::fs::read_to_string(path))
+    }
+
+    /// Loads the given files, in order, along with any files they include. Files are read with
+    /// the given function, so they don't need to be on disk.
+    /// ```
+    /// use fountain::data::Line;
+    /// use fountain::project::Project;
+    /// use std::io;
+    ///
+    /// let project = Project::load(&["main.fountain"], |path| {
+    ///     match path.to_str().unwrap() {
+    ///         "main.fountain" => Ok("INT. MESS\n\n{{include: acts/one.fountain}}\n".to_owned()),
+    ///         "acts/one.fountain" => Ok("Kane eats.\n".to_owned()),
+    ///         _ => Err(io::ErrorKind::NotFound.into()),
+    ///     }
+    /// })
+    /// .unwrap();
+    /// assert_eq!(project.document.lines[1], Line::Action("Kane eats.".to_owned()));
+    /// let (path, position) = project.locate(1);
+    /// assert_eq!(path.to_str(), Some("acts/one.fountain"));
+    /// assert_eq!((position.line, position.column), (1, 1));
+    /// ```
+    pub fn load<P, F>(paths: &[P], read: F) -> Result<Project, ProjectError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> io::Result<String>,
+    {
+        Project::load_with_options(paths, &ParseOptions::default(), read)
+    }
+
+    /// Like `load`, but parses every file with the given options.
+    pub fn load_with_options<P, F>(
+        paths: &[P],
+        options: &ParseOptions,
+        mut read: F,
+    ) -> Result<Project, ProjectError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> io::Result<String>,
+    {
+        let mut project = Project {
+            document: Document::default(),
+            files: Vec::new(),
+            sources: Vec::new(),
+            unparsed: Vec::new(),
+        };
+        for path in paths {
+            let path = normalize(path.as_ref());
+            project.include(path, None, &mut Vec::new(), options, &mut read)?;
+        }
+        Ok(project)
+    }
+
+    /// The file and position which the line at the given index came from.
+    pub fn locate(&self, index: usize) -> (&Path, Position) {
+        self.position_of(self.sources[index])
+    }
+
+    /// The file and position where the given source starts.
+    pub fn position_of(&self, source: Source) -> (&Path, Position) {
+        let file = &self.files[source.file];
+        (&file.path, source.span.position(&file.text))
+    }
+
+    /// Reads a file and appends its lines, and those of any file it includes.
+    fn include<F>(
+        &mut self,
+        path: PathBuf,
+        included_from: Option<(PathBuf, Position)>,
+        stack: &mut Vec<PathBuf>,
+        options: &ParseOptions,
+        read: &mut F,
+    ) -> Result<(), ProjectError>
+    where
+        F: FnMut(&Path) -> io::Result<String>,
+    {
+        if let Some(i) = stack.iter().position(|p| *p == path) {
+            let mut cycle = stack[i..].to_vec();
+            cycle.push(path);
+            return Err(ProjectError::Cycle(cycle));
+        }
+        let text = match read(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                return Err(ProjectError::Read {
+                    path,
+                    included_from,
+                    error,
+                })
+            }
+        };
+        let (unparsed, doc, spans) =
+            match crate::parse_document_with_spans_and_options::<(&str, ErrorKind)>(&text, options)
+            {
+                Ok((unparsed, (doc, spans))) => (unparsed.len(), doc, spans),
+                Err(e) => {
+                    return Err(ProjectError::Parse {
+                        path,
+                        message: format!("{:?}", e),
+                    })
+                }
+            };
+        let file = self.files.len();
+        if unparsed > 0 {
+            let span = Span {
+                start: text.len() - unparsed,
+                end: text.len(),
+            };
+            self.unparsed.push(Source { file, span });
+        }
+        if self.files.is_empty() {
+            self.document.titlepage = doc.titlepage;
+        }
+        self.files.push(SourceFile {
+            path: path.clone(),
+            text,
+        });
+
+        stack.push(path);
+        for (line, span) in doc.lines.into_iter().zip(spans) {
+            match include_target(&line) {
+                Some(target) => {
+                    let from = stack.last().unwrap();
+                    let target = normalize(&from.parent().unwrap_or(Path::new("")).join(target));
+                    let at = (from.clone(), position(&self.files[file].text, span.start));
+                    self.include(target, Some(at), stack, options, read)?;
+                }
+                None => {
+                    self.document.lines.push(line);
+                    self.sources.push(Source { file, span });
+                }
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+}
+
+/// The path named by an include directive, if the line is one.
+fn include_target(line: &Line) -> Option<&str> {
+    match line {
+        Line::Action(text) => text
+            .trim()
+            .strip_prefix("{{")?
+            .strip_suffix("}}")?
+            .trim()
+            .strip_prefix("include:")
+            .map(str::trim)
+            .filter(|target| !target.is_empty()),
+        _ => None,
+    }
+}
+
+/// Removes "." and resolvable ".." components, so that the same file included by different
+/// relative paths is recognized as the same file.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                _ => out.push(".."),
+            },
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn load(paths: &[&str], files: &[(&str, &str)]) -> Result<Project, ProjectError> {
+        let files: BTreeMap<_, _> = files.iter().copied().collect();
+        Project::load(paths, |path| match files.get(path.to_str().unwrap()) {
+            Some(text) => Ok(text.to_string()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        })
+    }
+
+    #[test]
+    fn test_manifest_order() {
+        let project = load(
+            &["one.fountain", "two.fountain"],
+            &[
+                ("one.fountain", "Title: Alien\n\nINT. MESS\n"),
+                ("two.fountain", "Title: Ignored\n\nEXT. SPACE\n"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(project.document.titlepage.title.as_deref(), Some("Alien"));
+        assert_eq!(
+            project.document.lines,
+            vec![
+                Line::Scene("INT. MESS".to_owned()),
+                Line::Scene("EXT. SPACE".to_owned())
+            ]
+        );
+        let (path, position) = project.locate(1);
+        assert_eq!(path, Path::new("two.fountain"));
+        assert_eq!((position.line, position.column), (3, 1));
+    }
+
+    #[test]
+    fn test_nested_includes() {
+        let project = load(
+            &["main.fountain"],
+            &[
+                (
+                    "main.fountain",
+                    "{{include: acts/one.fountain}}\n\nTHE END\n",
+                ),
+                (
+                    "acts/one.fountain",
+                    "INT. MESS\n\n{{ include: ../scenes/two.fountain }}\n",
+                ),
+                ("scenes/two.fountain", "Kane eats.\n"),
+            ],
+        )
+        .unwrap();
+        let lines: Vec<_> = project.document.lines.iter().map(Line::text).collect();
+        assert_eq!(lines, vec!["INT. MESS", "Kane eats.", "THE END"]);
+        let files: Vec<_> = project.sources.iter().map(|s| s.file).collect();
+        assert_eq!(files, vec![1, 2, 0]);
+        assert_eq!(project.files[2].path, Path::new("scenes/two.fountain"));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = load(
+            &["main.fountain"],
+            &[("main.fountain", "INT. MESS\n\n{{include: gone.fountain}}\n")],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Couldn't read gone.fountain (included from main.fountain:3:1): entity not found"
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let err = load(
+            &["a.fountain"],
+            &[
+                ("a.fountain", "{{include: b.fountain}}\n"),
+                ("b.fountain", "{{include: ./a.fountain}}\n"),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Files include each other in a cycle: a.fountain -> b.fountain -> a.fountain"
+        );
+    }
+
+    #[test]
+    fn test_same_file_twice() {
+        let project = load(
+            &["a.fountain"],
+            &[
+                (
+                    "a.fountain",
+                    "{{include: b.fountain}}\n\n{{include: b.fountain}}\n",
+                ),
+                ("b.fountain", "INT. MESS\n"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(project.document.lines.len(), 2);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("../a/../../b")), Path::new("../../b"));
+    }
+}
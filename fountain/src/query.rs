@@ -0,0 +1,373 @@
+//! Composable queries over a document's lines, e.g. "every line KANE speaks in the MESS", so that
+//! tools don't each need their own loop over `Document::lines`.
+use super::characters::normalize_name;
+use super::data::*;
+use super::prelude::*;
+use super::scenes::{SceneHeading, Vocabulary};
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// The kinds of Line, for querying by kind.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum Kind {
+    Scene,
+    Action,
+    Dialogue,
+    Speaker,
+    Parenthetical,
+    Transition,
+    Lyric,
+    Section,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 8] = [
+        Kind::Scene,
+        Kind::Action,
+        Kind::Dialogue,
+        Kind::Speaker,
+        Kind::Parenthetical,
+        Kind::Transition,
+        Kind::Lyric,
+        Kind::Section,
+    ];
+
+    /// The kind of the given line.
+    pub fn of(line: &Line) -> Kind {
+        match line {
+            Line::Scene(_) => Kind::Scene,
+            Line::Action(_) => Kind::Action,
+            Line::Dialogue(_) => Kind::Dialogue,
+            Line::Speaker { .. } => Kind::Speaker,
+            Line::Parenthetical(_) => Kind::Parenthetical,
+            Line::Transition(_) => Kind::Transition,
+            Line::Lyric(_) => Kind::Lyric,
+            Line::Section { .. } => Kind::Section,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Scene => "scene",
+            Kind::Action => "action",
+            Kind::Dialogue => "dialogue",
+            Kind::Speaker => "speaker",
+            Kind::Parenthetical => "parenthetical",
+            Kind::Transition => "transition",
+            Kind::Lyric => "lyric",
+            Kind::Section => "section",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Kind::ALL
+            .iter()
+            .find(|kind| kind.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<_> = Kind::ALL.iter().map(|k| k.name()).collect();
+                format!(
+                    "Unknown kind '{}', expected one of: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A line matched by a query, with where it is in the document.
+/// This will impl Serialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct Element<'a> {
+    /// Index of the line within `Document::lines`.
+    pub index: usize,
+    pub line: &'a Line,
+    /// Number of the scene containing the line (see `Document::scenes`), or None if the line comes
+    /// before the first Scene Heading.
+    pub scene: Option<usize>,
+    /// The Scene Heading of the scene containing the line.
+    pub heading: Option<&'a str>,
+    /// The Speaker of a Speaker, Parenthetical or Dialogue line.
+    pub speaker: Option<&'a str>,
+    /// Titles of the Sections containing the line, outermost first.
+    pub sections: Vec<&'a str>,
+}
+
+type Filter<'a> = Box<dyn Fn(&Element<'a>) -> bool + 'a>;
+
+/// A query over a document's lines, built up by adding filters. Every filter must match.
+/// ```
+/// use fountain::query::Kind;
+/// use nom::error::VerboseError;
+///
+/// let doc = "\
+/// INT. MESS - NIGHT
+///
+/// KANE
+/// Food!
+///
+/// EXT. NOSTROMO - NIGHT
+///
+/// KANE
+/// Space!
+/// ";
+/// let doc = fountain::parse_document::<VerboseError<&str>>(doc).unwrap().1;
+/// let lines: Vec<_> = doc
+///     .query()
+///     .speaker("Kane")
+///     .kind(Kind::Dialogue)
+///     .location("mess")
+///     .iter()
+///     .map(|element| (element.index, element.line.text()))
+///     .collect();
+/// assert_eq!(lines, vec![(2, "Food!")]);
+/// ```
+pub struct Query<'a> {
+    doc: &'a Document,
+    words: Vocabulary,
+    filters: Vec<Filter<'a>>,
+}
+
+impl Document {
+    /// Starts a query which matches every line, to be narrowed down with filters.
+    pub fn query(&self) -> Query<'_> {
+        self.query_with(&Vocabulary::english())
+    }
+
+    /// Like `query`, but the location and time of day filters read Scene Headings written with
+    /// the given words.
+    pub fn query_with(&self, words: &Vocabulary) -> Query<'_> {
+        Query {
+            doc: self,
+            words: words.clone(),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Query<'a> {
+    /// Only match elements for which the predicate returns true.
+    pub fn filter<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&Element<'a>) -> bool + 'a,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Only match lines of the given kind.
+    pub fn kind(self, kind: Kind) -> Self {
+        self.filter(move |e| Kind::of(e.line) == kind)
+    }
+
+    /// Only match the Speaker, Parenthetical and Dialogue lines of the given character. Names are
+    /// normalized (see `normalize_name`) and matched case-insensitively.
+    pub fn speaker(self, name: &str) -> Self {
+        let name = normalize_name(name);
+        self.filter(move |e| {
+            e.speaker
+                .is_some_and(|s| normalize_name(s).eq_ignore_ascii_case(&name))
+        })
+    }
+
+    /// Only match lines in scenes whose location or sub-location contains the given text, ignoring
+    /// case.
+    pub fn location(self, location: &str) -> Self {
+        let location = location.to_uppercase();
+        let words = self.words.clone();
+        self.filter(move |e| {
+            e.heading.is_some_and(|h| {
+                let heading = SceneHeading::parse_with(h, &words);
+                heading.location.to_uppercase().contains(&location)
+                    || heading
+                        .sub_location
+                        .is_some_and(|s| s.to_uppercase().contains(&location))
+            })
+        })
+    }
+
+    /// Only match lines in scenes set at the given time of day, ignoring case.
+    pub fn time_of_day(self, time: &str) -> Self {
+        let time = time.to_owned();
+        let words = self.words.clone();
+        self.filter(move |e| {
+            e.heading
+                .and_then(|h| SceneHeading::parse_with(h, &words).time)
+                .is_some_and(|t| t.eq_ignore_ascii_case(&time))
+        })
+    }
+
+    /// Only match lines in the scene with the given number (see `Document::scenes`).
+    pub fn scene(self, number: usize) -> Self {
+        self.filter(move |e| e.scene == Some(number))
+    }
+
+    /// Only match lines within a Section with the given title (ignoring case), including the
+    /// Section line itself and any subsections.
+    pub fn section(self, title: &str) -> Self {
+        let title = title.to_owned();
+        self.filter(move |e| e.sections.iter().any(|s| s.eq_ignore_ascii_case(&title)))
+    }
+
+    /// Only match lines whose text contains the given text, ignoring case.
+    pub fn containing(self, text: &str) -> Self {
+        let text = text.to_lowercase();
+        self.filter(move |e| e.line.text().to_lowercase().contains(&text))
+    }
+
+    /// Every matching element, in document order.
+    pub fn iter(&self) -> impl Iterator<Item = Element<'a>> + '_ {
+        let mut scene = None;
+        let mut heading = None;
+        let mut speaker = None;
+        let mut sections: Vec<(usize, &'a str)> = Vec::new();
+        let doc: &'a Document = self.doc;
+        doc.lines
+            .iter()
+            .enumerate()
+            .map(move |(index, line)| {
+                match line {
+                    Line::Scene(h) => {
+                        scene = Some(scene.unwrap_or(0) + 1);
+                        heading = Some(h.as_str());
+                    }
+                    Line::Section { title, depth } => {
+                        sections.retain(|(d, _)| d < depth);
+                        sections.push((*depth, title.as_str()));
+                    }
+                    _ => {}
+                }
+                speaker = match line {
+                    Line::Speaker { name, .. } => Some(name.as_str()),
+                    Line::Parenthetical(_) | Line::Dialogue(_) => speaker,
+                    _ => None,
+                };
+                Element {
+                    index,
+                    line,
+                    scene,
+                    heading,
+                    speaker,
+                    sections: sections.iter().map(|(_, title)| *title).collect(),
+                }
+            })
+            .filter(move |element| self.filters.iter().all(|f| f(element)))
+    }
+
+    /// The matching lines as a new document, with the Speaker of any matching Parenthetical or
+    /// Dialogue included so that it's still valid Fountain.
+    pub fn to_document(&self) -> Document {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut last = None;
+        for element in self.iter() {
+            let continues_speech = last.is_some_and(|l| l + 1 == element.index);
+            if let (Some(name), false, Line::Parenthetical(_) | Line::Dialogue(_)) =
+                (element.speaker, continues_speech, element.line)
+            {
+                lines.push(Line::Speaker {
+                    name: name.to_owned(),
+                    is_dual: false,
+                });
+            }
+            lines.push(element.line.clone());
+            last = Some(element.index);
+        }
+        Document {
+            lines,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    const DOC: &str = "\
+# ACT ONE
+
+## ARRIVAL
+
+INT. MESS - NIGHT
+
+KANE
+Food!
+
+# ACT TWO
+
+EXT. NOSTROMO - DAY
+
+Silence.
+
+KANE
+(quietly)
+Space.
+";
+
+    fn parse(text: &str) -> Document {
+        crate::parse_document::<VerboseError<&str>>(text).unwrap().1
+    }
+
+    fn indices(query: Query) -> Vec<usize> {
+        query.iter().map(|e| e.index).collect()
+    }
+
+    #[test]
+    fn test_sections() {
+        let doc = parse(DOC);
+        assert_eq!(indices(doc.query().section("act one")), vec![0, 1, 2, 3, 4]);
+        assert_eq!(indices(doc.query().section("ARRIVAL")), vec![1, 2, 3, 4]);
+        assert_eq!(
+            indices(doc.query().section("ACT TWO")),
+            vec![5, 6, 7, 8, 9, 10]
+        );
+        let element = doc.query().iter().nth(2).unwrap();
+        assert_eq!(element.sections, vec!["ACT ONE", "ARRIVAL"]);
+    }
+
+    #[test]
+    fn test_scene_filters() {
+        let doc = parse(DOC);
+        assert_eq!(
+            indices(doc.query().time_of_day("day")),
+            vec![6, 7, 8, 9, 10]
+        );
+        assert_eq!(indices(doc.query().scene(1).kind(Kind::Speaker)), vec![3]);
+        assert_eq!(
+            indices(doc.query().location("NOSTROMO").containing("SILENCE")),
+            vec![7]
+        );
+    }
+
+    #[test]
+    fn test_to_document() {
+        let doc = parse(DOC);
+        let dialogue = doc.query().kind(Kind::Dialogue).to_document();
+        assert_eq!(dialogue.as_fountain(), "KANE\nFood!\n\nKANE\nSpace.\n");
+        let speech = doc.query().speaker("KANE").scene(2).to_document();
+        assert_eq!(speech.as_fountain(), "KANE\n(quietly)\nSpace.\n");
+    }
+
+    #[test]
+    fn test_kind_from_str() {
+        assert_eq!("lyric".parse(), Ok(Kind::Lyric));
+        assert!("song".parse::<Kind>().is_err());
+    }
+}
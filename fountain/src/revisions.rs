@@ -0,0 +1,198 @@
+//! Revision marks for production drafts. Once a script is locked, every changed line is marked
+//! with an asterisk in the right margin, and each revised page is labelled with the revision's
+//! color and date, so the cast and crew can swap in only the pages which changed.
+use super::data::*;
+use super::diff::{diff, LineEdit};
+use super::prelude::*;
+use alloc::collections::BTreeSet;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// The usual order of revision colors, starting from the original White draft.
+pub const REVISION_COLORS: [&str; 10] = [
+    "White",
+    "Blue",
+    "Pink",
+    "Yellow",
+    "Green",
+    "Goldenrod",
+    "Buff",
+    "Salmon",
+    "Cherry",
+    "Tan",
+];
+
+/// One round of revisions, e.g. the Blue pages from 2 March.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Revision {
+    /// e.g. "Blue" or "Blue Revision".
+    pub name: String,
+    pub date: Option<String>,
+}
+
+impl Revision {
+    /// Reads the revision from the title page's `Revision` key, and its date from the
+    /// `Revision Date` key if there is one. Keys are case-insensitive.
+    /// ```
+    /// use fountain::data::TitlePage;
+    /// use fountain::revisions::Revision;
+    /// let titlepage = TitlePage {
+    ///     other: vec![
+    ///         ("Revision".to_owned(), "Pink".to_owned()),
+    ///         ("Revision Date".to_owned(), "3/2/2021".to_owned()),
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    /// let revision = Revision::from_titlepage(&titlepage).unwrap();
+    /// assert_eq!(revision.name, "Pink");
+    /// assert_eq!(revision.date, Some("3/2/2021".to_owned()));
+    /// assert_eq!(revision.color(), Some("Pink"));
+    /// ```
+    pub fn from_titlepage(titlepage: &TitlePage) -> Option<Revision> {
+        let get = |key: &str| {
+            titlepage
+                .other
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.trim().to_owned())
+        };
+        get("Revision").map(|name| Revision {
+            name,
+            date: get("Revision Date"),
+        })
+    }
+
+    /// The revision's color, if its name starts with one of the standard REVISION_COLORS.
+    pub fn color(&self) -> Option<&'static str> {
+        let first_word = self.name.split_whitespace().next()?;
+        REVISION_COLORS
+            .iter()
+            .find(|color| color.eq_ignore_ascii_case(first_word))
+            .copied()
+    }
+}
+
+impl Document {
+    /// Indices into this document's lines which should carry a revision mark, because they were
+    /// added or changed since the previous draft. Where lines (or whole scenes) were only deleted,
+    /// the line after them is marked instead, so the deletion still shows up on the page.
+    /// ```
+    /// use nom::error::VerboseError;
+    ///
+    /// let old = "INT. MESS - NIGHT\n\nThe crew eats.\n\nKANE\nFood!\n";
+    /// let new = "INT. MESS - NIGHT\n\nThe crew eats.\n\nKANE\nDecent food!\n";
+    /// let old = fountain::parse_document::<VerboseError<&str>>(old).unwrap().1;
+    /// let new = fountain::parse_document::<VerboseError<&str>>(new).unwrap().1;
+    /// assert_eq!(new.revised_lines(&old).into_iter().collect::<Vec<_>>(), vec![3]);
+    /// ```
+    pub fn revised_lines(&self, previous: &Document) -> BTreeSet<usize> {
+        let mut revised = BTreeSet::new();
+        // Set when a whole scene was deleted, so the next line which survives gets marked.
+        let mut scene_deleted = false;
+        let mut last_line = None;
+        for scene in diff(previous, self).scenes {
+            let mut after_deletion = scene_deleted;
+            let mut last_new = None;
+            for edit in scene.edits {
+                match edit {
+                    LineEdit::Insert { new } => {
+                        revised.insert(new);
+                        after_deletion = false;
+                        last_new = Some(new);
+                    }
+                    LineEdit::Equal { new, .. } => {
+                        if after_deletion {
+                            revised.insert(new);
+                        }
+                        after_deletion = false;
+                        last_new = Some(new);
+                    }
+                    LineEdit::Delete { .. } => after_deletion = true,
+                }
+            }
+            match last_new {
+                Some(last) => {
+                    if after_deletion {
+                        revised.insert(last);
+                    }
+                    scene_deleted = false;
+                    last_line = Some(last);
+                }
+                None => scene_deleted = after_deletion,
+            }
+        }
+        // The script's last scenes were deleted, so there's no line after them to mark.
+        if let (true, Some(last)) = (scene_deleted, last_line) {
+            revised.insert(last);
+        }
+        revised
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    fn parse(text: &str) -> Document {
+        crate::parse_document::<VerboseError<&str>>(text).unwrap().1
+    }
+
+    #[test]
+    fn test_revised_lines_after_deletion() {
+        let old = parse("INT. MESS - NIGHT\n\nThe crew eats.\n\nThe cat eats.\n\nKANE\nFood!\n");
+        let new = parse("INT. MESS - NIGHT\n\nThe crew eats.\n\nKANE\nFood!\n");
+        let expected: BTreeSet<usize> = vec![2].into_iter().collect();
+        assert_eq!(new.revised_lines(&old), expected);
+
+        let new = parse("INT. MESS - NIGHT\n\nThe crew eats.\n\nThe cat eats.\n");
+        let expected: BTreeSet<usize> = vec![2].into_iter().collect();
+        assert_eq!(new.revised_lines(&old), expected);
+    }
+
+    #[test]
+    fn test_revised_lines_after_deleted_scene() {
+        let old = parse(
+            "INT. MESS - NIGHT\n\nThe crew eats.\n\nEXT. SPACE\n\nStars.\n\nINT. BRIDGE\n\nKane sits.\n",
+        );
+        // The line after the deleted scene is marked.
+        let new = parse("INT. MESS - NIGHT\n\nThe crew eats.\n\nINT. BRIDGE\n\nKane sits.\n");
+        let expected: BTreeSet<usize> = vec![2].into_iter().collect();
+        assert_eq!(new.revised_lines(&old), expected);
+        let html = new.as_revised_html(
+            &old,
+            &Revision {
+                name: "Blue".to_owned(),
+                date: None,
+            },
+        );
+        assert!(
+            html.contains("<p class='scene'>INT. BRIDGE<span class='revision-mark'>*</span></p>")
+        );
+        assert!(html.contains("<div class='page revision-blue'>"));
+
+        // With no line after it, the line before it is marked.
+        let new = parse("INT. MESS - NIGHT\n\nThe crew eats.\n\nEXT. SPACE\n\nStars.\n");
+        let expected: BTreeSet<usize> = vec![3].into_iter().collect();
+        assert_eq!(new.revised_lines(&old), expected);
+    }
+
+    #[test]
+    fn test_unrevised() {
+        let doc = parse("INT. MESS - NIGHT\n\nThe crew eats.\n");
+        assert!(doc.revised_lines(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_color() {
+        let revision = |name: &str| Revision {
+            name: name.to_owned(),
+            date: None,
+        };
+        assert_eq!(revision("BLUE REVISION").color(), Some("Blue"));
+        assert_eq!(revision("Second Draft").color(), None);
+        assert_eq!(revision("").color(), None);
+    }
+}
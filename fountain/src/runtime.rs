@@ -0,0 +1,173 @@
+//! Estimates how long a screenplay is, on the page and on screen.
+use super::data::*;
+use super::pages::{Eighths, Pagination};
+use super::prelude::*;
+use core::ops::Range;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// The usual rule of thumb: one page of screenplay is one minute of screen time.
+pub const SECONDS_PER_PAGE: u64 = 60;
+
+/// How long something in the screenplay is.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Length {
+    /// e.g. the Scene Heading or the act's Section title.
+    pub name: String,
+    pub length: Eighths,
+    /// Estimated screen time in seconds.
+    pub seconds: u64,
+}
+
+/// Estimates of a document's length.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Estimate {
+    /// Number of printed pages, not counting the title page.
+    pub pages: usize,
+    /// Length of the whole document.
+    pub length: Eighths,
+    /// Estimated screen time of the whole document, in seconds.
+    pub seconds: u64,
+    /// Every scene, in order.
+    pub scenes: Vec<Length>,
+    /// Every top-level Section (e.g. "# ACT ONE"), in order. Empty if the document has none.
+    pub acts: Vec<Length>,
+    /// Lines of printed text taken up by Speakers, Parentheticals and Dialogue.
+    pub dialogue_lines: usize,
+    /// Lines of printed text taken up by Action.
+    pub action_lines: usize,
+}
+
+impl Estimate {
+    /// The fraction of dialogue and action which is dialogue, from 0 to 1.
+    pub fn dialogue_ratio(&self) -> f64 {
+        let total = self.dialogue_lines + self.action_lines;
+        if total == 0 {
+            0.0
+        } else {
+            self.dialogue_lines as f64 / total as f64
+        }
+    }
+}
+
+impl Document {
+    /// Estimates the document's page count and screen time, both overall and per scene and act.
+    pub fn estimate(&self) -> Estimate {
+        let pagination = self.paginate();
+        let length = |name: &str, lines: Range<usize>| -> Length {
+            let length = pagination.eighths(lines);
+            Length {
+                name: name.to_owned(),
+                length,
+                seconds: seconds(length),
+            }
+        };
+        let scenes = self
+            .scenes()
+            .iter()
+            .map(|scene| length(scene.heading, scene.start..scene.end()))
+            .collect();
+        let act_starts: Vec<(usize, &str)> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| match line {
+                Line::Section { title, depth: 1 } => Some((i, title.as_str())),
+                _ => None,
+            })
+            .collect();
+        let acts = act_starts
+            .iter()
+            .enumerate()
+            .map(|(n, &(start, title))| {
+                let end = act_starts.get(n + 1).map_or(self.lines.len(), |a| a.0);
+                length(title, start..end)
+            })
+            .collect();
+        let total = pagination.eighths(0..self.lines.len());
+        Estimate {
+            pages: pagination.pages.len(),
+            length: total,
+            seconds: seconds(total),
+            scenes,
+            acts,
+            dialogue_lines: self.height_where(&pagination, |l| {
+                l.is_speaker() || l.is_parenthetical() || l.is_dialogue()
+            }),
+            action_lines: self.height_where(&pagination, Line::is_action),
+        }
+    }
+
+    /// Total printed height of every line matching the predicate.
+    fn height_where<P>(&self, pagination: &Pagination, predicate: P) -> usize
+    where
+        P: Fn(&Line) -> bool,
+    {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| predicate(line))
+            .map(|(i, _)| pagination.height(i..i + 1))
+            .sum()
+    }
+}
+
+fn seconds(length: Eighths) -> u64 {
+    u64::from(length.0) * SECONDS_PER_PAGE / 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let mut lines = vec![
+            Line::Section {
+                title: "ACT ONE".to_owned(),
+                depth: 1,
+            },
+            Line::Scene("INT. MESS".to_owned()),
+        ];
+        for _ in 0..27 {
+            lines.push(Line::Action("The crew eats.".to_owned()));
+        }
+        lines.push(Line::Section {
+            title: "ACT TWO".to_owned(),
+            depth: 1,
+        });
+        lines.push(Line::Scene("INT. BRIDGE".to_owned()));
+        lines.push(Line::Speaker {
+            name: "DALLAS".to_owned(),
+            is_dual: false,
+        });
+        lines.push(Line::Dialogue("Mother?".to_owned()));
+        let doc = Document {
+            lines,
+            ..Default::default()
+        };
+        let estimate = doc.estimate();
+        assert_eq!(estimate.pages, 2);
+        assert_eq!(estimate.scenes.len(), 2);
+        assert_eq!(estimate.scenes[0].name, "INT. MESS");
+        assert_eq!(estimate.scenes[0].length, Eighths(8));
+        assert_eq!(estimate.scenes[0].seconds, 60);
+        assert_eq!(estimate.acts.len(), 2);
+        assert_eq!(estimate.acts[0].name, "ACT ONE");
+        assert_eq!(estimate.acts[1].name, "ACT TWO");
+        assert_eq!(estimate.acts[1].length, Eighths(1));
+        assert_eq!(estimate.action_lines, 54);
+        assert_eq!(estimate.dialogue_lines, 3);
+        assert_eq!(estimate.length, Eighths(9));
+    }
+
+    #[test]
+    fn test_dialogue_ratio() {
+        let doc = Document::default();
+        assert_eq!(doc.estimate().dialogue_ratio(), 0.0);
+    }
+}
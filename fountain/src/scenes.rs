@@ -0,0 +1,530 @@
+//! Splits a Fountain document into its scenes, and parses Scene Headings into their parts.
+use super::data::*;
+use super::prelude::*;
+use super::utils::strip_prefix_ignore_case;
+use core::fmt;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// Times of day which may end a Scene Heading, e.g. the "NIGHT" in "EXT. NOSTROMO - NIGHT".
+pub const TIMES_OF_DAY: &[&str] = &[
+    "DAY",
+    "NIGHT",
+    "MORNING",
+    "AFTERNOON",
+    "EVENING",
+    "DAWN",
+    "DUSK",
+    "SUNRISE",
+    "SUNSET",
+    "NOON",
+    "MIDNIGHT",
+    "CONTINUOUS",
+    "LATER",
+    "MOMENTS LATER",
+    "SAME",
+    "SAME TIME",
+];
+
+/// Whether a scene takes place inside, outside, or both (e.g. in a moving car).
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum Setting {
+    /// INT.
+    Interior,
+    /// EXT. or EST. (establishing shot)
+    Exterior,
+    /// INT./EXT., INT/EXT or I/E
+    InteriorExterior,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Setting::Interior => write!(f, "INT"),
+            Setting::Exterior => write!(f, "EXT"),
+            Setting::InteriorExterior => write!(f, "INT/EXT"),
+        }
+    }
+}
+
+/// A Scene Heading split into its parts, e.g.
+/// "INT. NOSTROMO - MESS - NIGHT #12#" has setting Interior, location "NOSTROMO", sub-location
+/// "MESS", time "NIGHT" and scene number "12".
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct SceneHeading {
+    /// None if the heading doesn't start with INT, EXT etc, e.g. a forced heading like ".FLASHBACK"
+    pub setting: Option<Setting>,
+    pub location: String,
+    /// Everything between the location and the time of day, e.g. a room within a building.
+    pub sub_location: Option<String>,
+    /// One of `TIMES_OF_DAY` (or the `Vocabulary`'s times of day), if the heading ends with one.
+    pub time: Option<String>,
+    /// An explicit [scene number](https://fountain.io/syntax#section-slug) like #12A#
+    pub number: Option<String>,
+}
+
+impl SceneHeading {
+    /// Splits Scene Heading text into its parts. This never fails: anything which isn't
+    /// recognized as a setting, time of day or scene number becomes part of the location.
+    /// ```
+    /// use fountain::scenes::{SceneHeading, Setting};
+    /// let heading = SceneHeading::parse("INT./EXT. CAR - MOVING - NIGHT #4#");
+    /// assert_eq!(heading.setting, Some(Setting::InteriorExterior));
+    /// assert_eq!(heading.location, "CAR");
+    /// assert_eq!(heading.sub_location, Some("MOVING".to_owned()));
+    /// assert_eq!(heading.time, Some("NIGHT".to_owned()));
+    /// assert_eq!(heading.number, Some("4".to_owned()));
+    /// ```
+    pub fn parse(heading: &str) -> SceneHeading {
+        split_heading(heading, PREFIXES.iter().copied(), TIMES_OF_DAY)
+    }
+
+    /// Like `parse`, but for a Scene Heading written with the given words, e.g. in another
+    /// language.
+    /// ```
+    /// use fountain::scenes::{SceneHeading, Setting, Vocabulary};
+    /// let heading = SceneHeading::parse_with("AUSSEN. HAFEN - NACHT", &Vocabulary::german());
+    /// assert_eq!(heading.setting, Some(Setting::Exterior));
+    /// assert_eq!(heading.location, "HAFEN");
+    /// assert_eq!(heading.time, Some("NACHT".to_owned()));
+    /// ```
+    pub fn parse_with(heading: &str, words: &Vocabulary) -> SceneHeading {
+        let settings = words.settings.iter().map(|(p, s)| (p.as_str(), *s));
+        split_heading(heading, settings, &words.times_of_day)
+    }
+}
+
+/// The prefixes of English Scene Headings, for each setting.
+const PREFIXES: &[(&str, Setting)] = &[
+    ("INT./EXT", Setting::InteriorExterior),
+    ("INT/EXT", Setting::InteriorExterior),
+    ("I/E", Setting::InteriorExterior),
+    ("INT", Setting::Interior),
+    ("EXT", Setting::Exterior),
+    ("EST", Setting::Exterior),
+];
+
+/// The words which Scene Headings are written with, which differ between languages: the prefixes
+/// giving a scene's setting, and the times of day. Use them to parse Scene Headings with
+/// `SceneHeading::parse_with`, and to recognize them with `ParseOptions::with_vocabulary`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Vocabulary {
+    /// Prefixes which start a Scene Heading, e.g. ("INT", Interior), matched ignoring case.
+    pub settings: Vec<(String, Setting)>,
+    /// Times of day which may end a Scene Heading, matched ignoring case.
+    pub times_of_day: Vec<String>,
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Vocabulary::english()
+    }
+}
+
+impl Vocabulary {
+    fn new(settings: &[(&str, Setting)], times_of_day: &[&str]) -> Self {
+        Vocabulary {
+            settings: settings.iter().map(|(p, s)| (p.to_string(), *s)).collect(),
+            times_of_day: times_of_day.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    /// INT., EXT. etc, and `TIMES_OF_DAY`. This is what `SceneHeading::parse` uses.
+    pub fn english() -> Self {
+        Vocabulary::new(PREFIXES, TIMES_OF_DAY)
+    }
+
+    /// INNEN, AUSSEN (or AUßEN) and INNEN/AUSSEN, as well as the English abbreviations, which
+    /// German scripts often use too.
+    pub fn german() -> Self {
+        let mut words = Vocabulary::new(
+            &[
+                ("INNEN/AUSSEN", Setting::InteriorExterior),
+                ("INNEN/AUßEN", Setting::InteriorExterior),
+                ("INNEN", Setting::Interior),
+                ("AUSSEN", Setting::Exterior),
+                ("AUßEN", Setting::Exterior),
+            ],
+            &[
+                "TAG",
+                "NACHT",
+                "MORGEN",
+                "VORMITTAG",
+                "MITTAG",
+                "NACHMITTAG",
+                "ABEND",
+                "DÄMMERUNG",
+                "SONNENAUFGANG",
+                "SONNENUNTERGANG",
+                "MITTERNACHT",
+                "SPÄTER",
+                "KURZ DARAUF",
+                "GLEICHZEITIG",
+            ],
+        );
+        words.add_english_settings();
+        words
+    }
+
+    /// INT. and EXT., or INTÉRIEUR and EXTÉRIEUR written out, with French times of day.
+    pub fn french() -> Self {
+        let mut words = Vocabulary::new(
+            &[
+                ("INTÉRIEUR/EXTÉRIEUR", Setting::InteriorExterior),
+                ("INTÉRIEUR", Setting::Interior),
+                ("EXTÉRIEUR", Setting::Exterior),
+            ],
+            &[
+                "JOUR",
+                "NUIT",
+                "MATIN",
+                "MIDI",
+                "APRÈS-MIDI",
+                "SOIR",
+                "AUBE",
+                "CRÉPUSCULE",
+                "LEVER DU SOLEIL",
+                "COUCHER DU SOLEIL",
+                "MINUIT",
+                "PLUS TARD",
+                "SUITE",
+                "CONTINU",
+            ],
+        );
+        words.add_english_settings();
+        words
+    }
+
+    /// INT. and EXT., or INTERIOR and EXTERIOR written out, with Spanish times of day.
+    pub fn spanish() -> Self {
+        let mut words = Vocabulary::new(
+            &[
+                ("INTERIOR/EXTERIOR", Setting::InteriorExterior),
+                ("INTERIOR", Setting::Interior),
+                ("EXTERIOR", Setting::Exterior),
+            ],
+            &[
+                "DÍA",
+                "NOCHE",
+                "MAÑANA",
+                "MEDIODÍA",
+                "TARDE",
+                "AMANECER",
+                "ATARDECER",
+                "ANOCHECER",
+                "MEDIANOCHE",
+                "MÁS TARDE",
+                "CONTINUO",
+                "CONTINUACIÓN",
+                "MISMO MOMENTO",
+            ],
+        );
+        words.add_english_settings();
+        words
+    }
+
+    /// The vocabulary for a language, by its English name or its two-letter code, e.g. "german"
+    /// or "de", ignoring case.
+    /// ```
+    /// use fountain::scenes::Vocabulary;
+    /// assert_eq!(Vocabulary::for_language("German"), Some(Vocabulary::german()));
+    /// assert_eq!(Vocabulary::for_language("es"), Some(Vocabulary::spanish()));
+    /// assert_eq!(Vocabulary::for_language("klingon"), None);
+    /// ```
+    pub fn for_language(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "english" | "en" => Some(Vocabulary::english()),
+            "german" | "de" => Some(Vocabulary::german()),
+            "french" | "fr" => Some(Vocabulary::french()),
+            "spanish" | "es" => Some(Vocabulary::spanish()),
+            _ => None,
+        }
+    }
+
+    fn add_english_settings(&mut self) {
+        let english = PREFIXES.iter().map(|(p, s)| (p.to_string(), *s));
+        self.settings.extend(english);
+    }
+}
+
+/// Splits a Scene Heading into its parts, given the prefixes for each setting and the times of day
+/// it may be written with.
+fn split_heading<'w, T: AsRef<str>>(
+    heading: &str,
+    settings: impl Iterator<Item = (&'w str, Setting)>,
+    times_of_day: &[T],
+) -> SceneHeading {
+    let heading = heading.trim();
+    let number = scene_number(heading).map(str::to_owned);
+    let rest = without_scene_number(heading);
+    let (setting, rest) = split_setting(rest.strip_prefix('.').unwrap_or(rest), settings);
+    let mut parts: Vec<&str> = rest.split(" - ").map(str::trim).collect();
+    let is_time_of_day = |s: &str| {
+        times_of_day
+            .iter()
+            .any(|t| strip_prefix_ignore_case(s, t.as_ref()) == Some(""))
+    };
+    let time = match parts.last() {
+        Some(last) if parts.len() > 1 && is_time_of_day(last) => parts.pop(),
+        _ => None,
+    };
+    let location = parts.remove(0);
+    let sub_location = if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" - "))
+    };
+    SceneHeading {
+        setting,
+        location: location.to_owned(),
+        sub_location,
+        time: time.map(str::to_owned),
+        number,
+    }
+}
+
+/// Splits the setting's prefix, e.g. INT, from the rest of a Scene Heading. The longest prefix
+/// wins, so that "INT./EXT. CAR" isn't read as INT.
+fn split_setting<'h, 'w>(
+    heading: &'h str,
+    settings: impl Iterator<Item = (&'w str, Setting)>,
+) -> (Option<Setting>, &'h str) {
+    let mut found: Option<(usize, Setting, &str)> = None;
+    for (prefix, setting) in settings {
+        let rest = match strip_prefix_ignore_case(heading, prefix) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let ends =
+            rest.starts_with(". ") || rest.starts_with(' ') || rest == "." || rest.is_empty();
+        if ends && found.is_none_or(|(len, _, _)| prefix.len() > len) {
+            let rest = rest.strip_prefix('.').unwrap_or(rest);
+            found = Some((prefix.len(), setting, rest.trim_start()));
+        }
+    }
+    match found {
+        Some((_, setting, rest)) => (Some(setting), rest),
+        None => (None, heading),
+    }
+}
+
+/// The Scene Heading without its explicit scene number, if it has one.
+/// ```
+/// use fountain::scenes::without_scene_number;
+/// assert_eq!(without_scene_number("INT. MESS - NIGHT #12A#"), "INT. MESS - NIGHT");
+/// assert_eq!(without_scene_number("INT. MESS - NIGHT"), "INT. MESS - NIGHT");
+/// ```
+pub fn without_scene_number(heading: &str) -> &str {
+    let trimmed = heading.trim_end();
+    match trimmed.strip_suffix('#').and_then(|h| h.rfind('#')) {
+        Some(i) => trimmed[..i].trim_end(),
+        None => heading,
+    }
+}
+
+/// The Scene Heading's explicit scene number, if it has one. Unlike the heading's other parts,
+/// this doesn't depend on the words it's written with.
+/// ```
+/// use fountain::scenes::scene_number;
+/// assert_eq!(scene_number("INNEN. KÜCHE - NACHT #12A#"), Some("12A"));
+/// assert_eq!(scene_number("INT. MESS - NIGHT"), None);
+/// ```
+pub fn scene_number(heading: &str) -> Option<&str> {
+    let stripped = heading.trim_end().strip_suffix('#')?;
+    let open = stripped.rfind('#')?;
+    Some(stripped[open + 1..].trim())
+}
+
+/// A Scene is a Scene Heading plus every line up to (but not including) the next Scene Heading.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Scene<'a> {
+    /// Scenes are numbered from 1, in the order they appear in the document.
+    pub number: usize,
+    /// The Scene Heading text, e.g. "INT. MESS"
+    pub heading: &'a str,
+    /// Index of the Scene Heading within `Document::lines`.
+    pub start: usize,
+    /// All lines of the scene, starting with its Scene Heading.
+    pub lines: &'a [Line],
+}
+
+impl Scene<'_> {
+    /// Index one past the last line of this scene within `Document::lines`.
+    pub fn end(&self) -> usize {
+        self.start + self.lines.len()
+    }
+
+    /// The parts of this scene's heading.
+    pub fn parsed_heading(&self) -> SceneHeading {
+        SceneHeading::parse(self.heading)
+    }
+
+    /// The parts of this scene's heading, if it's written with the given words.
+    pub fn parsed_heading_with(&self, words: &Vocabulary) -> SceneHeading {
+        SceneHeading::parse_with(self.heading, words)
+    }
+}
+
+impl Document {
+    /// Returns every scene in the document. Lines before the first Scene Heading don't belong to
+    /// any scene, so they aren't included.
+    pub fn scenes(&self) -> Vec<Scene<'_>> {
+        let starts: Vec<usize> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.is_scene())
+            .map(|(i, _)| i)
+            .collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let end = starts.get(n + 1).copied().unwrap_or(self.lines.len());
+                let heading = match &self.lines[start] {
+                    Line::Scene(s) => s.as_str(),
+                    _ => unreachable!("scenes always start with a Scene Heading"),
+                };
+                Scene {
+                    number: n + 1,
+                    heading,
+                    start,
+                    lines: &self.lines[start..end],
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the number of the scene containing the line at the given index, or None if the
+    /// line comes before the first Scene Heading.
+    pub fn scene_number_at(&self, index: usize) -> Option<usize> {
+        let n = self.lines[..=index].iter().filter(|l| l.is_scene()).count();
+        if n == 0 {
+            None
+        } else {
+            Some(n)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(lines: Vec<Line>) -> Document {
+        Document {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scenes() {
+        let d = doc(vec![
+            Line::Action("Before any scene.".to_owned()),
+            Line::Scene("INT. MESS".to_owned()),
+            Line::Action("The crew eats.".to_owned()),
+            Line::Transition("CUT TO:".to_owned()),
+            Line::Scene("EXT. NOSTROMO".to_owned()),
+        ]);
+        let scenes = d.scenes();
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(scenes[0].number, 1);
+        assert_eq!(scenes[0].heading, "INT. MESS");
+        assert_eq!(scenes[0].start, 1);
+        assert_eq!(scenes[0].end(), 4);
+        assert_eq!(scenes[1].heading, "EXT. NOSTROMO");
+        assert_eq!(scenes[1].lines.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_heading() {
+        let heading = SceneHeading::parse("EXT. NOSTROMO - NIGHT");
+        assert_eq!(
+            heading,
+            SceneHeading {
+                setting: Some(Setting::Exterior),
+                location: "NOSTROMO".to_owned(),
+                sub_location: None,
+                time: Some("NIGHT".to_owned()),
+                number: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_heading_sub_location() {
+        let heading = SceneHeading::parse("INT. NOSTROMO - MESS - CONTINUOUS #12A#");
+        assert_eq!(heading.setting, Some(Setting::Interior));
+        assert_eq!(heading.location, "NOSTROMO");
+        assert_eq!(heading.sub_location, Some("MESS".to_owned()));
+        assert_eq!(heading.time, Some("CONTINUOUS".to_owned()));
+        assert_eq!(heading.number, Some("12A".to_owned()));
+    }
+
+    #[test]
+    fn test_parse_heading_no_time() {
+        let heading = SceneHeading::parse("INT. HOUSE - KITCHEN");
+        assert_eq!(heading.location, "HOUSE");
+        assert_eq!(heading.sub_location, Some("KITCHEN".to_owned()));
+        assert_eq!(heading.time, None);
+    }
+
+    #[test]
+    fn test_parse_heading_settings() {
+        let setting = |h| SceneHeading::parse(h).setting;
+        assert_eq!(setting("I/E CAR - DAY"), Some(Setting::InteriorExterior));
+        assert_eq!(setting("INT/EXT. CAR"), Some(Setting::InteriorExterior));
+        assert_eq!(setting("EST. CITY"), Some(Setting::Exterior));
+        assert_eq!(setting("int. office"), Some(Setting::Interior));
+        assert_eq!(setting(".FLASHBACK"), None);
+        assert_eq!(setting("INTERCUT"), None);
+    }
+
+    #[test]
+    fn test_parse_localized_heading() {
+        let german = Vocabulary::german();
+        let heading = SceneHeading::parse_with("INNEN/AUSSEN. AUTO - FAHREND - TAG", &german);
+        assert_eq!(heading.setting, Some(Setting::InteriorExterior));
+        assert_eq!(heading.location, "AUTO");
+        assert_eq!(heading.sub_location, Some("FAHREND".to_owned()));
+        assert_eq!(heading.time, Some("TAG".to_owned()));
+        let heading = SceneHeading::parse_with("INT. KÜCHE - DÄMMERUNG", &german);
+        assert_eq!(heading.setting, Some(Setting::Interior));
+        assert_eq!(heading.time, Some("DÄMMERUNG".to_owned()));
+
+        let heading =
+            SceneHeading::parse_with("Intérieur cuisine - nuit #3#", &Vocabulary::french());
+        assert_eq!(heading.setting, Some(Setting::Interior));
+        assert_eq!(heading.location, "cuisine");
+        assert_eq!(heading.time, Some("nuit".to_owned()));
+        assert_eq!(heading.number, Some("3".to_owned()));
+
+        let heading = SceneHeading::parse_with("EXT. PLAZA - DÍA", &Vocabulary::spanish());
+        assert_eq!(heading.setting, Some(Setting::Exterior));
+        assert_eq!(heading.time, Some("DÍA".to_owned()));
+        // English times of day aren't in other languages' vocabularies.
+        let heading = SceneHeading::parse_with("EXT. PLAZA - NIGHT", &Vocabulary::spanish());
+        assert_eq!(heading.time, None);
+        assert_eq!(
+            SceneHeading::parse_with("INT./EXT. CAR - DAY", &Vocabulary::english()),
+            SceneHeading::parse("INT./EXT. CAR - DAY")
+        );
+    }
+
+    #[test]
+    fn test_scene_number_at() {
+        let d = doc(vec![
+            Line::Action("Before any scene.".to_owned()),
+            Line::Scene("INT. MESS".to_owned()),
+            Line::Action("The crew eats.".to_owned()),
+        ]);
+        assert_eq!(d.scene_number_at(0), None);
+        assert_eq!(d.scene_number_at(1), Some(1));
+        assert_eq!(d.scene_number_at(2), Some(1));
+    }
+}
@@ -0,0 +1,195 @@
+//! A stable, versioned JSON format for parsed documents, for programs which store screenplays or
+//! pass them between services. Only available with the "use_serde" feature.
+//!
+//! A document is written as a `Versioned` object: the `Document` fields plus a `version`, e.g.
+//! ```json
+//! {
+//!   "version": 1,
+//!   "lines": [
+//!     { "Scene": "INT. MESS" },
+//!     { "Speaker": { "name": "KANE", "is_dual": false } },
+//!     { "Dialogue": "First thing I'm going to do when we get back is eat some decent food." }
+//!   ],
+//!   "titlepage": { "author": null, "title": "Alien", "other": [["Draft date", "1979"]] }
+//! }
+//! ```
+//! Each `Line` is an object with one key, the variant's name. The JSON Schema for the current
+//! version is in `schema/document.schema.json`, and can be generated with `json_schema` if the
+//! "json_schema" feature is specified.
+//!
+//! The version only changes when a reader of the old format could misread the new one, e.g. when
+//! a variant is added to `Line` or a field is renamed. Adding a field which older readers can
+//! ignore doesn't change the version, so readers shouldn't reject unknown fields.
+use crate::data::Document;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// The version of the JSON format written by this release.
+pub const VERSION: u32 = 1;
+
+/// A document in the versioned JSON format.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "json_schema", derive(schemars::JsonSchema))]
+pub struct Versioned {
+    /// The version of the format. Readers should check it before reading anything else.
+    pub version: u32,
+    #[serde(flatten)]
+    pub document: Document,
+}
+
+/// Just the version of a JSON document. Deserialize this first to check the version before
+/// deserializing the rest, which might not be in a shape this release understands.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SchemaError {
+    /// The document was written in a newer (or unknown) version of the format.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::UnsupportedVersion(v) => write!(
+                f,
+                "JSON format version {} isn't supported, only versions up to {}",
+                v, VERSION
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SchemaError {}
+
+impl Header {
+    /// Checks that this release can read the document.
+    pub fn check(self) -> Result<(), SchemaError> {
+        if (1..=VERSION).contains(&self.version) {
+            Ok(())
+        } else {
+            Err(SchemaError::UnsupportedVersion(self.version))
+        }
+    }
+}
+
+impl Versioned {
+    /// Returns the document, if this release can read its version.
+    pub fn into_document(self) -> Result<Document, SchemaError> {
+        Header {
+            version: self.version,
+        }
+        .check()?;
+        Ok(self.document)
+    }
+}
+
+impl From<Document> for Versioned {
+    fn from(document: Document) -> Self {
+        Versioned {
+            version: VERSION,
+            document,
+        }
+    }
+}
+
+/// The JSON Schema for `Versioned`, i.e. the current version of the format.
+#[cfg(feature = "json_schema")]
+pub fn json_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(Versioned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Line, TitlePage};
+
+    const V1_EXAMPLE: &str = include_str!("../schema/examples/v1.json");
+
+    /// Every kind of Line and title page field, so any change to how one is written shows up.
+    fn example() -> Document {
+        Document {
+            titlepage: TitlePage {
+                title: Some("Alien".to_owned()),
+                author: None,
+                other: vec![("Draft date".to_owned(), "1979".to_owned())],
+            },
+            lines: vec![
+                Line::Section {
+                    title: "ACT ONE".to_owned(),
+                    depth: 1,
+                },
+                Line::Scene("INT. MESS".to_owned()),
+                Line::Action("The entire crew is seated.".to_owned()),
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false,
+                },
+                Line::Parenthetical("(mouth full)".to_owned()),
+                Line::Dialogue("First thing I'm going to do is eat some decent food.".to_owned()),
+                Line::Speaker {
+                    name: "PARKER".to_owned(),
+                    is_dual: true,
+                },
+                Line::Lyric("Eat some decent food".to_owned()),
+                Line::Transition("CUT TO:".to_owned()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_writes_v1() {
+        let written = serde_json::to_value(Versioned::from(example())).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(V1_EXAMPLE).unwrap();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_reads_v1() {
+        let versioned: Versioned = serde_json::from_str(V1_EXAMPLE).unwrap();
+        assert_eq!(versioned.into_document(), Ok(example()));
+    }
+
+    #[test]
+    fn test_ignores_unknown_fields() {
+        let mut json: serde_json::Value = serde_json::from_str(V1_EXAMPLE).unwrap();
+        json["generator"] = "some other tool".into();
+        let versioned: Versioned = serde_json::from_value(json).unwrap();
+        assert_eq!(versioned.into_document(), Ok(example()));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let json = r#"{"version": 2, "script": []}"#;
+        let header: Header = serde_json::from_str(json).unwrap();
+        assert_eq!(header.check(), Err(SchemaError::UnsupportedVersion(2)));
+        let json = r#"{"version": 2, "lines": [], "titlepage": {"title": null, "author": null, "other": []}}"#;
+        let versioned: Versioned = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            versioned.into_document(),
+            Err(SchemaError::UnsupportedVersion(2))
+        );
+    }
+
+    /// The checked-in schema must match the types. Run the tests with FOUNTAIN_UPDATE_SCHEMA=1 to
+    /// regenerate it after changing them, and bump `VERSION` if the change isn't backwards
+    /// compatible.
+    #[test]
+    #[cfg(feature = "json_schema")]
+    fn test_schema_file_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/schema/document.schema.json");
+        let generated = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        if std::env::var_os("FOUNTAIN_UPDATE_SCHEMA").is_some() {
+            std::fs::write(path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "{} is out of date; rerun the tests with FOUNTAIN_UPDATE_SCHEMA=1",
+            path
+        );
+    }
+}
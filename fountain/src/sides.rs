@@ -0,0 +1,145 @@
+//! Sides: the scenes an actor needs for an audition or a day's shooting.
+use super::breakdown::SceneBreakdown;
+use super::characters::normalize_name;
+use super::data::*;
+use super::lock::Lock;
+use super::prelude::*;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// One scene of a document, and whether it features the character the sides are for.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct SideScene {
+    /// The scene's position in the document, counting from 1 (see `Document::scenes`).
+    pub scene: usize,
+    /// The scene number printed in the full script.
+    pub number: String,
+    /// The printed number of the page the scene starts on in the full script.
+    pub page: String,
+    /// True if the character speaks in the scene, or is named in its action.
+    pub featured: bool,
+}
+
+/// What to do with scenes which don't feature the character.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Unfeatured {
+    /// Leave them out.
+    Omit,
+    /// Print them struck through, so the actor can see what happens around their scenes.
+    CrossOut,
+}
+
+impl Document {
+    /// Every scene in the document, marking which ones feature the given character. Names are
+    /// normalized (see `normalize_name`) and matched case-insensitively.
+    /// ```
+    /// use nom::error::VerboseError;
+    ///
+    /// let doc = "\
+    /// INT. MESS - NIGHT
+    ///
+    /// KANE
+    /// Food!
+    ///
+    /// INT. BRIDGE - NIGHT
+    ///
+    /// Mother beeps.
+    ///
+    /// INT. INFIRMARY - NIGHT
+    ///
+    /// Ash examines Kane.
+    /// ";
+    /// let doc = fountain::parse_document::<VerboseError<&str>>(doc).unwrap().1;
+    /// let featured: Vec<_> = doc
+    ///     .sides("Kane")
+    ///     .into_iter()
+    ///     .filter(|s| s.featured)
+    ///     .map(|s| s.number)
+    ///     .collect();
+    /// assert_eq!(featured, vec!["1", "3"]);
+    /// ```
+    pub fn sides(&self, character: &str) -> Vec<SideScene> {
+        self.sides_from(&self.scene_breakdown(), character)
+    }
+
+    /// Like `sides`, but using the scene and page numbers of a locked draft.
+    pub fn locked_sides(&self, character: &str, lock: &Lock) -> Vec<SideScene> {
+        self.sides_from(&self.locked_scene_breakdown(lock), character)
+    }
+
+    fn sides_from(&self, breakdown: &[SceneBreakdown], character: &str) -> Vec<SideScene> {
+        let name = normalize_name(character);
+        let scenes = self.scenes();
+        breakdown
+            .iter()
+            .map(|b| {
+                let speaks = b.characters.iter().any(|c| c.eq_ignore_ascii_case(&name));
+                let named = scenes[b.scene - 1].lines.iter().any(|line| match line {
+                    Line::Action(text) => mentions(text, &name),
+                    _ => false,
+                });
+                SideScene {
+                    scene: b.scene,
+                    number: b.number.clone(),
+                    page: b.page.clone(),
+                    featured: speaks || named,
+                }
+            })
+            .collect()
+    }
+}
+
+/// True if the text contains the name as a whole word or words, ignoring case and punctuation.
+fn mentions(text: &str, name: &str) -> bool {
+    let words = |s: &str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_uppercase)
+            .collect()
+    };
+    let name = words(name);
+    !name.is_empty() && words(text).windows(name.len()).any(|w| w == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions() {
+        assert!(mentions("Ash examines Kane.", "KANE"));
+        assert!(mentions("Kane's helmet cracks.", "KANE"));
+        assert!(mentions("Mrs. Thompson waves.", "MRS. THOMPSON"));
+        assert!(!mentions("Kanega waves.", "KANE"));
+        assert!(!mentions("Anything.", ""));
+    }
+
+    #[test]
+    fn test_sides_dual_dialogue() {
+        let doc = Document {
+            lines: vec![
+                Line::Scene("INT. MESS".to_owned()),
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false,
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Speaker {
+                    name: "PARKER ^".to_owned(),
+                    is_dual: true,
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Scene("INT. BRIDGE".to_owned()),
+                Line::Action("Empty.".to_owned()),
+            ],
+            ..Default::default()
+        };
+        let sides = doc.sides("parker");
+        assert_eq!(sides.len(), 2);
+        assert!(sides[0].featured);
+        assert!(!sides[1].featured);
+        assert_eq!(sides[1].page, "1");
+    }
+}
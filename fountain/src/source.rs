@@ -0,0 +1,59 @@
+//! Locations in the Fountain text which a document was parsed from.
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// A byte range within the parsed text. For a Line, this covers the line's text but not its
+/// line ending.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Where this span starts, as a line and column of the text it came from.
+    pub fn position(&self, text: &str) -> Position {
+        position(text, self.start)
+    }
+}
+
+/// A line and column in the parsed text, both counted from 1. Columns count characters, not bytes.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts a byte offset into the text into a line and column.
+/// ```
+/// use fountain::source::{position, Position};
+/// let text = "INT. MESS\n\nKANE\n";
+/// assert_eq!(position(text, 11), Position { line: 3, column: 1 });
+/// ```
+pub fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let text = "ab\ncdé\nf";
+        assert_eq!(position(text, 0), Position { line: 1, column: 1 });
+        assert_eq!(position(text, 2), Position { line: 1, column: 3 });
+        assert_eq!(position(text, 3), Position { line: 2, column: 1 });
+        assert_eq!(position(text, 7), Position { line: 2, column: 4 });
+        assert_eq!(position(text, 8), Position { line: 3, column: 1 });
+    }
+}
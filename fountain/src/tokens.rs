@@ -0,0 +1,460 @@
+//! Splits Fountain text into classified spans for syntax highlighting. Unlike parsing, this never
+//! fails: text the parser can't understand is highlighted as action.
+use super::data::*;
+use super::options::ParseOptions;
+use super::prelude::*;
+use super::scenes::without_scene_number;
+use super::source::Span;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+/// What a token is, for choosing how to highlight it.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum TokenKind {
+    /// A title page key, e.g. "Title"
+    TitleKey,
+    /// A title page value, e.g. "Alien"
+    TitleValue,
+    /// A Scene Heading, not including its scene number
+    SceneHeading,
+    /// A scene number like "#12A#" at the end of a Scene Heading
+    SceneNumber,
+    /// A Speaker's name
+    Character,
+    /// A Speaker's extensions, e.g. "(V.O.)" or "(CONT'D)"
+    Extension,
+    /// The caret which marks Dual Dialogue
+    DualDialogue,
+    Parenthetical,
+    Dialogue,
+    Action,
+    Transition,
+    Lyric,
+    /// A Section, including its pound-signs
+    Section,
+    /// An emphasis marker: "*" (italics), "**" (bold), "***" (both) or "_" (underline). The text
+    /// between markers keeps the kind of the line it's in.
+    Emphasis,
+    /// A [note](https://fountain.io/syntax#section-notes) in double brackets, e.g. "[[FIX THIS]]"
+    Note,
+    /// A [boneyard](https://fountain.io/syntax#section-boneyard) comment between /* and */
+    Boneyard,
+    /// A character which forces the line to be read as some element, e.g. the "!" of forced action
+    /// or the "~" of a lyric.
+    Force,
+}
+
+/// A classified span of the text.
+/// This will impl Serialize and Deserialize if the feature "use_serde" is specified.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    fn new(kind: TokenKind, start: usize, end: usize) -> Self {
+        Token {
+            kind,
+            span: Span { start, end },
+        }
+    }
+}
+
+/// Splits the text into tokens. Tokens are in order and don't overlap. Whitespace between
+/// elements (like blank lines) isn't part of any token.
+/// ```
+/// use fountain::tokens::{tokenize, TokenKind};
+/// let text = "KANE (V.O.)\nI'm *very* hungry.\n";
+/// let kinds: Vec<_> = tokenize(text)
+///     .into_iter()
+///     .map(|t| (t.kind, &text[t.span.start..t.span.end]))
+///     .collect();
+/// assert_eq!(kinds, vec![
+///     (TokenKind::Character, "KANE"),
+///     (TokenKind::Extension, "(V.O.)"),
+///     (TokenKind::Dialogue, "I'm "),
+///     (TokenKind::Emphasis, "*"),
+///     (TokenKind::Dialogue, "very"),
+///     (TokenKind::Emphasis, "*"),
+///     (TokenKind::Dialogue, " hungry."),
+/// ]);
+/// ```
+pub fn tokenize(text: &str) -> Vec<Token> {
+    tokenize_with_options(text, &ParseOptions::default())
+}
+
+/// Like `tokenize`, but reads the text the way the options say to.
+pub fn tokenize_with_options(text: &str, options: &ParseOptions) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest_start = 0;
+    let parsed = crate::parse_document_with_spans_and_options::<(&str, _)>(text, options);
+    if let Ok((rest, (doc, spans))) = parsed {
+        let body_start = spans.first().map_or(text.len() - rest.len(), |s| s.start);
+        if doc.titlepage != TitlePage::default() {
+            title_page(text, body_start, &mut tokens);
+        }
+        for (line, span) in doc.lines.iter().zip(spans) {
+            line_tokens(text, line, span, &mut tokens);
+        }
+        rest_start = text.len() - rest.len();
+    }
+    // Anything the parser didn't understand is shown as action.
+    let mut start = rest_start;
+    for line in text[rest_start..].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if !content.trim().is_empty() {
+            tokens.push(Token::new(TokenKind::Action, start, start + content.len()));
+        }
+        start += line.len();
+    }
+
+    // Notes and boneyard can cover parts of lines, or several lines, so they're cut out of the
+    // tokens found so far. Emphasis is found in what's left.
+    let regions = comment_regions(text);
+    let mut out = Vec::new();
+    for token in tokens {
+        for piece in cut_out(token, &regions) {
+            if is_prose(piece.kind) {
+                emphasis(text, piece, &mut out);
+            } else {
+                out.push(piece);
+            }
+        }
+    }
+    out.extend(regions);
+    out.sort_by_key(|t| t.span.start);
+    out
+}
+
+/// Title page lines are "Key: value", or an indented value continuing the previous key.
+fn title_page(text: &str, end: usize, tokens: &mut Vec<Token>) {
+    let mut start = 0;
+    for line in text[..end].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let indented = content.starts_with(char::is_whitespace);
+        match content.find(':') {
+            Some(colon) if !indented => {
+                tokens.push(Token::new(TokenKind::TitleKey, start, start + colon));
+                push_trimmed(content, start, colon + 1, TokenKind::TitleValue, tokens);
+            }
+            _ => push_trimmed(content, start, 0, TokenKind::TitleValue, tokens),
+        }
+        start += line.len();
+    }
+}
+
+/// Pushes a token for `line[from..]` without its surrounding whitespace, if there's anything left.
+fn push_trimmed(
+    line: &str,
+    line_start: usize,
+    from: usize,
+    kind: TokenKind,
+    tokens: &mut Vec<Token>,
+) {
+    let part = &line[from..];
+    let trimmed = part.trim();
+    if !trimmed.is_empty() {
+        let start = line_start + from + (part.len() - part.trim_start().len());
+        tokens.push(Token::new(kind, start, start + trimmed.len()));
+    }
+}
+
+fn line_tokens(text: &str, line: &Line, span: Span, tokens: &mut Vec<Token>) {
+    let Span { start, end } = span;
+    let source = &text[start..end];
+    let mut push = |kind, from: usize, to: usize| {
+        if from < to {
+            tokens.push(Token::new(kind, start + from, start + to));
+        }
+    };
+    // Lines which can start with a character forcing them to be read as that element.
+    let forced = |kind, force: &str, push: &mut dyn FnMut(TokenKind, usize, usize)| {
+        if let Some(rest) = source.strip_prefix(force) {
+            push(TokenKind::Force, 0, force.len());
+            push(kind, source.len() - rest.trim_start().len(), source.len());
+        } else {
+            push(kind, 0, source.len());
+        }
+    };
+    match line {
+        Line::Scene(_) => {
+            let heading = without_scene_number(source).len();
+            push(TokenKind::SceneHeading, 0, heading);
+            let number = source[heading..].trim_start();
+            push(
+                TokenKind::SceneNumber,
+                source.len() - number.len(),
+                source.len(),
+            );
+        }
+        Line::Speaker { is_dual, .. } => {
+            let mut name_end = source.trim_end().len();
+            if *is_dual {
+                name_end = source[..name_end].trim_end_matches('^').trim_end().len();
+                let caret = source[name_end..]
+                    .find('^')
+                    .map_or(name_end, |i| name_end + i);
+                push(TokenKind::DualDialogue, caret, caret + 1);
+            }
+            let forced_start = source.starts_with('@') as usize;
+            match source[..name_end].find('(') {
+                Some(open) => {
+                    push(
+                        TokenKind::Character,
+                        forced_start,
+                        source[..open].trim_end().len(),
+                    );
+                    push(TokenKind::Extension, open, name_end);
+                }
+                None => push(TokenKind::Character, forced_start, name_end),
+            }
+            push(TokenKind::Force, 0, forced_start);
+        }
+        Line::Parenthetical(_) => push(TokenKind::Parenthetical, 0, source.len()),
+        Line::Dialogue(_) => push(TokenKind::Dialogue, 0, source.len()),
+        Line::Action(_) => forced(TokenKind::Action, "!", &mut push),
+        Line::Transition(_) => forced(TokenKind::Transition, ">", &mut push),
+        Line::Lyric(_) => forced(TokenKind::Lyric, "~", &mut push),
+        Line::Section { .. } => push(TokenKind::Section, 0, source.len()),
+    }
+}
+
+fn is_prose(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Action | TokenKind::Dialogue | TokenKind::Parenthetical | TokenKind::Lyric
+    )
+}
+
+/// Finds every boneyard and note. Unclosed boneyard lasts until the end of the text, and an
+/// unclosed note until the end of its line, so that text being typed is highlighted sensibly.
+fn comment_regions(text: &str) -> Vec<Token> {
+    let mut regions = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let (kind, close, unclosed_end) = if rest.starts_with("/*") {
+            (TokenKind::Boneyard, "*/", text.len())
+        } else if rest.starts_with("[[") {
+            let line_end = rest.find('\n').map_or(text.len(), |n| i + n);
+            (TokenKind::Note, "]]", line_end)
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        let end = rest[2..]
+            .find(close)
+            .map_or(unclosed_end, |n| i + 2 + n + close.len());
+        regions.push(Token::new(kind, i, end));
+        i = end;
+    }
+    regions
+}
+
+/// The parts of the token which aren't inside any of the (sorted) regions.
+fn cut_out(token: Token, regions: &[Token]) -> Vec<Token> {
+    let mut pieces = Vec::new();
+    let mut start = token.span.start;
+    for region in regions {
+        if region.span.end <= start || region.span.start >= token.span.end {
+            continue;
+        }
+        if region.span.start > start {
+            pieces.push(Token::new(token.kind, start, region.span.start));
+        }
+        start = region.span.end;
+    }
+    if start < token.span.end {
+        pieces.push(Token::new(token.kind, start, token.span.end));
+    }
+    pieces
+}
+
+/// Splits emphasis markers out of a token. Markers only count if they're closed by a matching
+/// marker later in the token, and aren't escaped with a backslash.
+fn emphasis(text: &str, token: Token, tokens: &mut Vec<Token>) {
+    let source = &text[token.span.start..token.span.end];
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    let mut markers = Vec::new();
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+        let marker = ["***", "**", "*", "_"]
+            .iter()
+            .find(|m| rest.starts_with(*m))
+            .filter(|_| !source[..i].ends_with('\\'));
+        match marker {
+            Some(marker) => {
+                // Markers hug the text they emphasize, so "2 * 3" has no emphasis.
+                let after = rest[marker.len()..].chars().next();
+                let before = source[..i].chars().next_back();
+                let can_open = after.is_some_and(|c| !c.is_whitespace());
+                let can_close = before.is_some_and(|c| !c.is_whitespace());
+                match open.iter().rposition(|(m, _)| m == marker) {
+                    Some(n) if can_close => {
+                        markers.push((open[n].1, marker.len()));
+                        markers.push((i, marker.len()));
+                        open.remove(n);
+                    }
+                    _ if can_open => open.push((marker, i)),
+                    _ => {}
+                }
+                i += marker.len();
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    markers.sort_unstable();
+    let mut start = 0;
+    for (at, len) in markers {
+        let at_abs = token.span.start + at;
+        if at > start {
+            tokens.push(Token::new(token.kind, token.span.start + start, at_abs));
+        }
+        tokens.push(Token::new(TokenKind::Emphasis, at_abs, at_abs + len));
+        start = at + len;
+    }
+    if start < source.len() {
+        tokens.push(Token::new(
+            token.kind,
+            token.span.start + start,
+            token.span.end,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    fn kinds(text: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(text)
+            .into_iter()
+            .map(|t| (t.kind, &text[t.span.start..t.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_title_page() {
+        let text = "Title:\n    Alien\nAuthor: Dan O'Bannon\n\nINT. MESS - NIGHT #1#\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (TitleKey, "Title"),
+                (TitleValue, "Alien"),
+                (TitleKey, "Author"),
+                (TitleValue, "Dan O'Bannon"),
+                (SceneHeading, "INT. MESS - NIGHT"),
+                (SceneNumber, "#1#"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_speakers() {
+        let text = "BRETT (O.S.) ^\n(quietly)\nRight.\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (Character, "BRETT"),
+                (Extension, "(O.S.)"),
+                (DualDialogue, "^"),
+                (Parenthetical, "(quietly)"),
+                (Dialogue, "Right."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forced_speaker() {
+        let text = "@McCLANE (V.O.)\nYippee ki-yay.\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (Force, "@"),
+                (Character, "McCLANE"),
+                (Extension, "(V.O.)"),
+                (Dialogue, "Yippee ki-yay."),
+            ]
+        );
+        let doc = crate::parse_document::<(&str, nom::error::ErrorKind)>(text)
+            .unwrap()
+            .1;
+        // The parsed name is what the Character and Extension tokens cover.
+        let tokens = tokenize(text);
+        let name = &text[tokens[1].span.start..tokens[2].span.end];
+        assert_eq!(
+            doc.lines[0],
+            Line::Speaker {
+                name: name.to_owned(),
+                is_dual: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_forced() {
+        let text = "!KANE EATS.\n\n> CUT TO BLACK\n\n~Eat some food\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (Force, "!"),
+                (Action, "KANE EATS."),
+                (Force, ">"),
+                (Transition, "CUT TO BLACK"),
+                (Force, "~"),
+                (Lyric, "Eat some food"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(
+            kinds("He **really** _runs_, 2 * 3 \\*not*\n"),
+            vec![
+                (Action, "He "),
+                (Emphasis, "**"),
+                (Action, "really"),
+                (Emphasis, "**"),
+                (Action, " "),
+                (Emphasis, "_"),
+                (Action, "runs"),
+                (Emphasis, "_"),
+                (Action, ", 2 * 3 \\*not*"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notes_and_boneyard() {
+        let text = "Kane eats. [[Too short?]]\n\n/* CUT\n\nINT. GALLEY */\n\nRipley [[looks\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (Action, "Kane eats. "),
+                (Note, "[[Too short?]]"),
+                (Boneyard, "/* CUT\n\nINT. GALLEY */"),
+                (Action, "Ripley "),
+                (Note, "[[looks"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_never_fails() {
+        // No final newline, and a Speaker whose dialogue hasn't been written yet.
+        assert_eq!(
+            kinds("INT. MESS - NIGHT\n\nKANE"),
+            vec![(SceneHeading, "INT. MESS - NIGHT"), (Action, "KANE")]
+        );
+        assert_eq!(kinds(""), vec![]);
+        assert_eq!(kinds("\n\n\n"), vec![]);
+    }
+}
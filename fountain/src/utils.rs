@@ -5,16 +5,23 @@ where
     (0..end).rev().find(|&i| predicate(&slice[i]))
 }
 
-pub fn position_after<T, P>(slice: &[T], start: usize, predicate: P) -> Option<usize>
-where
-    P: Fn(&T) -> bool,
-{
-    for (i, item) in slice.iter().enumerate().skip(start + 1) {
-        if predicate(item) {
-            return Some(i);
+/// Strips the prefix from the start of the text, ignoring case in any script, not only English.
+pub fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut len = 0;
+    let mut chars = text.chars();
+    for p in prefix.chars() {
+        let c = chars.next()?;
+        if c != p && !c.to_lowercase().eq(p.to_lowercase()) {
+            return None;
         }
+        len += c.len_utf8();
     }
-    None
+    Some(&text[len..])
+}
+
+/// Whether the two strings are equal, ignoring case in any script, not only English.
+pub fn eq_ignore_case(a: &str, b: &str) -> bool {
+    strip_prefix_ignore_case(a, b) == Some("")
 }
 
 #[cfg(test)]
@@ -28,8 +35,14 @@ mod tests {
     }
 
     #[test]
-    fn test_position_after() {
-        let v: Vec<_> = (0..10).collect();
-        assert_eq!(Some(8), position_after(&v, 6, |x| x % 2 == 0));
+    fn test_strip_prefix_ignore_case() {
+        assert_eq!(strip_prefix_ignore_case("int. mess", "INT"), Some(". mess"));
+        assert_eq!(
+            strip_prefix_ignore_case("außen. hof", "AUßEN"),
+            Some(". hof")
+        );
+        assert_eq!(strip_prefix_ignore_case("Día", "DÍA"), Some(""));
+        assert_eq!(strip_prefix_ignore_case("IN", "INT"), None);
+        assert_eq!(strip_prefix_ignore_case("EXT. SEA", "INT"), None);
     }
 }
@@ -0,0 +1,266 @@
+use super::data::*;
+use super::parse::{block, titlepage_item};
+use super::prelude::*;
+use nom::error::ErrorKind;
+
+impl Line {
+    /// Writes this line as Fountain markup, without a line ending. Action which would otherwise be
+    /// read as some other element (e.g. an all-caps line, which looks like a Speaker) is forced
+    /// with a leading "!", and a Speaker which wouldn't be read as one is forced with "@".
+    pub fn as_fountain(&self) -> String {
+        match self {
+            Line::Scene(s) => s.clone(),
+            // An extra blank line between blocks.
+            Line::Action(s) if s.is_empty() => String::new(),
+            Line::Action(s) => {
+                // Action is always followed by a blank line, or ends the document.
+                let text = format!("{}\n\n", s);
+                match block::<(&str, ErrorKind)>(&text) {
+                    Ok(("\n", lines)) if lines == [self.clone()] && !s.starts_with('!') => {
+                        s.clone()
+                    }
+                    _ => format!("!{}", s),
+                }
+            }
+            // Two spaces keep an empty line within the Dialogue.
+            Line::Dialogue(s) if s.is_empty() => "  ".to_owned(),
+            Line::Dialogue(s) => s.clone(),
+            Line::Speaker { name, is_dual } => {
+                let caret = if *is_dual { " ^" } else { "" };
+                let line = format!("{}{}", name, caret);
+                // A Speaker is always followed by Dialogue or a Parenthetical.
+                let text = format!("{}\nx\n\n", line);
+                let dialogue = Line::Dialogue("x".to_owned());
+                match block::<(&str, ErrorKind)>(&text) {
+                    Ok(("\n", lines)) if lines == [self.clone(), dialogue] => line,
+                    _ => format!("@{}", line),
+                }
+            }
+            Line::Parenthetical(s) => format!("({})", s),
+            Line::Transition(s) => {
+                if s.ends_with("TO:") && !s.chars().any(char::is_lowercase) {
+                    s.clone()
+                } else {
+                    format!("> {}", s)
+                }
+            }
+            Line::Lyric(s) => format!("~{}", s),
+            Line::Section { title, depth } => format!("{} {}", "#".repeat(*depth), title),
+        }
+    }
+}
+
+impl TitlePage {
+    /// Writes the title page as Fountain key-value pairs, one per line.
+    fn as_fountain(&self) -> String {
+        let title = self.title.iter().map(|v| ("Title", v.as_str()));
+        let author = self.author.iter().map(|v| ("Author", v.as_str()));
+        let other = self.other.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        title
+            .chain(author)
+            .chain(other)
+            .map(|(k, v)| format!("{}: {}\n", k, v))
+            .collect()
+    }
+}
+
+impl Document {
+    /// Writes the document as Fountain markup. Parsing the result gives back the same document.
+    /// ```
+    /// use nom::error::VerboseError;
+    ///
+    /// let text = "\
+    /// Title: Alien
+    ///
+    /// INT. MESS - NIGHT
+    ///
+    /// KANE
+    /// (coughing)
+    /// Food!
+    ///
+    /// CUT TO:
+    /// ";
+    /// let doc = fountain::parse_document::<VerboseError<&str>>(text).unwrap().1;
+    /// assert_eq!(doc.as_fountain(), text);
+    /// ```
+    pub fn as_fountain(&self) -> String {
+        let mut body = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            // Blocks are separated by a blank line, but a Speaker's lines aren't, and an empty
+            // Action is a blank line already.
+            let separated = match (i.checked_sub(1).map(|p| &self.lines[p]), line) {
+                (None, _) => false,
+                (Some(Line::Action(s)), _) if s.is_empty() => false,
+                (
+                    Some(Line::Speaker { .. } | Line::Parenthetical(_) | Line::Dialogue(_)),
+                    Line::Parenthetical(_) | Line::Dialogue(_),
+                ) => false,
+                _ => true,
+            };
+            if separated {
+                body.push('\n');
+            }
+            if i + 1 == self.lines.len() && line.text().is_empty() && line.is_action() {
+                // Blank lines at the end are skipped, so an empty Action there is forced.
+                body.push('!');
+            } else {
+                body.push_str(&line.as_fountain());
+            }
+            body.push('\n');
+        }
+        let mut out = self.titlepage.as_fountain();
+        // A blank line after the title page is skipped, and so is one at the very start. Without
+        // one, a first line like "Note: the door is open." would be read as the title page.
+        if !out.is_empty()
+            || self.lines.first() == Some(&Line::Action(String::new()))
+            || titlepage_item::<(&str, ErrorKind)>(&body).is_ok()
+        {
+            out.push('\n');
+        }
+        out.push_str(&body);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::VerboseError;
+
+    fn round_trip(doc: &Document) -> Document {
+        let text = doc.as_fountain();
+        match crate::parse_document::<VerboseError<&str>>(&text) {
+            Ok(("", parsed)) => parsed,
+            other => panic!("couldn't parse {:?}: {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let doc = Document {
+            lines: vec![
+                Line::Section {
+                    title: "ACT ONE".to_owned(),
+                    depth: 1,
+                },
+                Line::Scene("EXT. NOSTROMO - SPACE".to_owned()),
+                Line::Action("THE NOSTROMO".to_owned()),
+                Line::Action("!Bang".to_owned()),
+                Line::Action("> not a transition".to_owned()),
+                Line::Speaker {
+                    name: "KANE".to_owned(),
+                    is_dual: false,
+                },
+                Line::Dialogue("Food!".to_owned()),
+                Line::Speaker {
+                    name: "PARKER".to_owned(),
+                    is_dual: true,
+                },
+                Line::Parenthetical("mouth full".to_owned()),
+                Line::Dialogue("Food!".to_owned()),
+                Line::Lyric("Sleep, little ship".to_owned()),
+                Line::Transition("Fade out.".to_owned()),
+                Line::Transition("SMASH CUT TO:".to_owned()),
+            ],
+            titlepage: TitlePage {
+                title: Some("Alien".to_owned()),
+                author: Some("Dan O'Bannon".to_owned()),
+                other: vec![("Draft date".to_owned(), "1978".to_owned())],
+            },
+        };
+        assert_eq!(round_trip(&doc), doc);
+    }
+
+    #[test]
+    fn test_round_trip_blank_lines() {
+        let text = "\n\nThe crew eats.\n\n\n\nDEALER\nTen.\n(beat)\nFour.\n  \nHit?\n";
+        let doc = crate::parse_document::<VerboseError<&str>>(text).unwrap().1;
+        assert_eq!(doc.lines[0], Line::Action(String::new()));
+        assert_eq!(doc.as_fountain(), text);
+        assert_eq!(round_trip(&doc), doc);
+        let titled = Document {
+            titlepage: TitlePage {
+                title: Some("Alien".to_owned()),
+                ..Default::default()
+            },
+            ..doc
+        };
+        assert_eq!(round_trip(&titled), titled);
+    }
+
+    #[test]
+    fn test_round_trip_titlepage_like_action() {
+        let text = "\nNote: the door is open.\n\nINT. MESS - DAY\n\nThe crew eats.\n";
+        let doc = crate::parse_document::<VerboseError<&str>>(text).unwrap().1;
+        assert_eq!(doc.titlepage, TitlePage::default());
+        assert_eq!(
+            doc.lines[0],
+            Line::Action("Note: the door is open.".to_owned())
+        );
+        assert_eq!(doc.as_fountain(), text);
+        assert_eq!(round_trip(&doc), doc);
+    }
+
+    #[test]
+    fn test_round_trip_speakers() {
+        let text = "  KANE  \nFood!\n\n@McCLANE ^\nHi.\n\n@INT. MESS\nFood!\n\n@!BANG\nHi.\n";
+        let doc = crate::parse_document::<VerboseError<&str>>(text).unwrap().1;
+        assert_eq!(round_trip(&doc), doc);
+        let names = [
+            "INT. MESS",
+            "!BANG",
+            "~LA",
+            ".MESS",
+            "> OUT",
+            "# ACT",
+            "@HOME",
+            "McCLANE",
+        ];
+        for name in names {
+            let doc = Document {
+                lines: vec![
+                    Line::Speaker {
+                        name: name.to_owned(),
+                        is_dual: false,
+                    },
+                    Line::Dialogue("Hi.".to_owned()),
+                ],
+                ..Default::default()
+            };
+            assert_eq!(round_trip(&doc), doc, "{}", name);
+        }
+        let kane = Line::Speaker {
+            name: "KANE".to_owned(),
+            is_dual: true,
+        };
+        assert_eq!(kane.as_fountain(), "KANE ^");
+    }
+
+    #[test]
+    fn test_round_trip_empty_action_at_end() {
+        let text = "The crew eats.\n\n\n!\n";
+        let doc = crate::parse_document::<VerboseError<&str>>(text).unwrap().1;
+        assert_eq!(doc.lines.len(), 3);
+        assert_eq!(doc.as_fountain(), text);
+        assert_eq!(round_trip(&doc), doc);
+    }
+
+    #[test]
+    fn test_forced_action() {
+        // A Speaker needs Dialogue after them, so an all-caps line followed by a blank line is
+        // Action without forcing it.
+        assert_eq!(Line::Action("THE END".to_owned()).as_fountain(), "THE END");
+        assert_eq!(
+            Line::Action("FADE TO:".to_owned()).as_fountain(),
+            "!FADE TO:"
+        );
+        assert_eq!(
+            Line::Action("The end.".to_owned()).as_fountain(),
+            "The end."
+        );
+        assert_eq!(
+            Line::Action("INT. MESS".to_owned()).as_fountain(),
+            "!INT. MESS"
+        );
+    }
+}